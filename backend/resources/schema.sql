@@ -38,8 +38,10 @@ CREATE TABLE IF NOT EXISTS concordium_transactions (
        tx_hash BYTEA NOT NULL,
        -- Who this transaction was sent for. This is used for rate limiting. We
        -- use the account index so that it is per account and not per account
-       -- address, to account for aliases.
-       account_index INT8 NOT NULL,
+       -- address, to account for aliases. This is NULL for transactions sent
+       -- for several accounts, which are recorded in
+       -- concordium_transaction_sponsorees instead.
+       account_index INT8,
        -- The transaction serialized as a BlockItem, exactly as it can be
        -- submitted to the chain.
        tx BYTEA NOT NULL,
@@ -50,6 +52,26 @@ CREATE TABLE IF NOT EXISTS concordium_transactions (
        CONSTRAINT concordium_transactions_tx_hash_unique UNIQUE (tx_hash)
        );
 
+-- The account index used to be required. Batched transactions are sent for
+-- several accounts, so they have no single account index.
+ALTER TABLE concordium_transactions ALTER COLUMN account_index DROP NOT NULL;
+
+-- The accounts that transactions sent for several accounts at once, such as
+-- batched mints, were sent for. This is used for rate limiting together with
+-- the account index of concordium_transactions.
+CREATE TABLE IF NOT EXISTS concordium_transaction_sponsorees (
+       -- Hash of the transaction.
+       tx_hash BYTEA NOT NULL,
+       -- The account the transaction was (partly) sent for. An account appears
+       -- once for each of its requests in the transaction.
+       account_index INT8 NOT NULL,
+       -- Timestamp when the transaction was inserted.
+       insert_time timestamp with time zone NOT NULL
+       );
+
+-- Index for the benefit of rate limiting.
+CREATE INDEX IF NOT EXISTS concordium_transaction_sponsorees_account_time ON concordium_transaction_sponsorees (account_index, insert_time);
+
 -- |Events recorded from the registry contract on Concordium.
 CREATE TABLE IF NOT EXISTS concordium_events (
        id INT8 PRIMARY KEY UNIQUE,
@@ -58,17 +80,85 @@ CREATE TABLE IF NOT EXISTS concordium_events (
        tx_hash BYTEA NOT NULL,
        -- The type of event.
        event_type concordium_event_type NOT NULL,
-       -- Owner of the token that was minted or burned.
+       -- Canonical address of the owner of the token that was minted or burned.
        owner BYTEA NOT NULL,
        -- ID of the token that was minted or burned.
        token_id INT8 NOT NULL,
        -- Timestamp of the block in which the transaction resides.
-       block_time timestamp with time zone NOT NULL
+       block_time timestamp with time zone NOT NULL,
+       -- Platform of the token that was minted or burned, if known.
+       platform TEXT
        );
 
+-- The platform column was added after the table was first created.
+ALTER TABLE concordium_events ADD COLUMN IF NOT EXISTS platform TEXT;
+
+-- One-off migrations of existing data. Since this schema is run on every start
+-- of the service, each migration is recorded here once it has been applied.
+CREATE TABLE IF NOT EXISTS schema_migrations (
+       name TEXT PRIMARY KEY,
+       applied_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT now()
+);
+
+-- Owners are stored as canonical account addresses, i.e., with the last 3
+-- bytes set to zero, so that all aliases of an account share the same history.
+-- Events recorded before this was the case are canonicalised once.
+DO $$ BEGIN
+IF NOT EXISTS (SELECT 1 FROM schema_migrations WHERE name = 'canonical_event_owners') THEN
+   UPDATE concordium_events SET owner = overlay(owner PLACING '\x000000'::BYTEA FROM 30 FOR 3)
+          WHERE substring(owner FROM 30) <> '\x000000'::BYTEA;
+   INSERT INTO schema_migrations (name) VALUES ('canonical_event_owners');
+END IF;
+END $$;
+
 -- Index for the benefit of the history endpoint.
 CREATE INDEX IF NOT EXISTS concordium_events_account_timestamp ON concordium_events (owner, block_time);
 
+-- Index for looking up the tokens minted by a transaction.
+CREATE INDEX IF NOT EXISTS concordium_events_tx_hash ON concordium_events (tx_hash);
+
+-- Encrypted data of tokens that only store a commitment to their data in the
+-- contract. The data is addressed by its SHA-256 hash.
+CREATE TABLE IF NOT EXISTS proof_data (
+       -- SHA-256 hash of the data.
+       hash BYTEA PRIMARY KEY,
+       -- The encrypted data, exactly as it would otherwise be stored in the
+       -- contract.
+       data BYTEA NOT NULL,
+       -- Timestamp when the data was inserted.
+       insert_time timestamp with time zone NOT NULL,
+       -- Hash of the transaction that replaces the data of the token, if any.
+       -- The data is deleted once that transaction is finalized.
+       superseded_by BYTEA
+       );
+
+-- Index for deleting the data replaced by a transaction.
+CREATE INDEX IF NOT EXISTS proof_data_superseded_by ON proof_data (superseded_by);
+
+-- Challenges issued by the /v1/proof/challenge endpoint that were used by a
+-- transaction. Challenges are not stored when they are issued, since they are
+-- authenticated by the service. A challenge can only be used once, unless the
+-- transaction that used it failed.
+CREATE TABLE IF NOT EXISTS proof_challenges (
+       -- The challenge. This is the expiry followed by a MAC of the fields below.
+       challenge BYTEA PRIMARY KEY,
+       -- Canonical address of the account the challenge was issued for.
+       account BYTEA NOT NULL,
+       -- Address of the contract the challenge was issued for.
+       contract_index INT8 NOT NULL,
+       contract_subindex INT8 NOT NULL,
+       -- Platform and user data the challenge was issued for.
+       platform TEXT NOT NULL,
+       user_data TEXT NOT NULL,
+       -- Time after which the challenge can no longer be used.
+       expiry timestamp with time zone NOT NULL,
+       -- Timestamp when the challenge was used.
+       used_time timestamp with time zone,
+       -- Hash of the transaction that used the challenge. This is NULL while
+       -- the transaction is being sent.
+       tx_hash BYTEA
+       );
+
 -- A type with exactly one value used to make sure there can only be one row in
 -- the checkpoints table.
 DO $$ BEGIN
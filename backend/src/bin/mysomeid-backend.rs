@@ -3,10 +3,6 @@
 // - Resend stored transactions.
 // - Do we need to support the order for transaction list?
 // -
-use aes_gcm::{
-    aead::{Aead, KeyInit},
-    Aes256Gcm, Nonce,
-};
 pub use anyhow::Context;
 use axum::{
     body::Bytes,
@@ -19,17 +15,17 @@ use axum_prometheus::PrometheusMetricLayerBuilder;
 use backend::*;
 use clap::Parser;
 use concordium::{
-    base as concordium_base, cis2,
+    base as concordium_base,
     common::{self, VERSION_0},
     id::{
         constants::{ArCurve, AttributeKind},
-        id_proof_types::Statement,
-        types::AccountCredentialWithoutProofs,
+        id_proof_types::{AtomicStatement, Statement},
+        types::{AccountCredentialWithoutProofs, AttributeTag, IpIdentity, YearMonth},
     },
     smart_contracts::{common as concordium_std, common::AccountAddress},
     types::{
         queries::BlockInfo, AbsoluteBlockHeight, AccountIndex, BlockItemSummary, ContractAddress,
-        CryptographicParameters, Energy, WalletAccount,
+        CredentialRegistrationID, CryptographicParameters, Energy, WalletAccount,
     },
     v2::{self, BlockIdentifier, QueryError},
 };
@@ -40,12 +36,10 @@ use concordium_base::{
 };
 use concordium_rust_sdk as concordium;
 use futures::{StreamExt, TryStreamExt};
-use rand::Rng;
 use reqwest::Url;
 use sha2::Digest;
 use std::{
     collections::{BTreeMap, HashMap, HashSet},
-    io::Read,
     path::PathBuf,
     str::FromStr,
     sync::Arc,
@@ -97,6 +91,14 @@ struct Api {
         env = "MYSOMEID_BASE_URL"
     )]
     base_url: Url,
+    #[clap(
+        long = "proof-base-url",
+        default_value = "https://app.mysome.id/v",
+        help = "Base URL of the links to proofs that are returned when minting, recovering and \
+                rotating keys.",
+        env = "MYSOMEID_PROOF_BASE_URL"
+    )]
+    proof_base_url: Url,
     #[clap(
         long = "nft-image",
         help = "Path to the PNG nft image.",
@@ -136,6 +138,14 @@ struct Api {
         env = "MYSOMEID_PROMETHEUS_ADDRESS"
     )]
     prometheus_address: Option<std::net::SocketAddr>,
+    #[clap(
+        long = "summary-interval",
+        default_value = "60",
+        help = "Interval in seconds at which the summary of the contract is queried for the \
+                Prometheus gauges.",
+        env = "MYSOMEID_SUMMARY_INTERVAL"
+    )]
+    summary_interval: u64,
     #[clap(
         long = "max-pool-size",
         default_value = "16",
@@ -187,12 +197,101 @@ struct Api {
         default_value = "5"
     )]
     max_daily_mints: u32,
+    #[clap(
+        long = "max-key-derivations",
+        help = "Maximum number of keys derived from passphrases at a time. Requests that need to \
+                derive a key while as many are being derived are rejected.",
+        env = "MYSOMEID_MAX_KEY_DERIVATIONS",
+        default_value = "8"
+    )]
+    max_key_derivations: usize,
     #[clap(
         long = "allowed-domains",
         help = "Allowed domains for image requests.",
         env = "MYSOMEID_ALLOWED_DOMAINS"
     )]
     allowed_domains: Vec<String>,
+    #[clap(
+        long = "proof-validity-days",
+        help = "Number of days minted proofs are valid for. If not set proofs do not expire.",
+        env = "MYSOMEID_PROOF_VALIDITY_DAYS"
+    )]
+    proof_validity_days: Option<u32>,
+    #[clap(
+        long = "off-chain-data",
+        help = "Store only a commitment to the encrypted proof data in the contract, and keep the \
+                data itself in the database.",
+        env = "MYSOMEID_OFF_CHAIN_DATA"
+    )]
+    off_chain_data: bool,
+    #[clap(
+        long = "mint-batch-window",
+        default_value = "200",
+        help = "Time in milliseconds for which mint requests are collected before they are \
+                submitted in a single transaction.",
+        env = "MYSOMEID_MINT_BATCH_WINDOW"
+    )]
+    mint_batch_window: u64,
+    #[clap(
+        long = "max-mint-batch-size",
+        default_value = "10",
+        help = "Maximum number of mint requests submitted in a single transaction.",
+        env = "MYSOMEID_MAX_MINT_BATCH_SIZE"
+    )]
+    max_mint_batch_size: usize,
+    #[clap(
+        long = "mint-request-timeout",
+        default_value = "30000",
+        help = "Timeout in milliseconds for mint requests. Requests that are not submitted within \
+                the timeout are rejected, and submitted requests wait for the minted token until \
+                the timeout.",
+        env = "MYSOMEID_MINT_REQUEST_TIMEOUT"
+    )]
+    mint_request_timeout: u64,
+    #[clap(
+        long = "challenge-validity",
+        default_value = "600",
+        help = "Number of seconds for which issued challenges can be used.",
+        env = "MYSOMEID_CHALLENGE_VALIDITY"
+    )]
+    challenge_validity: u32,
+    #[clap(
+        long = "challenge-secret",
+        help = "Path to a file with the hex-encoded secret key of at least 32 bytes used to \
+                authenticate issued challenges.",
+        env = "MYSOMEID_CHALLENGE_SECRET"
+    )]
+    challenge_secret: std::path::PathBuf,
+    #[clap(
+        long = "legacy-challenge-cutover",
+        help = "Time (RFC 3339) from which the service issued challenges. Tokens minted before it \
+                may have a proof for the hash of their platform and user data instead. If not \
+                given, only tokens minted before the contract recorded the time of minting may.",
+        env = "MYSOMEID_LEGACY_CHALLENGE_CUTOVER"
+    )]
+    legacy_challenge_cutover: Option<chrono::DateTime<chrono::Utc>>,
+    #[clap(
+        long = "statements",
+        help = "Path to a JSON file with the statements to prove for each platform. Platforms \
+                that are not in the file use the statement revealing the first and last name.",
+        env = "MYSOMEID_STATEMENTS"
+    )]
+    statements: Option<PathBuf>,
+    #[clap(
+        long = "key-id",
+        default_value = "0",
+        help = "Identifier of the key of the service, recorded in the data of minted tokens.",
+        env = "MYSOMEID_KEY_ID"
+    )]
+    key_id: u32,
+    #[clap(
+        long = "verifier-keys",
+        help = "Hex-encoded X25519 public keys of the designated verifiers. The data key of \
+                tokens minted with designated verifiers is also encrypted for these keys.",
+        env = "MYSOMEID_VERIFIER_KEYS",
+        value_delimiter = ','
+    )]
+    verifier_keys: Vec<payload::VerifierKey>,
 }
 
 #[derive(Debug, Clone)]
@@ -201,7 +300,8 @@ struct ServiceState {
     pub concordium_client:     concordium::v2::Client,
     pub crypto_params:         Arc<CryptographicParameters>,
     pub base_url:              Arc<Url>,
-    pub statement:             Arc<Statement<ArCurve, AttributeKind>>,
+    pub proof_base_url:        Arc<Url>,
+    pub statements:            Arc<Statements>,
     pub nft_image:             Bytes,
     pub nft_image_revoked:     Bytes,
     pub signer:                Arc<WalletAccount>,
@@ -211,8 +311,18 @@ struct ServiceState {
     // that we don't skip nonces in case of other failures, such as failure to send a transaction.
     pub nonce_counter:         Arc<tokio::sync::Mutex<concordium::types::Nonce>>,
     pub tx_sender:             tokio::sync::mpsc::Sender<TxChannelData>,
+    pub mint_sender:           tokio::sync::mpsc::Sender<MintRequest>,
+    pub mint_request_timeout:  std::time::Duration,
     pub read_db:               db::ReadDatabase,
     pub max_daily_mints:       u32,
+    pub key_derivations:       Arc<tokio::sync::Semaphore>,
+    pub proof_validity_days:   Option<u32>,
+    pub off_chain_data:        bool,
+    pub challenge_validity:    chrono::Duration,
+    pub challenge_secret:      Arc<Vec<u8>>,
+    pub challenge_cutover:     Option<chrono::DateTime<chrono::Utc>>,
+    pub key_id:                u32,
+    pub verifier_keys:         Arc<Vec<payload::VerifierKey>>,
     pub allowed_domains:       Arc<Vec<String>>,
     pub allowed_substitutions: Arc<HashMap<&'static str, Vec<&'static str>>>,
     pub allowed_titles:        Arc<HashSet<&'static str>>,
@@ -275,6 +385,14 @@ async fn main() -> anyhow::Result<()> {
 
     let signer =
         WalletAccount::from_json_file(app.concordium_wallet).context("Unable to read keys.")?;
+    let challenge_secret = std::fs::read_to_string(app.challenge_secret)
+        .context("Unable to read the challenge secret.")?;
+    let challenge_secret =
+        hex::decode(challenge_secret.trim()).context("The challenge secret is not hex-encoded.")?;
+    anyhow::ensure!(
+        challenge_secret.len() >= 32,
+        "The challenge secret must be at least 32 bytes."
+    );
 
     let sender_account = signer.address;
 
@@ -297,17 +415,15 @@ async fn main() -> anyhow::Result<()> {
         .context("Unable to build network client.")?;
 
     let (sender, receiver) = tokio::sync::mpsc::channel(10);
+    let (mint_sender, mint_receiver) = tokio::sync::mpsc::channel(100);
 
     let state = ServiceState {
         client,
         concordium_client: concordium_client.clone(),
         crypto_params: Arc::new(global_context),
         base_url: Arc::new(app.base_url),
-        statement: Arc::new(
-            Statement::new()
-                .reveal_attribute("firstName".parse().unwrap())
-                .reveal_attribute("lastName".parse().unwrap()),
-        ),
+        proof_base_url: Arc::new(app.proof_base_url),
+        statements: Arc::new(Statements::load(app.statements.as_deref())?),
         nft_image: Bytes::from(
             std::fs::read(app.nft_image).context("Unable to read the NFT image")?,
         ),
@@ -318,13 +434,35 @@ async fn main() -> anyhow::Result<()> {
         contract_address: app.concordium_contract,
         nonce_counter: Arc::new(tokio::sync::Mutex::new(starting_nonce.nonce)),
         tx_sender: sender,
+        mint_sender,
+        mint_request_timeout: std::time::Duration::from_millis(app.mint_request_timeout),
         read_db,
         max_daily_mints: app.max_daily_mints,
+        key_derivations: Arc::new(tokio::sync::Semaphore::new(app.max_key_derivations)),
+        proof_validity_days: app.proof_validity_days,
+        off_chain_data: app.off_chain_data,
+        challenge_validity: chrono::Duration::seconds(app.challenge_validity.into()),
+        challenge_secret: Arc::new(challenge_secret),
+        challenge_cutover: app.legacy_challenge_cutover,
+        key_id: app.key_id,
+        verifier_keys: Arc::new(app.verifier_keys),
         allowed_domains: Arc::new(app.allowed_domains),
         allowed_substitutions: Arc::new(get_allowed_substitutions()),
         allowed_titles: Arc::new(get_allowed_titles()),
     };
 
+    let mint_batcher = MintBatcher {
+        signer:            state.signer.clone(),
+        nonce_counter:     state.nonce_counter.clone(),
+        tx_sender:         state.tx_sender.clone(),
+        read_db:           state.read_db.clone(),
+        max_daily_mints:   app.max_daily_mints,
+        concordium_client: concordium_client.clone(),
+        contract_address:  app.concordium_contract,
+        window:            std::time::Duration::from_millis(app.mint_batch_window),
+        max_batch_size:    app.max_mint_batch_size.max(1),
+    };
+
     let (db_sender, db_receiver) = tokio::sync::mpsc::channel(100);
     let (stop_sender, mut stop_receiver) = tokio::sync::watch::channel(());
     let (died_sender, died_receiver) = tokio::sync::broadcast::channel(10);
@@ -386,8 +524,12 @@ async fn main() -> anyhow::Result<()> {
             axum::routing::post(verify_proof),
         )
         .route(
-            "/v1/proof/nft",
-            axum::routing::post(mint_nft),
+            "/v1/proof/recover",
+            axum::routing::post(recover_nft),
+        )
+        .route(
+            "/v1/proof/rotate",
+            axum::routing::post(rotate_key),
         )
         .route(
             "/v1/proof/nft/:proofId/:decryptionKey",
@@ -395,7 +537,15 @@ async fn main() -> anyhow::Result<()> {
         )
         .route(
             "/v1/proof/nft/:proofId",
-            axum::routing::get(get_proof_state),
+            axum::routing::get(get_proof_state).post(get_proof_with_key),
+        )
+        .route(
+            "/v1/proof/encrypted/:proofId",
+            axum::routing::get(get_encrypted_proof),
+        )
+        .route(
+            "/v1/proof/data/:hash",
+            axum::routing::get(get_proof_data),
         )
         .route(
             "/v1/proof/validate-proof-url",
@@ -413,6 +563,10 @@ async fn main() -> anyhow::Result<()> {
             "/v1/names/match",
             axum::routing::get(match_names),
         )
+        .route(
+            "/v1/proof/minted/:transactionHash",
+            axum::routing::get(get_minted_tokens),
+        )
         .route(
             "/v1/proof/meta/:proof",
             axum::routing::get(get_metadata),
@@ -429,15 +583,18 @@ async fn main() -> anyhow::Result<()> {
             "/v1/qr/image/scan", // TODO: This is duplicate, there is no point in this.
             axum::routing::get(parse_qr),
         )
+        .layer(tower_http::timeout::TimeoutLayer::new(
+            std::time::Duration::from_millis(app.request_timeout),
+        ))
+        // Mint requests must not be cancelled once their token may be minted, since the key of
+        // the token would be lost. They are timed out by the mint batcher instead.
+        .route("/v1/proof/nft", axum::routing::post(mint_nft))
         .with_state(state)
         .layer(tower_http::trace::TraceLayer::new_for_http().
                make_span_with(DefaultMakeSpan::new().
                               include_headers(app.log_headers)).
                on_response(DefaultOnResponse::new().
                            include_headers(app.log_headers)))
-        .layer(tower_http::timeout::TimeoutLayer::new(
-            std::time::Duration::from_millis(app.request_timeout),
-        ))
         .layer(tower_http::limit::RequestBodyLimitLayer::new(16_386)) // 16kB bodies is plenty for the proofs we need.
         .layer(tower_http::cors::CorsLayer::permissive().allow_methods([http::Method::GET, http::Method::POST]))
         .layer(prometheus_layer);
@@ -459,6 +616,13 @@ async fn main() -> anyhow::Result<()> {
                 .context("Unable to start Prometheus server.")?;
             Ok::<(), anyhow::Error>(())
         });
+        tokio::spawn(summary_metrics(
+            ContractClient {
+                address: app.concordium_contract,
+                client:  concordium_client.clone(),
+            },
+            std::time::Duration::from_secs(app.summary_interval),
+        ));
     }
 
     let tx_sender_handle = spawn_cancel(
@@ -466,6 +630,8 @@ async fn main() -> anyhow::Result<()> {
         tx_sender(starting_nonce.nonce, receiver, db_sender, concordium_client),
     );
 
+    let mint_batcher_handle = spawn_cancel(died_sender.clone(), mint_batcher.run(mint_receiver));
+
     // run our app with hyper
     tracing::debug!("listening on {}", app.listen_address);
     let mut stop_receiver_server = stop_receiver.clone();
@@ -489,6 +655,7 @@ async fn main() -> anyhow::Result<()> {
     let shutdown = [
         await_and_report("database handler", db_handle),
         await_and_report("transaction sender", tx_sender_handle),
+        await_and_report("mint batcher", mint_batcher_handle),
     ];
     shutdown
         .into_iter()
@@ -502,11 +669,126 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-#[tracing::instrument(level = "debug")]
-/// Get the statement
+/// The statements that proofs are made for.
+#[derive(Debug)]
+struct Statements {
+    /// The statement for new proofs of each platform, and its hash.
+    current: HashMap<SupportedPlatform, (Arc<Statement<ArCurve, AttributeKind>>, [u8; 32])>,
+    /// All statements by their hash.
+    by_hash: HashMap<[u8; 32], Arc<Statement<ArCurve, AttributeKind>>>,
+    /// The statement of proofs that do not record the hash of their statement.
+    names:   Arc<Statement<ArCurve, AttributeKind>>,
+}
+
+impl Statements {
+    /// Load the statements from a JSON file mapping platforms to lists of
+    /// statements. The first statement of a platform is used for new proofs,
+    /// and the others are only used to validate existing proofs. Platforms
+    /// that are not in the file, or all if no file is given, use the statement
+    /// revealing the first and last name. Each statement must reveal the first
+    /// and last name, since these are stored in the token.
+    fn load(path: Option<&std::path::Path>) -> anyhow::Result<Self> {
+        let first_name: AttributeTag = "firstName".parse().unwrap();
+        let last_name: AttributeTag = "lastName".parse().unwrap();
+        let names = Arc::new(
+            Statement::new()
+                .reveal_attribute(first_name)
+                .reveal_attribute(last_name),
+        );
+        let mut configured: HashMap<SupportedPlatform, Vec<Statement<ArCurve, AttributeKind>>> =
+            if let Some(path) = path {
+                let file = std::fs::File::open(path).context("Unable to open statements file.")?;
+                serde_json::from_reader(std::io::BufReader::new(file))
+                    .context("Unable to parse statements file.")?
+            } else {
+                HashMap::new()
+            };
+        fn hash(statement: &Statement<ArCurve, AttributeKind>) -> [u8; 32] {
+            sha2::Sha256::digest(common::to_bytes(statement)).into()
+        }
+        let mut statements = Self {
+            current: HashMap::new(),
+            by_hash: HashMap::new(),
+            names:   names.clone(),
+        };
+        statements.by_hash.insert(hash(&names), names.clone());
+        for platform in [SupportedPlatform::LinkedIn] {
+            let platform_statements = match configured.remove(&platform) {
+                Some(platform_statements) => {
+                    anyhow::ensure!(
+                        !platform_statements.is_empty(),
+                        "No statement is given for platform {platform}."
+                    );
+                    platform_statements.into_iter().map(Arc::new).collect()
+                }
+                None => vec![names.clone()],
+            };
+            for (i, statement) in platform_statements.into_iter().enumerate() {
+                let reveals = |tag: AttributeTag| {
+                    statement.statements.iter().any(|atomic| match atomic {
+                        AtomicStatement::RevealAttribute { statement } => {
+                            statement.attribute_tag == tag
+                        }
+                        _ => false,
+                    })
+                };
+                anyhow::ensure!(
+                    reveals(first_name) && reveals(last_name),
+                    "The statements for platform {platform} must reveal the first and last name."
+                );
+                let statement_hash = hash(&statement);
+                statements.by_hash.insert(statement_hash, statement.clone());
+                if i == 0 {
+                    statements
+                        .current
+                        .insert(platform, (statement, statement_hash));
+                }
+            }
+        }
+        Ok(statements)
+    }
+
+    /// The statement for new proofs of the platform, and its hash.
+    fn for_platform(
+        &self,
+        platform: SupportedPlatform,
+    ) -> (Arc<Statement<ArCurve, AttributeKind>>, [u8; 32]) {
+        let (statement, hash) = &self.current[&platform];
+        (statement.clone(), *hash)
+    }
+
+    /// The statement a proof was made for, given the hash recorded with it.
+    fn for_proof(
+        &self,
+        statement_hash: Option<[u8; 32]>,
+    ) -> Option<Arc<Statement<ArCurve, AttributeKind>>> {
+        match statement_hash {
+            Some(hash) => self.by_hash.get(&hash).cloned(),
+            None => Some(self.names.clone()),
+        }
+    }
+
+    /// The hash of the statement a proof was made for, given the hash recorded
+    /// with it.
+    fn hash_for_proof(&self, statement_hash: Option<[u8; 32]>) -> [u8; 32] {
+        statement_hash
+            .unwrap_or_else(|| sha2::Sha256::digest(common::to_bytes(&*self.names)).into())
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct StatementQueryParams {
+    platform: Option<SupportedPlatform>,
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+/// Get the statement for proofs of the platform. This is the statement for
+/// LinkedIn if no platform is given.
 async fn get_statement(
-    State(ServiceState { statement, .. }): State<ServiceState>,
+    State(ServiceState { statements, .. }): State<ServiceState>,
+    Query(StatementQueryParams { platform }): Query<StatementQueryParams>,
 ) -> axum::Json<serde_json::Value> {
+    let (statement, _) = statements.for_platform(platform.unwrap_or(SupportedPlatform::LinkedIn));
     serde_json::json!({ "statement": &*statement }).into()
 }
 
@@ -516,9 +798,13 @@ type ProofId = u64;
 #[derive(Debug, serde::Deserialize)]
 struct MetadataQueryParams {
     #[serde(rename = "p")]
-    platform: SupportedPlatform,
+    platform:    SupportedPlatform,
     #[serde(rename = "r")]
-    revoked:  u8,
+    revoked:     u8,
+    // The expiry time of the token in milliseconds since the unix epoch, if it
+    // expires.
+    #[serde(rename = "e")]
+    valid_until: Option<u64>,
 }
 #[tracing::instrument(level = "debug", skip_all)]
 /// Get the metadata of a token/proof.
@@ -528,6 +814,14 @@ async fn get_metadata(
     State(ServiceState { base_url, .. }): State<ServiceState>,
 ) -> axum::Json<serde_json::Value> {
     let base_url: Url = (*base_url).clone();
+    let attributes = match params.valid_until {
+        Some(valid_until) => vec![serde_json::json!({
+            "type": "date",
+            "name": "validUntil",
+            "value": valid_until.to_string(),
+        })],
+        None => Vec::new(),
+    };
     serde_json::json!({
         "name": "mysome.id",
         "decimals": 0,
@@ -539,6 +833,7 @@ async fn get_metadata(
         "display": {
           "url": make_img_url(base_url, &proof, &params, false),
         },
+        "attributes": attributes,
     }).into()
 }
 
@@ -558,6 +853,70 @@ fn make_img_url(
     base
 }
 
+/// Make the link to a token in the compact format, which contains the token id
+/// and the key.
+fn make_proof_url(mut base: Url, token_id: ProofId, key: &[u8; 32]) -> Url {
+    if let Ok(mut segments) = base.path_segments_mut() {
+        segments.pop_if_empty().push(&link::encode(token_id, key));
+    }
+    base
+}
+
+fn make_proof_data_url(mut base: Url, hash: &[u8; 32]) -> Url {
+    base.set_path(&format!("v1/proof/data/{}", hex::encode(hash)));
+    base
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+/// Get the encrypted data of a token that is stored off-chain. The data is
+/// addressed by its SHA-256 hash, which is committed to in the contract.
+async fn get_proof_data(
+    Path(hash): Path<String>,
+    State(ServiceState { read_db, .. }): State<ServiceState>,
+) -> Result<impl axum::response::IntoResponse, Error> {
+    let Ok(Ok(hash)) = hex::decode(hash).map(<[u8; 32]>::try_from) else {
+        return Err(Error::InvalidRequest("The hash must be 64 hex characters.".into()));
+    };
+    match read_db.get_proof_data(&hash).await {
+        Ok(Some(data)) => Ok((TypedHeader(ContentType::octet_stream()), data)),
+        Ok(None) => Err(Error::NotFound),
+        Err(e) => {
+            tracing::error!("Error querying database: {e:#}");
+            Err(Error::Internal)
+        }
+    }
+}
+
+/// Retrieve the data of a token that is stored off-chain and check it against
+/// the commitment stored in the contract.
+async fn get_off_chain_data(
+    read_db: &db::ReadDatabase,
+    commitment: &DataCommitment,
+) -> Result<Vec<u8>, Error> {
+    let data = match read_db.get_proof_data(&commitment.hash).await {
+        Ok(Some(data)) => data,
+        Ok(None) => {
+            tracing::warn!(
+                "No data stored for commitment {}.",
+                hex::encode(commitment.hash)
+            );
+            return Err(Error::NotFound);
+        }
+        Err(e) => {
+            tracing::error!("Error querying database: {e:#}");
+            return Err(Error::Internal);
+        }
+    };
+    if sha2::Sha256::digest(&data)[..] != commitment.hash[..] {
+        tracing::error!(
+            "Stored data does not match commitment {}.",
+            hex::encode(commitment.hash)
+        );
+        return Err(Error::Internal);
+    }
+    Ok(data)
+}
+
 #[derive(Debug, serde::Deserialize)]
 enum ImageVariant {
     #[serde(rename = "thumb")]
@@ -703,23 +1062,241 @@ async fn parse_qr(
 struct ChallengeParams {
     platform:  SupportedPlatform,
     user_data: String,
+    account:   AccountAddress,
+}
+
+/// Length of the expiry prefix of a challenge.
+const CHALLENGE_EXPIRY_LEN: usize = 8;
+
+/// Compute the MAC of a challenge issued for a proof from `account` for the
+/// given platform and user data, which expires at `expiry` milliseconds.
+fn challenge_mac(
+    secret: &[u8],
+    contract_address: ContractAddress,
+    account: &AccountAddress,
+    expiry: i64,
+    platform: SupportedPlatform,
+    user_data: &str,
+) -> hmac::Hmac<sha2::Sha256> {
+    use hmac::Mac;
+    let mut mac = <hmac::Hmac<sha2::Sha256>>::new_from_slice(secret)
+        .expect("HMAC accepts keys of any length.");
+    mac.update(&canonical_address(account).0);
+    mac.update(&contract_address.index.to_be_bytes());
+    mac.update(&contract_address.subindex.to_be_bytes());
+    mac.update(&expiry.to_be_bytes());
+    mac.update(platform.to_string().as_bytes());
+    mac.update(user_data.as_bytes());
+    mac
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
-/// Get the statement
+/// Issue a challenge for a proof from `account`. The challenge is the expiry
+/// followed by a MAC of the account, the contract, the expiry, the platform and
+/// the user data, so it does not have to be stored until it is used.
 async fn get_challenge(
+    State(ServiceState {
+        contract_address,
+        challenge_validity,
+        challenge_secret,
+        ..
+    }): State<ServiceState>,
     Query(ChallengeParams {
         platform,
         user_data,
+        account,
     }): Query<ChallengeParams>,
-) -> axum::Json<serde_json::Value> {
-    let mut hasher = sha2::Sha256::new();
-    hasher.update((platform as u32).to_be_bytes());
-    hasher.update(user_data);
-    serde_json::json!({
-        "challenge": Challenge{ challenge: hasher.finalize().into() }
+) -> Result<axum::Json<serde_json::Value>, Error> {
+    let expiry = (chrono::Utc::now() + challenge_validity).timestamp_millis();
+    let challenge = make_challenge(
+        &challenge_secret,
+        contract_address,
+        &account,
+        expiry,
+        platform,
+        &user_data,
+    );
+    Ok(axum::Json(serde_json::json!({
+        "challenge": challenge,
+        "expiry": expiry,
+    })))
+}
+
+/// Make the challenge for a proof from `account` for the given platform and
+/// user data, which expires at `expiry` milliseconds.
+fn make_challenge(
+    secret: &[u8],
+    contract_address: ContractAddress,
+    account: &AccountAddress,
+    expiry: i64,
+    platform: SupportedPlatform,
+    user_data: &str,
+) -> Challenge {
+    use hmac::Mac;
+    let mac = challenge_mac(
+        secret,
+        contract_address,
+        account,
+        expiry,
+        platform,
+        user_data,
+    )
+    .finalize()
+    .into_bytes();
+    let mut challenge = Challenge {
+        challenge: [0u8; 32],
+    };
+    challenge.challenge[..CHALLENGE_EXPIRY_LEN].copy_from_slice(&expiry.to_be_bytes());
+    challenge.challenge[CHALLENGE_EXPIRY_LEN..].copy_from_slice(&mac[..32 - CHALLENGE_EXPIRY_LEN]);
+    challenge
+}
+
+/// Check that `challenge` was issued by the service for `account`, the
+/// contract, the platform and the user data, and that it has not expired.
+/// Whether it was already used is checked by [`ensure_challenge_unused`], or
+/// when it is reserved with [`reserve_challenge`].
+fn check_challenge(
+    secret: &[u8],
+    contract_address: ContractAddress,
+    account: &AccountAddress,
+    challenge: &Challenge,
+    platform: SupportedPlatform,
+    user_data: &str,
+) -> Result<db::IssuedChallenge, Error> {
+    use hmac::Mac;
+    let (expiry_bytes, tag) = challenge.challenge.split_at(CHALLENGE_EXPIRY_LEN);
+    let mut expiry = [0u8; CHALLENGE_EXPIRY_LEN];
+    expiry.copy_from_slice(expiry_bytes);
+    let expiry = i64::from_be_bytes(expiry);
+    challenge_mac(
+        secret,
+        contract_address,
+        account,
+        expiry,
+        platform,
+        user_data,
+    )
+    .verify_truncated_left(tag)
+    .map_err(|_| Error::ChallengeMismatch)?;
+    let expiry = match chrono::TimeZone::timestamp_millis_opt(&chrono::Utc, expiry) {
+        chrono::LocalResult::Single(expiry) if expiry > chrono::Utc::now() => expiry,
+        _ => return Err(Error::InvalidRequest("The challenge has expired.".into())),
+    };
+    Ok(db::IssuedChallenge {
+        account: canonical_address(account),
+        contract: contract_address,
+        platform: platform.to_string(),
+        user_data: user_data.into(),
+        expiry,
     })
-    .into()
+}
+
+/// Return [`Error::ChallengeMismatch`] unless the challenge of a minted token
+/// was issued for `platform` and `user_data`. Tokens minted before challenges
+/// were issued by the service, i.e., before `cutover` or before the contract
+/// recorded the time of minting, use the hash of the platform and the user data
+/// as the challenge. `issued_at` is the time the token was minted in
+/// milliseconds, which is 0 if it was not recorded.
+async fn ensure_token_challenge_binding(
+    read_db: &db::ReadDatabase,
+    challenge: &Challenge,
+    platform: SupportedPlatform,
+    user_data: &str,
+    issued_at: u64,
+    cutover: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<(), Error> {
+    match read_db.get_challenge(&challenge.challenge).await {
+        Ok(Some(issued)) => {
+            if issued.platform != platform.to_string() || issued.user_data != user_data {
+                return Err(Error::ChallengeMismatch);
+            }
+            Ok(())
+        }
+        Ok(None) => {
+            // Owners can replace the data of their tokens, so the hash could
+            // otherwise be stored with the data of any profile.
+            let legacy = issued_at == 0
+                || cutover.map_or(false, |cutover| {
+                    (issued_at as i64) < cutover.timestamp_millis()
+                });
+            if !legacy {
+                return Err(Error::ChallengeMismatch);
+            }
+            let mut hasher = sha2::Sha256::new();
+            hasher.update((platform as u32).to_be_bytes());
+            hasher.update(user_data);
+            let expected: [u8; 32] = hasher.finalize().into();
+            if expected != challenge.challenge {
+                return Err(Error::ChallengeMismatch);
+            }
+            Ok(())
+        }
+        Err(e) => {
+            tracing::error!("Error querying database: {e:#}");
+            Err(Error::Internal)
+        }
+    }
+}
+
+/// Return an error if `challenge` is used by a transaction that did not fail,
+/// or is reserved by another request.
+async fn ensure_challenge_unused(
+    read_db: &db::ReadDatabase,
+    challenge: &Challenge,
+) -> Result<(), Error> {
+    match read_db.is_challenge_used(&challenge.challenge).await {
+        Ok(false) => Ok(()),
+        Ok(true) => Err(Error::InvalidRequest(
+            "The challenge has already been used.".into(),
+        )),
+        Err(e) => {
+            tracing::error!("Error querying database: {e:#}");
+            Err(Error::Internal)
+        }
+    }
+}
+
+/// Reserve `challenge` for a transaction, so that it cannot be used by another
+/// request. This fails if it is already used by a transaction that did not
+/// fail. The use must be completed with [`complete_challenge_use`].
+async fn reserve_challenge(
+    read_db: &db::ReadDatabase,
+    challenge: &Challenge,
+    issued: &db::IssuedChallenge,
+) -> Result<(), Error> {
+    match read_db
+        .reserve_challenge(&challenge.challenge, issued)
+        .await
+    {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(Error::InvalidRequest(
+            "The challenge has already been used.".into(),
+        )),
+        Err(e) => {
+            tracing::error!("Error querying database: {e:#}");
+            Err(Error::Internal)
+        }
+    }
+}
+
+/// Record the transaction that used a reserved challenge, or release the
+/// challenge if no transaction was sent, so that it can be used again.
+async fn complete_challenge_use(
+    read_db: &db::ReadDatabase,
+    challenge: &Challenge,
+    sent: Option<&concordium::types::hashes::TransactionHash>,
+) {
+    let result = match sent {
+        Some(hash) => {
+            read_db
+                .set_challenge_transaction(&challenge.challenge, hash)
+                .await
+        }
+        None => read_db.release_challenge(&challenge.challenge).await,
+    };
+    if let Err(e) = result {
+        tracing::error!("Error recording the use of a challenge: {e:#}");
+    }
 }
 
 #[derive(serde::Deserialize)]
@@ -728,6 +1305,8 @@ struct VerifyParams {
     account:   AccountAddress,
     proof:     ProofWithContext,
     challenge: Challenge,
+    platform:  SupportedPlatform,
+    user_data: String,
 }
 
 /// Get the statement
@@ -736,11 +1315,26 @@ async fn verify_proof(
     State(ServiceState {
         concordium_client,
         crypto_params,
-        statement,
+        statements,
+        contract_address,
+        challenge_secret,
+        read_db,
         ..
     }): State<ServiceState>,
     axum::Json(params): axum::Json<VerifyParams>,
 ) -> Result<axum::Json<serde_json::Value>, Error> {
+    check_challenge(
+        &challenge_secret,
+        contract_address,
+        &params.account,
+        &params.challenge,
+        params.platform,
+        &params.user_data,
+    )?;
+    // Verifying a proof does not use its challenge, which can still be used to
+    // mint a token afterwards.
+    ensure_challenge_unused(&read_db, &params.challenge).await?;
+    let (statement, _) = statements.for_platform(params.platform);
     let result = verify_proof_worker(concordium_client, crypto_params, statement, params).await?;
     Ok(axum::Json(serde_json::json!({ "result": result })))
 }
@@ -753,6 +1347,7 @@ async fn verify_proof_worker(
         account,
         proof,
         challenge,
+        ..
     }: VerifyParams,
 ) -> Result<bool, Error> {
     if proof.proof.version != VERSION_0 {
@@ -827,19 +1422,22 @@ struct MatchNamesParams {
     id_name:    String,
 }
 
-// The URL is meant to be in the format
+// The URL is meant to be in the compact format base/:link, or
+// base/:tokenId/:link (see the link module), or in the legacy format
 // base/:tokenId/:decryptionKey
 // Where tokenId is a u64 (represented as a number), and decryption key is
 // base64 encoded (and then URI encoded if necessary)
 fn get_token_id_and_key(url: &Url) -> Option<(u64, DecryptionKey)> {
     let mut path_segments = url.path_segments()?.rev();
-    let key_b64 = percent_encoding::percent_decode_str(path_segments.next()?)
+    let last = percent_encoding::percent_decode_str(path_segments.next()?)
         .decode_utf8()
         .ok()?;
-    let key = key_b64.parse::<EncryptionKey>().ok()?;
-    let id = path_segments.next()?;
-    let id = id.parse::<u64>().ok()?;
-    Some((id, key))
+    let id = path_segments.next().and_then(|id| id.parse::<u64>().ok());
+    if let Some((id, key)) = link::decode(&last, id) {
+        return Some((id, EncryptionKey { key }));
+    }
+    let key = last.parse::<EncryptionKey>().ok()?;
+    Some((id?, key))
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
@@ -903,19 +1501,42 @@ async fn validate_proof_worker(
         concordium_client,
         contract_address,
         crypto_params,
-        statement,
+        statements,
         allowed_substitutions,
         allowed_titles,
+        read_db,
+        challenge_cutover,
+        key_derivations,
         ..
     }): State<ServiceState>,
 ) -> Result<bool, Error> {
-    let proof =
-        get_proof_worker(token_id, concordium_client.clone(), contract_address, key).await?;
+    let proof = match get_proof_worker(
+        token_id,
+        concordium_client.clone(),
+        contract_address,
+        read_db.clone(),
+        key_derivations,
+        ProofKey::DecryptionKey(key),
+    )
+    .await
+    {
+        Ok(proof) => proof,
+        // A proof erased by its owner is no longer valid.
+        Err(Error::Erased) => return Ok(false),
+        Err(e) => return Err(e),
+    };
 
     if proof.revoked {
         return Ok(false);
     }
 
+    if proof
+        .valid_until
+        .map_or(false, |t| t <= chrono::Utc::now().timestamp_millis() as u64)
+    {
+        return Ok(false);
+    }
+
     if platform != proof.platform {
         return Ok(false);
     }
@@ -924,6 +1545,18 @@ async fn validate_proof_worker(
         return Ok(false);
     }
 
+    // The proof must have been made for this profile, and not only wrapped with
+    // its user data.
+    ensure_token_challenge_binding(
+        &read_db,
+        &proof.private.challenge,
+        platform,
+        user_data,
+        proof.issued_at,
+        challenge_cutover,
+    )
+    .await?;
+
     // Ensure that the parameters stored in the proof are the same as that sent in
     // the query parameters.
     match fuzzy_match_names(
@@ -940,10 +1573,17 @@ async fn validate_proof_worker(
         Err(_) => return Err(Error::Invalid),
     }
 
+    // Check the proof against the statement it was made for.
+    let Some(statement) = statements.for_proof(proof.private.statement_hash) else {
+        tracing::error!("Token {token_id} has a proof of an unknown statement.");
+        return Err(Error::Internal);
+    };
     let res = verify_proof_worker(concordium_client, crypto_params, statement, VerifyParams {
         account:   proof.owner,
         proof:     proof.private.proof,
         challenge: proof.private.challenge,
+        platform:  proof.platform,
+        user_data: proof.private.user_data,
     })
     .await?;
     Ok(res)
@@ -988,6 +1628,16 @@ struct EncryptionKey {
 /// decryption key. This alias is for those documentation purposes.
 type DecryptionKey = EncryptionKey;
 
+/// A key with which the data of a token can be decrypted.
+#[derive(serde::Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+enum ProofKey {
+    /// The key of the token.
+    DecryptionKey(DecryptionKey),
+    /// The passphrase the key of the token is derived from.
+    Passphrase(String),
+}
+
 impl std::fmt::Debug for EncryptionKey {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(String::from(*self).as_str())
@@ -1034,42 +1684,104 @@ async fn get_proof(
     State(ServiceState {
         concordium_client,
         contract_address,
+        read_db,
+        key_derivations,
         ..
     }): State<ServiceState>,
 ) -> Result<axum::Json<GetProofResponse>, Error> {
-    get_proof_worker(token_id, concordium_client, contract_address, key)
-        .await
-        .map(axum::Json)
+    get_proof_worker(
+        token_id,
+        concordium_client,
+        contract_address,
+        read_db,
+        key_derivations,
+        ProofKey::DecryptionKey(key),
+    )
+    .await
+    .map(axum::Json)
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
-async fn get_proof_state(
+/// Get the data of a token with a key supplied in the body of the request,
+/// instead of in the path.
+async fn get_proof_with_key(
     Path(token_id): Path<ProofId>,
     State(ServiceState {
         concordium_client,
         contract_address,
+        read_db,
+        key_derivations,
         ..
     }): State<ServiceState>,
-) -> Result<axum::Json<GetProofStateResponse>, Error> {
-    get_proof_state_worker(token_id, concordium_client, contract_address)
-        .await
-        .map(axum::Json)
+    axum::Json(key): axum::Json<ProofKey>,
+) -> Result<axum::Json<GetProofResponse>, Error> {
+    get_proof_worker(
+        token_id,
+        concordium_client,
+        contract_address,
+        read_db,
+        key_derivations,
+        key,
+    )
+    .await
+    .map(axum::Json)
 }
 
-#[derive(serde::Deserialize)]
-struct GetEventsParams {
-    limit: Option<u32>,
-    from:  Option<i64>,
+#[tracing::instrument(level = "debug", skip_all)]
+/// Get the encrypted data of a token as it is stored, so that designated
+/// verifiers can decrypt it with their secret key without sending the key to
+/// the service.
+async fn get_encrypted_proof(
+    Path(token_id): Path<ProofId>,
+    State(ServiceState {
+        concordium_client,
+        contract_address,
+        read_db,
+        ..
+    }): State<ServiceState>,
+) -> Result<impl axum::response::IntoResponse, Error> {
+    let (_, data) =
+        view_token_with_data(token_id, concordium_client, contract_address, &read_db).await?;
+    Ok((TypedHeader(ContentType::octet_stream()), data))
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+async fn get_proof_state(
+    Path(token_id): Path<ProofId>,
+    State(ServiceState {
+        concordium_client,
+        contract_address,
+        ..
+    }): State<ServiceState>,
+) -> Result<axum::Json<GetProofStateResponse>, Error> {
+    get_proof_state_worker(token_id, concordium_client, contract_address)
+        .await
+        .map(axum::Json)
+}
+
+#[derive(serde::Deserialize)]
+struct GetEventsParams {
+    limit:    Option<u32>,
+    from:     Option<i64>,
+    platform: Option<SupportedPlatform>,
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
 async fn get_events(
     Path(address): Path<AccountAddress>,
-    Query(GetEventsParams { limit, from }): Query<GetEventsParams>,
+    Query(GetEventsParams {
+        limit,
+        from,
+        platform,
+    }): Query<GetEventsParams>,
     State(ServiceState { read_db, .. }): State<ServiceState>,
 ) -> Result<axum::Json<serde_json::Value>, Error> {
     let effective_limit = limit.map_or(500, |x| std::cmp::min(500, x));
-    match read_db.get_events(address, from, effective_limit).await {
+    let platform = platform.map(|p| p.to_string());
+    match read_db
+        .get_events(address, from, effective_limit, platform.as_deref())
+        .await
+    {
         Ok(events) => Ok(axum::Json(serde_json::json!({
             "limit": effective_limit,
             "events": events
@@ -1081,13 +1793,14 @@ async fn get_events(
     }
 }
 
-#[tracing::instrument(level = "debug", skip(concordium_client, contract_address))]
-async fn get_proof_worker(
+/// Look up a token and its encrypted data. If the token only stores a
+/// commitment to its data, the data is taken from the database.
+async fn view_token_with_data(
     token_id: ProofId,
     concordium_client: v2::Client,
     contract_address: ContractAddress,
-    key: EncryptionKey,
-) -> Result<GetProofResponse, Error> {
+    read_db: &db::ReadDatabase,
+) -> Result<(ViewData, Vec<u8>), Error> {
     let mut contract_client = ContractClient {
         address: contract_address,
         client:  concordium_client,
@@ -1115,59 +1828,137 @@ async fn get_proof_worker(
         },
     };
 
-    let Some(view_data) = response else {
+    let Some(mut view_data) = response else {
         return Err(Error::InvalidRequest("No token with the given token ID.".into()))
     };
 
-    let encryption_data = &view_data.data;
+    if view_data.burned_at.is_some() {
+        return Err(Error::Erased);
+    }
 
-    let cipher = Aes256Gcm::new((&key.key).into());
+    let encryption_data = match &view_data.commitment {
+        Some(commitment) => get_off_chain_data(read_db, commitment).await?,
+        None => std::mem::take(&mut view_data.data),
+    };
+    Ok((view_data, encryption_data))
+}
 
-    let (nonce, ciphertext) = {
-        let mut cursor = std::io::Cursor::new(&encryption_data);
-        let mut version = [0u8; 4];
-        if cursor.read_exact(&mut version).is_err() {
+#[tracing::instrument(
+    level = "debug",
+    skip(concordium_client, contract_address, read_db, key_derivations, key)
+)]
+async fn get_proof_worker(
+    token_id: ProofId,
+    concordium_client: v2::Client,
+    contract_address: ContractAddress,
+    read_db: db::ReadDatabase,
+    key_derivations: Arc<tokio::sync::Semaphore>,
+    key: ProofKey,
+) -> Result<GetProofResponse, Error> {
+    let (view_data, encryption_data) =
+        view_token_with_data(token_id, concordium_client, contract_address, &read_db).await?;
+
+    let opened = match key {
+        ProofKey::DecryptionKey(key) => payload::open(&encryption_data, &key.key),
+        ProofKey::Passphrase(passphrase) => derive_key_blocking(&key_derivations, move || {
+            payload::open_with_passphrase(&encryption_data, &passphrase)
+        })
+        .await?
+        .map(|(header, data)| (Some(header), data)),
+    };
+    let (header, proof_data_bytes) = match opened {
+        Ok(opened) => opened,
+        Err(payload::PayloadError::Decryption) => {
+            return Err(Error::InvalidRequest("Unable to decrypt data.".into()));
+        }
+        Err(payload::PayloadError::NoPassphrase) => {
             return Err(Error::InvalidRequest(
-                "The stored proof cannot be read.".into(),
+                "The token is not protected by a passphrase.".into(),
             ));
         }
-        if version != [0u8; 4] {
+        Err(payload::PayloadError::UnsupportedVersion(_)) => {
             return Err(Error::InvalidRequest(
-                "Only version 0 proofs are supported.".into(),
+                "Only version 0 and 1 proofs are supported.".into(),
             ));
         }
-        let mut nonce = [0u8; 12];
-        if cursor.read_exact(&mut nonce).is_err() {
+        Err(_) => {
             return Err(Error::InvalidRequest(
                 "The stored proof cannot be read.".into(),
             ));
         }
-        (nonce, &encryption_data[16..])
     };
 
-    let proof_data_bytes = cipher
-        .decrypt((&nonce).into(), ciphertext)
-        .map_err(|_| Error::InvalidRequest("Unable to decrypt data.".into()))?;
+    let Ok(mut private) = common::from_bytes::<PrivateTokenData, _>(&mut std::io::Cursor::new(proof_data_bytes)) else { return Err(Error::InvalidRequest("Could not read stored private token data.".into()))};
 
-    let Ok(private) = common::from_bytes::<PrivateTokenData, _>(&mut std::io::Cursor::new(proof_data_bytes)) else { return Err(Error::InvalidRequest("Could not read stored private token data.".into()))};
+    if let Some(header) = &header {
+        if header.platform != view_data.platform
+            || header.owner != canonical_address(&view_data.owner)
+        {
+            return Err(Error::InvalidRequest(
+                "The stored proof does not belong to the token.".into(),
+            ));
+        }
+        private.statement_hash = Some(header.statement_hash);
+    }
 
     Ok(GetProofResponse {
         id: token_id,
         platform: view_data.platform,
         revoked: view_data.revoked,
+        revocation_reason: view_data.revocation_reason,
+        issued_at: view_data.issued_at.timestamp_millis(),
+        valid_until: view_data.valid_until.map(|t| t.timestamp_millis()),
+        replaces: view_data.replaces.map(|ContractTokenId(id)| id),
+        replaced_by: view_data.replaced_by.map(|ContractTokenId(id)| id),
         owner: view_data.owner,
+        designated_verifiers: header
+            .as_ref()
+            .map_or(false, |header| !header.verifiers.is_empty()),
+        passphrase_protected: header
+            .as_ref()
+            .map_or(false, |header| header.passphrase.is_some()),
+        profile_url: header.map(|header| header.profile_url),
+        data_hash: view_data.commitment.map(|commitment| commitment.hash),
         private,
     })
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct GetProofResponse {
-    pub id:       ProofId,
-    pub owner:    AccountAddress,
-    pub platform: SupportedPlatform,
-    pub revoked:  bool,
+    pub id:                   ProofId,
+    pub owner:                AccountAddress,
+    pub platform:             SupportedPlatform,
+    pub revoked:              bool,
+    #[serde(rename = "revocationReason")]
+    pub revocation_reason:    Option<RevocationReason>,
+    // Time the token was minted, in milliseconds since the unix epoch.
+    #[serde(rename = "issuedAt")]
+    pub issued_at:            u64,
+    // Time until which the token is valid, in milliseconds since the unix
+    // epoch. If not set the token does not expire.
+    #[serde(rename = "validUntil")]
+    pub valid_until:          Option<u64>,
+    // The token this token was reissued to replace, if any.
+    pub replaces:             Option<ProofId>,
+    // The token this token was replaced by when it was reissued, if any.
+    #[serde(rename = "replacedBy", default)]
+    pub replaced_by:          Option<ProofId>,
+    // URL of the profile the token is for. This is only recorded for tokens
+    // minted with version 1 of the data format.
+    #[serde(rename = "profileUrl")]
+    pub profile_url:          Option<String>,
+    // Whether the key of the token is also encrypted for designated verifiers.
+    #[serde(rename = "designatedVerifiers", default)]
+    pub designated_verifiers: bool,
+    // Whether the key of the token is derived from a passphrase.
+    #[serde(rename = "passphraseProtected", default)]
+    pub passphrase_protected: bool,
+    // Hash of the data of the token if it is stored off-chain. This is only
+    // used to delete the data once it is replaced.
+    #[serde(skip)]
+    pub data_hash:            Option<[u8; 32]>,
     #[serde(flatten)]
-    pub private:  PrivateTokenData,
+    pub private:              PrivateTokenData,
 }
 
 #[tracing::instrument(level = "debug", skip(concordium_client, contract_address))]
@@ -1208,19 +1999,43 @@ async fn get_proof_state_worker(
     };
 
     Ok(GetProofStateResponse {
-        id:       token_id,
-        platform: view_data.platform,
-        revoked:  view_data.revoked,
-        owner:    view_data.owner,
+        id:                token_id,
+        platform:          view_data.platform,
+        revoked:           view_data.revoked,
+        revocation_reason: view_data.revocation_reason,
+        issued_at:         view_data.issued_at.timestamp_millis(),
+        valid_until:       view_data.valid_until.map(|t| t.timestamp_millis()),
+        burned_at:         view_data.burned_at.map(|t| t.timestamp_millis()),
+        replaces:          view_data.replaces.map(|ContractTokenId(id)| id),
+        replaced_by:       view_data.replaced_by.map(|ContractTokenId(id)| id),
+        owner:             view_data.owner,
     })
 }
 
 #[derive(serde::Deserialize, serde::Serialize)]
 pub struct GetProofStateResponse {
-    pub id:       ProofId,
-    pub owner:    AccountAddress,
-    pub platform: SupportedPlatform,
-    pub revoked:  bool,
+    pub id:                ProofId,
+    pub owner:             AccountAddress,
+    pub platform:          SupportedPlatform,
+    pub revoked:           bool,
+    #[serde(rename = "revocationReason")]
+    pub revocation_reason: Option<RevocationReason>,
+    // Time the token was minted, in milliseconds since the unix epoch.
+    #[serde(rename = "issuedAt")]
+    pub issued_at:         u64,
+    // Time until which the token is valid, in milliseconds since the unix
+    // epoch. If not set the token does not expire.
+    #[serde(rename = "validUntil")]
+    pub valid_until:       Option<u64>,
+    // Time the token was burned by its owner, in milliseconds since the unix
+    // epoch. The proof of a burned token is erased.
+    #[serde(rename = "burnedAt")]
+    pub burned_at:         Option<u64>,
+    // The token this token was reissued to replace, if any.
+    pub replaces:          Option<ProofId>,
+    // The token this token was replaced by when it was reissued, if any.
+    #[serde(rename = "replacedBy", default)]
+    pub replaced_by:       Option<ProofId>,
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
@@ -1228,26 +2043,539 @@ async fn mint_nft(
     State(ServiceState {
         mut concordium_client,
         crypto_params,
-        statement,
+        statements,
+        contract_address,
+        mint_sender,
+        mint_request_timeout,
+        read_db,
+        proof_validity_days,
+        off_chain_data,
+        base_url,
+        challenge_secret,
+        proof_base_url,
+        key_id,
+        verifier_keys,
+        key_derivations,
+        ..
+    }): State<ServiceState>,
+    axum::Json(MintParams {
+        account,
+        platform,
+        mut private,
+        designated_verifiers,
+        passphrase,
+    }): axum::Json<MintParams>,
+) -> Result<axum::Json<serde_json::Value>, Error> {
+    let deadline = tokio::time::Instant::now() + mint_request_timeout;
+    if private.proof.proof.version != VERSION_0 {
+        return Err(Error::InvalidRequest(
+            "Only version 0 proofs are supported.".into(),
+        ));
+    }
+    if designated_verifiers && verifier_keys.is_empty() {
+        return Err(Error::InvalidRequest(
+            "No designated verifiers are configured.".into(),
+        ));
+    }
+    ensure_valid_passphrase(passphrase.as_deref())?;
+    // Do not send a mint transaction that the contract would reject.
+    ensure_not_paused(&concordium_client, contract_address).await?;
+    let issued = check_challenge(
+        &challenge_secret,
+        contract_address,
+        &account,
+        &private.challenge,
+        platform,
+        &private.user_data,
+    )?;
+    let (statement, statement_hash) = statements.for_platform(platform);
+    let account_index = verify_account_proof(
+        &mut concordium_client,
+        &crypto_params,
+        &statement,
+        account,
+        &private,
+    )
+    .await?;
+    private.statement_hash = Some(statement_hash);
+
+    // We have verified the request. Now we mint.
+    let header = payload::PayloadHeader {
+        platform,
+        owner: canonical_address(&account),
+        profile_url: platform.profile_url(&private.user_data),
+        minted_at: chrono::Utc::now().timestamp_millis() as u64,
+        statement_hash,
+        key_id,
+        verifiers: if designated_verifiers {
+            verifier_keys.to_vec()
+        } else {
+            Vec::new()
+        },
+        passphrase: passphrase.as_ref().map(|_| payload::PassphraseParams::generate()),
+    };
+    let (aux_data, key) =
+        encrypt_proof_data(&key_derivations, &private, header, passphrase).await?;
+
+    let valid_until = proof_validity_days.map(|days| {
+        let valid_until = chrono::Utc::now() + chrono::Duration::days(days.into());
+        concordium_std::Timestamp::from_timestamp_millis(valid_until.timestamp_millis() as u64)
+    });
+
+    let (data, commitment, off_chain) = prepare_token_data(off_chain_data, &base_url, aux_data);
+
+    let mint_params = ContractMintParams {
+        owner: account,
+        platform,
+        data,
+        valid_until,
+        commitment,
+    };
+
+    // The mint is submitted together with other requests by the mint batcher,
+    // which completes the use of the challenge.
+    reserve_challenge(&read_db, &private.challenge, &issued).await?;
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    if let Err(e) = mint_sender.try_send(MintRequest {
+        params: mint_params,
+        account,
+        account_index,
+        off_chain,
+        challenge: private.challenge,
+        deadline,
+        response: sender,
+    }) {
+        complete_challenge_use(&read_db, &private.challenge, None).await;
+        return match e {
+            TrySendError::Full(_) => {
+                tracing::warn!("Unable to enqueue mint request.");
+                Err(Error::Busy)
+            }
+            TrySendError::Closed(_) => {
+                tracing::error!("Unable to enqueue mint request since the mint batcher is dead.");
+                Err(Error::Internal)
+            }
+        };
+    }
+    let Ok(submitted) = receiver.await else {
+        tracing::error!("The mint batcher dropped the mint request.");
+        return Err(Error::Internal);
+    };
+    let SubmittedMint {
+        transaction_hash,
+        token_id,
+    } = submitted?;
+
+    // The token id is only known once the transaction is finalized. If that
+    // does not happen in time the response is returned without it, so that the
+    // key is not lost, and the token id can be looked up with
+    // `GET /v1/proof/minted/:transactionHash`.
+    let token_id = match tokio::time::timeout_at(deadline, token_id).await {
+        Ok(Ok(token_id)) => Some(token_id),
+        Ok(Err(_)) => None,
+        Err(_) => {
+            tracing::warn!("Transaction {transaction_hash} was not finalized in time.");
+            None
+        }
+    };
+    let proof_url =
+        token_id.map(|token_id| make_proof_url((*proof_base_url).clone(), token_id, &key));
+    Ok(axum::Json(serde_json::json!({
+        "transactionHash": transaction_hash,
+        "decryptionKey": EncryptionKey{key},
+        "tokenId": token_id,
+        "proofUrl": proof_url,
+    })))
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+/// Get the status of a mint transaction sent by the service, and the tokens it
+/// minted once it is finalized and indexed.
+async fn get_minted_tokens(
+    Path(transaction_hash): Path<concordium::types::hashes::TransactionHash>,
+    State(ServiceState { read_db, .. }): State<ServiceState>,
+) -> Result<axum::Json<serde_json::Value>, Error> {
+    let (status, tokens) = match read_db.get_minted_tokens(&transaction_hash).await {
+        Ok(Some(minted)) => minted,
+        Ok(None) => return Err(Error::NotFound),
+        Err(e) => {
+            tracing::error!("Error querying database: {e:#}");
+            return Err(Error::Internal);
+        }
+    };
+    let tokens = tokens
+        .into_iter()
+        .map(|(token_id, owner)| serde_json::json!({ "tokenId": token_id, "owner": owner }))
+        .collect::<Vec<_>>();
+    Ok(axum::Json(serde_json::json!({
+        "status": status,
+        "tokens": tokens,
+    })))
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecoverParams {
+    /// The token to recover.
+    token_id:       ProofId,
+    /// The key to decrypt the proof currently stored in the token.
+    decryption_key: DecryptionKey,
+    /// The new account to move the token to.
+    account:        AccountAddress,
+    #[serde(flatten)]
+    private:        PrivateTokenData,
+    /// If set, the new key of the token is derived from this passphrase.
+    #[serde(default)]
+    passphrase:     Option<String>,
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+/// Move a token to a new account of the same identity. The request must
+/// contain a proof from the new account which reveals the same names as the
+/// proof stored in the token, and the credential of the new account must be
+/// created from the same identity object as that of the proof stored in the
+/// token. The token then stores the new proof, encrypted with a fresh key.
+async fn recover_nft(
+    State(ServiceState {
+        mut concordium_client,
+        crypto_params,
+        statements,
         signer,
         contract_address,
         nonce_counter,
         tx_sender,
         read_db,
         max_daily_mints,
+        off_chain_data,
+        base_url,
+        challenge_secret,
+        proof_base_url,
+        key_id,
+        verifier_keys,
+        key_derivations,
         ..
     }): State<ServiceState>,
-    axum::Json(MintParams {
+    axum::Json(RecoverParams {
+        token_id,
+        decryption_key,
         account,
-        platform,
-        private,
-    }): axum::Json<MintParams>,
+        mut private,
+        passphrase,
+    }): axum::Json<RecoverParams>,
 ) -> Result<axum::Json<serde_json::Value>, Error> {
     if private.proof.proof.version != VERSION_0 {
         return Err(Error::InvalidRequest(
             "Only version 0 proofs are supported.".into(),
         ));
     }
+    ensure_valid_passphrase(passphrase.as_deref())?;
+    // Do not send a recover transaction that the contract would reject.
+    ensure_not_paused(&concordium_client, contract_address).await?;
+    let token = get_proof_worker(
+        token_id,
+        concordium_client.clone(),
+        contract_address,
+        read_db.clone(),
+        key_derivations.clone(),
+        ProofKey::DecryptionKey(decryption_key),
+    )
+    .await?;
+    if token.revoked {
+        return Err(Error::InvalidRequest("The token is revoked.".into()));
+    }
+    if canonical_address(&token.owner) == canonical_address(&account) {
+        return Err(Error::InvalidRequest(
+            "The token is already owned by the account.".into(),
+        ));
+    }
+    let issued = check_challenge(
+        &challenge_secret,
+        contract_address,
+        &account,
+        &private.challenge,
+        token.platform,
+        &private.user_data,
+    )?;
+    let (statement, statement_hash) = statements.for_platform(token.platform);
+    let account_index = verify_account_proof(
+        &mut concordium_client,
+        &crypto_params,
+        &statement,
+        account,
+        &private,
+    )
+    .await?;
+    // The new proof must be of the same identity and for the same profile.
+    if revealed_attributes(&private.proof) != revealed_attributes(&token.private.proof)
+        || private.first_name != token.private.first_name
+        || private.surname != token.private.surname
+    {
+        return Err(Error::InvalidRequest(
+            "The names do not match the names of the token.".into(),
+        ));
+    }
+    if private.user_data != token.private.user_data {
+        return Err(Error::InvalidRequest(
+            "The user data does not match the user data of the token.".into(),
+        ));
+    }
+    // Names can be shared by several identities, so the credential of the new
+    // account must also be created from the same identity object as the
+    // credential the token was minted with.
+    let token_identity = credential_identity(
+        &mut concordium_client,
+        token.owner,
+        &token.private.proof.credential,
+    )
+    .await?;
+    let identity =
+        credential_identity(&mut concordium_client, account, &private.proof.credential).await?;
+    if identity != token_identity {
+        return Err(Error::InvalidRequest(
+            "The account is not of the same identity as the owner of the token.".into(),
+        ));
+    }
+
+    private.statement_hash = Some(statement_hash);
+
+    let header = payload::PayloadHeader {
+        platform: token.platform,
+        owner: canonical_address(&account),
+        profile_url: token.platform.profile_url(&private.user_data),
+        minted_at: token.issued_at,
+        statement_hash,
+        key_id,
+        // The key of a recovered token is encrypted for the currently configured
+        // designated verifiers if the key of the token was.
+        verifiers: if token.designated_verifiers {
+            verifier_keys.to_vec()
+        } else {
+            Vec::new()
+        },
+        passphrase: passphrase.as_ref().map(|_| payload::PassphraseParams::generate()),
+    };
+    let (aux_data, key) =
+        encrypt_proof_data(&key_derivations, &private, header, passphrase).await?;
+    let (data, commitment, off_chain) = prepare_token_data(off_chain_data, &base_url, aux_data);
+
+    let recover_params = ContractRecoverParams {
+        token_id: ContractTokenId(token_id),
+        owner: account,
+        data,
+        commitment,
+    };
+
+    let update_payload = UpdateContractPayload {
+        amount:       Amount::zero(),
+        address:      contract_address,
+        receive_name: OwnedReceiveName::new_unchecked("mysomeid.recover".into()),
+        message:      OwnedParameter::from_serial(&recover_params).map_err(|_| Error::Internal)?,
+    };
+
+    const RECOVER_ENERGY: Energy = Energy { energy: 10_000 };
+    let nonce_counter = nonce_counter.lock().await;
+    // We only check this here after acquiring the lock. If we do it before we don't
+    // have guarantees due to parallel requests.
+    check_daily_limit(&read_db, max_daily_mints, account, account_index, 0).await?;
+    store_off_chain_data(&read_db, off_chain).await?;
+    reserve_challenge(&read_db, &private.challenge, &issued).await?;
+    let sent = send_sponsored_update(
+        &signer,
+        nonce_counter,
+        &tx_sender,
+        vec![account_index],
+        update_payload,
+        RECOVER_ENERGY,
+    )
+    .await;
+    complete_challenge_use(&read_db, &private.challenge, sent.as_ref().ok()).await;
+    let hash = sent?;
+    supersede_off_chain_data(&read_db, token.data_hash, &hash).await;
+
+    Ok(axum::Json(serde_json::json!({
+        "transactionHash": hash,
+        "decryptionKey": EncryptionKey{key},
+        "proofUrl": make_proof_url((*proof_base_url).clone(), token_id, &key),
+    })))
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RotateKeyParams {
+    /// The token whose key to replace.
+    token_id:       ProofId,
+    /// The current key of the token.
+    decryption_key: DecryptionKey,
+    /// A proof from the owner of the token.
+    #[serde(flatten)]
+    private:        PrivateTokenData,
+    /// If set, the new key of the token is derived from this passphrase.
+    #[serde(default)]
+    passphrase:     Option<String>,
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+/// Replace the key of a token, e.g., if its URL has leaked. The data of the
+/// token is encrypted with a fresh key, and the previous key no longer works.
+/// The request must contain a proof from the owner of the token, since anybody
+/// with the leaked key could otherwise lock the owner out of their token.
+async fn rotate_key(
+    State(ServiceState {
+        mut concordium_client,
+        crypto_params,
+        statements,
+        signer,
+        contract_address,
+        nonce_counter,
+        tx_sender,
+        read_db,
+        max_daily_mints,
+        off_chain_data,
+        base_url,
+        proof_base_url,
+        key_id,
+        verifier_keys,
+        challenge_secret,
+        key_derivations,
+        ..
+    }): State<ServiceState>,
+    axum::Json(RotateKeyParams {
+        token_id,
+        decryption_key,
+        private: owner_proof,
+        passphrase,
+    }): axum::Json<RotateKeyParams>,
+) -> Result<axum::Json<serde_json::Value>, Error> {
+    if owner_proof.proof.proof.version != VERSION_0 {
+        return Err(Error::InvalidRequest(
+            "Only version 0 proofs are supported.".into(),
+        ));
+    }
+    ensure_valid_passphrase(passphrase.as_deref())?;
+    // Do not send an update transaction that the contract would reject.
+    ensure_not_paused(&concordium_client, contract_address).await?;
+    let token = get_proof_worker(
+        token_id,
+        concordium_client.clone(),
+        contract_address,
+        read_db.clone(),
+        key_derivations.clone(),
+        ProofKey::DecryptionKey(decryption_key),
+    )
+    .await?;
+    if token.revoked {
+        return Err(Error::InvalidRequest("The token is revoked.".into()));
+    }
+    let issued = check_challenge(
+        &challenge_secret,
+        contract_address,
+        &token.owner,
+        &owner_proof.challenge,
+        token.platform,
+        &token.private.user_data,
+    )?;
+    let (statement, _) = statements.for_platform(token.platform);
+    let account_index = verify_account_proof(
+        &mut concordium_client,
+        &crypto_params,
+        &statement,
+        token.owner,
+        &owner_proof,
+    )
+    .await?;
+
+    // The data of the token is kept, and only encrypted with the new key.
+    let mut private = token.private;
+    let statement_hash = statements.hash_for_proof(private.statement_hash);
+    private.statement_hash = Some(statement_hash);
+    let header = payload::PayloadHeader {
+        platform: token.platform,
+        owner: canonical_address(&token.owner),
+        profile_url: token
+            .profile_url
+            .unwrap_or_else(|| token.platform.profile_url(&private.user_data)),
+        minted_at: token.issued_at,
+        statement_hash,
+        key_id,
+        verifiers: if token.designated_verifiers {
+            verifier_keys.to_vec()
+        } else {
+            Vec::new()
+        },
+        passphrase: passphrase.as_ref().map(|_| payload::PassphraseParams::generate()),
+    };
+    let (aux_data, key) =
+        encrypt_proof_data(&key_derivations, &private, header, passphrase).await?;
+    let (data, commitment, off_chain) = prepare_token_data(off_chain_data, &base_url, aux_data);
+
+    let update_params = ContractUpdateDataParams {
+        token_id: ContractTokenId(token_id),
+        data,
+        commitment,
+    };
+
+    let update_payload = UpdateContractPayload {
+        amount:       Amount::zero(),
+        address:      contract_address,
+        receive_name: OwnedReceiveName::new_unchecked("mysomeid.updateData".into()),
+        message:      OwnedParameter::from_serial(&update_params).map_err(|_| Error::Internal)?,
+    };
+
+    const UPDATE_DATA_ENERGY: Energy = Energy { energy: 10_000 };
+    let nonce_counter = nonce_counter.lock().await;
+    // We only check this here after acquiring the lock. If we do it before we don't
+    // have guarantees due to parallel requests.
+    check_daily_limit(&read_db, max_daily_mints, token.owner, account_index, 0).await?;
+    store_off_chain_data(&read_db, off_chain).await?;
+    reserve_challenge(&read_db, &owner_proof.challenge, &issued).await?;
+    let sent = send_sponsored_update(
+        &signer,
+        nonce_counter,
+        &tx_sender,
+        vec![account_index],
+        update_payload,
+        UPDATE_DATA_ENERGY,
+    )
+    .await;
+    complete_challenge_use(&read_db, &owner_proof.challenge, sent.as_ref().ok()).await;
+    let hash = sent?;
+    supersede_off_chain_data(&read_db, token.data_hash, &hash).await;
+
+    Ok(axum::Json(serde_json::json!({
+        "transactionHash": hash,
+        "decryptionKey": EncryptionKey{key},
+        "proofUrl": make_proof_url((*proof_base_url).clone(), token_id, &key),
+    })))
+}
+
+/// Return [`Error::Paused`] if the contract is paused.
+async fn ensure_not_paused(
+    concordium_client: &v2::Client,
+    contract_address: ContractAddress,
+) -> Result<(), Error> {
+    let mut contract_client = ContractClient {
+        address: contract_address,
+        client:  concordium_client.clone(),
+    };
+    match contract_client.is_paused().await {
+        Ok(false) => Ok(()),
+        Ok(true) => Err(Error::Paused),
+        Err(e) => {
+            tracing::error!("Error querying the contract: {e:#}");
+            Err(Error::Internal)
+        }
+    }
+}
+
+/// Verify that the proof in `private` is valid for a credential of `account`.
+/// Return the index of the account.
+async fn verify_account_proof(
+    concordium_client: &mut v2::Client,
+    crypto_params: &CryptographicParameters,
+    statement: &Statement<ArCurve, AttributeKind>,
+    account: AccountAddress,
+    private: &PrivateTokenData,
+) -> Result<AccountIndex, Error> {
     let account_info = match concordium_client
         .get_account_info(&account.into(), BlockIdentifier::LastFinal)
         .await
@@ -1278,7 +2606,7 @@ async fn mint_nft(
     };
     let result = statement.verify(
         &private.challenge.challenge,
-        &crypto_params,
+        crypto_params,
         private.proof.credential.as_ref(),
         &commitments,
         &private.proof.proof.value,
@@ -1287,78 +2615,232 @@ async fn mint_nft(
     if !result {
         return Err(Error::InvalidRequest("Proof is not valid.".into()));
     }
+    Ok(account_info.account_index)
+}
 
-    let proof_data = common::to_bytes(&private);
-
-    let (aux_data, key) = {
-        let mut rng = rand::thread_rng();
+/// The attributes of a credential that are fixed by the identity object the
+/// account was created from. All accounts of an identity share them.
+#[derive(PartialEq, Eq)]
+struct CredentialIdentity {
+    /// The identity provider that issued the identity.
+    ip_identity: IpIdentity,
+    /// The month the identity object was created.
+    created_at:  YearMonth,
+    /// The month until which the identity object is valid.
+    valid_to:    YearMonth,
+}
 
-        // We have verified the request. Now we mint.
-        let key = Aes256Gcm::generate_key(&mut rng);
-        let cipher = Aes256Gcm::new(&key);
-        let nonce = rng.gen::<[u8; 12]>();
-        drop(rng);
-        let nonce = Nonce::from_slice(&nonce);
-        let mut aux_data = {
-            let mut aux_data = vec![0u8; 4]; // version number.
-            aux_data.extend_from_slice(nonce);
-            aux_data
-        };
-        let mut ciphertext = cipher
-        .encrypt(nonce, &proof_data[..])
-        // TODO: Log error
-        .map_err(|_| Error::Internal)?;
-        aux_data.append(&mut ciphertext);
-        (aux_data, key.into())
+/// Look up the identity attributes of the credential `credential` of
+/// `account`.
+async fn credential_identity(
+    concordium_client: &mut v2::Client,
+    account: AccountAddress,
+    credential: &CredentialRegistrationID,
+) -> Result<CredentialIdentity, Error> {
+    let account_info = match concordium_client
+        .get_account_info(&account.into(), BlockIdentifier::LastFinal)
+        .await
+    {
+        Ok(ai) => ai.response,
+        Err(e) if e.is_not_found() => {
+            return Err(Error::InvalidRequest(
+                "Account does not exist on the chain.".into(),
+            ));
+        }
+        Err(e) => {
+            tracing::error!("Error querying account: {e}");
+            return Err(Error::Internal);
+        }
     };
+    account_info
+        .account_credentials
+        .into_values()
+        .find_map(|cred| match cred.value {
+            AccountCredentialWithoutProofs::Normal { cdv, .. }
+                if &cdv.cred_id == credential.as_ref() =>
+            {
+                Some(CredentialIdentity {
+                    ip_identity: cdv.ip_identity,
+                    created_at:  cdv.policy.created_at,
+                    valid_to:    cdv.policy.valid_to,
+                })
+            }
+            _ => None,
+        })
+        .ok_or_else(|| {
+            Error::InvalidRequest("The requested account does not have a matching credential.".into())
+        })
+}
 
-    let mint_params = ContractMintParams {
-        owner: account,
-        platform,
-        data: aux_data,
+/// Encrypt the private data of a token in version 1 of the data format with
+/// the given header. The key is derived from the passphrase if one is given,
+/// and is fresh otherwise. Return the data to store for the token, and the
+/// key.
+async fn encrypt_proof_data(
+    key_derivations: &Arc<tokio::sync::Semaphore>,
+    private: &PrivateTokenData,
+    header: payload::PayloadHeader,
+    passphrase: Option<String>,
+) -> Result<(Vec<u8>, [u8; 32]), Error> {
+    let proof_data = common::to_bytes(private);
+    let sealed = if passphrase.is_some() {
+        derive_key_blocking(key_derivations, move || {
+            payload::seal(&header, &proof_data, passphrase.as_deref())
+        })
+        .await?
+    } else {
+        payload::seal(&header, &proof_data, None)
     };
+    sealed.map_err(|e| {
+        tracing::error!("Unable to encrypt proof data: {e:#}");
+        Error::Internal
+    })
+}
 
-    let update_payload = UpdateContractPayload {
-        amount:       Amount::zero(),
-        address:      contract_address,
-        receive_name: OwnedReceiveName::new_unchecked("mysomeid.mint".into()),
-        // TODO: Log error.
-        message:      OwnedParameter::from_serial(&mint_params).map_err(|_| Error::Internal)?,
+/// Run `f`, which derives a key from a passphrase, on a blocking thread.
+/// Deriving the key is deliberately slow and memory-hungry, so at most
+/// `--max-key-derivations` are run at a time, and [`Error::Busy`] is returned
+/// if as many are already running. The permit is held by the blocking task, so
+/// that it is not released if the request is dropped.
+async fn derive_key_blocking<T: Send + 'static>(
+    key_derivations: &Arc<tokio::sync::Semaphore>,
+    f: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, Error> {
+    let Ok(permit) = key_derivations.clone().try_acquire_owned() else {
+        return Err(Error::Busy);
     };
+    tokio::task::spawn_blocking(move || {
+        let result = f();
+        drop(permit);
+        result
+    })
+    .await
+    .map_err(|e| {
+        tracing::error!("Deriving a key from a passphrase panicked: {e:#}");
+        Error::Internal
+    })
+}
 
-    // now we need to actually mint.
-    // First make the transaction.
-    let mut nonce_counter = nonce_counter.lock().await;
+/// Minimum length of passphrases from which keys of tokens are derived.
+const MIN_PASSPHRASE_LEN: usize = 8;
 
-    // We only check this here after acquiring the lock. If we do it before we don't
-    // have guarantees due to parallel requests.
-    match read_db
-        .get_num_submitted_last_day(account_info.account_index)
-        .await
-    {
+/// Ensure that the passphrase, if any, is long enough.
+fn ensure_valid_passphrase(passphrase: Option<&str>) -> Result<(), Error> {
+    match passphrase {
+        Some(passphrase) if passphrase.chars().count() < MIN_PASSPHRASE_LEN => {
+            Err(Error::InvalidRequest(format!(
+                "The passphrase must be at least {MIN_PASSPHRASE_LEN} characters."
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Split the encrypted data of a token into the data and commitment to store in
+/// the contract, and the data to store off-chain, if any.
+///
+/// If the data is stored off-chain, only a commitment to it is stored in the
+/// contract. The data is inserted into the database just before the
+/// transaction is sent.
+#[allow(clippy::type_complexity)]
+fn prepare_token_data(
+    off_chain_data: bool,
+    base_url: &Url,
+    aux_data: Vec<u8>,
+) -> (Vec<u8>, Option<DataCommitment>, Option<([u8; 32], Vec<u8>)>) {
+    if off_chain_data {
+        let hash: [u8; 32] = sha2::Sha256::digest(&aux_data).into();
+        let commitment = DataCommitment {
+            hash,
+            locator: make_proof_data_url(base_url.clone(), &hash).into(),
+        };
+        (Vec::new(), Some(commitment), Some((hash, aux_data)))
+    } else {
+        (aux_data, None, None)
+    }
+}
+
+/// Return [`Error::TooManyRequest`] if `account` has reached the daily limit of
+/// sponsored transactions, counting `pending` transactions for it which are
+/// not yet recorded in the database.
+///
+/// This must be called while holding the lock of the nonce counter, so that
+/// parallel requests cannot exceed the limit.
+async fn check_daily_limit(
+    read_db: &db::ReadDatabase,
+    max_daily_mints: u32,
+    account: AccountAddress,
+    account_index: AccountIndex,
+    pending: i64,
+) -> Result<(), Error> {
+    match read_db.get_num_submitted_last_day(account_index).await {
         Ok(num) => {
-            if num > max_daily_mints.into() {
+            if num + pending > max_daily_mints.into() {
                 tracing::warn!("Too many daily requests from {}.", account);
                 return Err(Error::TooManyRequest);
             }
+            Ok(())
         }
         Err(e) => {
             tracing::error!("Error querying database: {e:#}");
+            Err(Error::Internal)
+        }
+    }
+}
+
+/// Store the data of a token that is kept off-chain, if any. This must be done
+/// before the transaction minting the token is sent.
+async fn store_off_chain_data(
+    read_db: &db::ReadDatabase,
+    off_chain: Option<([u8; 32], Vec<u8>)>,
+) -> Result<(), Error> {
+    if let Some((hash, data)) = off_chain {
+        if let Err(e) = read_db.insert_proof_data(&hash, &data).await {
+            tracing::error!("Error storing proof data: {e:#}");
             return Err(Error::Internal);
         }
     }
+    Ok(())
+}
 
+/// Record that the off-chain data of a token with the given hash, if any, is
+/// replaced by the transaction with `tx_hash`, so that the data is deleted
+/// once the transaction is finalized and the old key no longer decrypts
+/// anything that is served.
+async fn supersede_off_chain_data(
+    read_db: &db::ReadDatabase,
+    data_hash: Option<[u8; 32]>,
+    tx_hash: &concordium::types::hashes::TransactionHash,
+) {
+    if let Some(data_hash) = data_hash {
+        if let Err(e) = read_db.supersede_proof_data(&data_hash, tx_hash).await {
+            tracing::error!("Error recording superseded proof data: {e:#}");
+        }
+    }
+}
+
+/// Send a contract update on behalf of the `sponsorees` accounts with the
+/// next nonce. The lock of the nonce counter is released once the transaction
+/// is enqueued. Return the hash of the transaction once it is recorded in the
+/// database.
+async fn send_sponsored_update(
+    signer: &WalletAccount,
+    mut nonce_counter: tokio::sync::MutexGuard<'_, concordium::types::Nonce>,
+    tx_sender: &tokio::sync::mpsc::Sender<TxChannelData>,
+    sponsorees: Vec<AccountIndex>,
+    update_payload: UpdateContractPayload,
+    energy: Energy,
+) -> Result<concordium::types::hashes::TransactionHash, Error> {
     let expiry = common::types::TransactionTime::from_seconds(
         chrono::offset::Utc::now().timestamp() as u64 + 3600,
     ); // 1h expiry.
-    const MINT_ENERGY: Energy = Energy { energy: 10_000 };
     let tx = transactions::send::update_contract(
-        &*signer,
+        signer,
         signer.address,
         *nonce_counter,
         expiry,
         update_payload,
-        MINT_ENERGY,
+        energy,
     );
     let bi = BlockItem::from(tx);
     let hash = bi.hash();
@@ -1366,7 +2848,7 @@ async fn mint_nft(
     if let Err(e) = tx_sender.try_send(TxChannelData {
         nonce: *nonce_counter,
         response: sender,
-        sponsoree: account_info.account_index,
+        sponsorees,
         bi,
     }) {
         match e {
@@ -1394,17 +2876,280 @@ async fn mint_nft(
             return Err(Error::Internal);
         }
 
-        Ok(axum::Json(
-            serde_json::json!({ "transactionHash": hash, "decryptionKey": EncryptionKey{key} }),
-        ))
+        Ok(hash)
+    }
+}
+
+/// Energy for minting a single token.
+const MINT_ENERGY: Energy = Energy { energy: 10_000 };
+
+/// A verified request to mint a token, waiting to be submitted in a batch.
+struct MintRequest {
+    params:        ContractMintParams,
+    account:       AccountAddress,
+    account_index: AccountIndex,
+    /// The data of the token to store off-chain, if any.
+    off_chain:     Option<([u8; 32], Vec<u8>)>,
+    /// The challenge of the proof, which is reserved until the request is
+    /// answered.
+    challenge:     Challenge,
+    /// The request is rejected if it is not submitted by this time.
+    deadline:      tokio::time::Instant,
+    /// Answered once the transaction the token is minted in is submitted.
+    response:      tokio::sync::oneshot::Sender<Result<SubmittedMint, Error>>,
+}
+
+/// A mint request that has been submitted in a batch.
+struct SubmittedMint {
+    transaction_hash: concordium::types::hashes::TransactionHash,
+    /// Resolves to the id of the minted token once the transaction is
+    /// finalized. It is closed without a value if the token cannot be
+    /// determined.
+    token_id:         tokio::sync::oneshot::Receiver<ProofId>,
+}
+
+/// Collects mint requests and submits them in batches with the contract's
+/// `batchMint` entrypoint.
+struct MintBatcher {
+    signer:            Arc<WalletAccount>,
+    nonce_counter:     Arc<tokio::sync::Mutex<concordium::types::Nonce>>,
+    tx_sender:         tokio::sync::mpsc::Sender<TxChannelData>,
+    read_db:           db::ReadDatabase,
+    max_daily_mints:   u32,
+    concordium_client: v2::Client,
+    contract_address:  ContractAddress,
+    /// How long to wait for more requests after the first request of a batch.
+    window:            std::time::Duration,
+    max_batch_size:    usize,
+}
+
+impl MintBatcher {
+    /// Submit batches of requests until the channel is closed.
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn run(
+        self,
+        mut receiver: tokio::sync::mpsc::Receiver<MintRequest>,
+    ) -> anyhow::Result<()> {
+        while let Some(first) = receiver.recv().await {
+            let deadline = tokio::time::Instant::now() + self.window;
+            let mut batch = vec![first];
+            while batch.len() < self.max_batch_size {
+                match tokio::time::timeout_at(deadline, receiver.recv()).await {
+                    Ok(Some(request)) => batch.push(request),
+                    Ok(None) | Err(_) => break,
+                }
+            }
+            self.submit_batch(batch).await;
+        }
+        Ok(())
+    }
+
+    /// Send a transaction minting the tokens of the requests that are within
+    /// the daily limit. Each request is answered once the transaction is
+    /// submitted, or with an error, and then sent the token minted for it once
+    /// the transaction is finalized. Requests that are no longer waiting for
+    /// an answer are dropped, since the key of their token would be lost.
+    async fn submit_batch(&self, batch: Vec<MintRequest>) {
+        let nonce_counter = self.nonce_counter.lock().await;
+
+        // Requests of the same account in the batch count towards its limit.
+        let mut pending = HashMap::new();
+        let mut mints = Vec::with_capacity(batch.len());
+        let mut sponsorees = Vec::with_capacity(batch.len());
+        let mut responses = Vec::with_capacity(batch.len());
+        for MintRequest {
+            params,
+            account,
+            account_index,
+            off_chain,
+            challenge,
+            deadline,
+            response,
+        } in batch
+        {
+            if response.is_closed() {
+                tracing::debug!("Dropping a mint request that was abandoned.");
+                complete_challenge_use(&self.read_db, &challenge, None).await;
+                continue;
+            }
+            if tokio::time::Instant::now() >= deadline {
+                tracing::warn!("Rejecting a mint request that timed out before it was submitted.");
+                self.answer(&challenge, response, Err(Error::Busy)).await;
+                continue;
+            }
+            let pending = pending.entry(account_index.index).or_insert(0);
+            let checked = match check_daily_limit(
+                &self.read_db,
+                self.max_daily_mints,
+                account,
+                account_index,
+                *pending,
+            )
+            .await
+            {
+                Ok(()) => store_off_chain_data(&self.read_db, off_chain).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = checked {
+                self.answer(&challenge, response, Err(e)).await;
+                continue;
+            }
+            *pending += 1;
+            mints.push(params);
+            sponsorees.push(account_index);
+            responses.push((challenge, response));
+        }
+        if responses.is_empty() {
+            return;
+        }
+
+        let energy = Energy {
+            energy: MINT_ENERGY.energy * mints.len() as u64,
+        };
+        let message = match OwnedParameter::from_serial(&ContractBatchMintParams { mints }) {
+            Ok(message) => message,
+            Err(_) => {
+                tracing::error!("The batch of {} mints is too large.", responses.len());
+                for (challenge, response) in responses {
+                    self.answer(&challenge, response, Err(Error::Internal))
+                        .await;
+                }
+                return;
+            }
+        };
+        let update_payload = UpdateContractPayload {
+            amount: Amount::zero(),
+            address: self.contract_address,
+            receive_name: OwnedReceiveName::new_unchecked("mysomeid.batchMint".into()),
+            message,
+        };
+
+        let sent = send_sponsored_update(
+            &self.signer,
+            nonce_counter,
+            &self.tx_sender,
+            sponsorees,
+            update_payload,
+            energy,
+        )
+        .await;
+        let transaction_hash = match sent {
+            Ok(transaction_hash) => transaction_hash,
+            Err(e) => {
+                for (challenge, response) in responses {
+                    self.answer(&challenge, response, Err(e.clone())).await;
+                }
+                return;
+            }
+        };
+        let mut token_senders = Vec::with_capacity(responses.len());
+        for (challenge, response) in responses {
+            let (token_sender, token_id) = tokio::sync::oneshot::channel();
+            token_senders.push(token_sender);
+            let submitted = SubmittedMint {
+                transaction_hash,
+                token_id,
+            };
+            self.answer(&challenge, response, Ok(submitted)).await;
+        }
+        tokio::spawn(reply_minted_tokens(
+            self.concordium_client.clone(),
+            self.contract_address,
+            transaction_hash,
+            token_senders,
+        ));
+    }
+
+    /// Record the use of the challenge of a request and answer it. This is done
+    /// here rather than by the request handler, which may be abandoned.
+    async fn answer(
+        &self,
+        challenge: &Challenge,
+        response: tokio::sync::oneshot::Sender<Result<SubmittedMint, Error>>,
+        result: Result<SubmittedMint, Error>,
+    ) {
+        let transaction_hash = result
+            .as_ref()
+            .ok()
+            .map(|submitted| &submitted.transaction_hash);
+        complete_challenge_use(&self.read_db, challenge, transaction_hash).await;
+        let _ = response.send(result);
+    }
+}
+
+/// Wait until the batch mint transaction is finalized, and send each request
+/// the token minted for it. The contract mints the tokens in the order of the
+/// requests. If the tokens cannot be determined the senders are dropped.
+async fn reply_minted_tokens(
+    mut concordium_client: v2::Client,
+    contract_address: ContractAddress,
+    transaction_hash: concordium::types::hashes::TransactionHash,
+    token_senders: Vec<tokio::sync::oneshot::Sender<ProofId>>,
+) {
+    let token_ids =
+        match minted_token_ids(&mut concordium_client, contract_address, &transaction_hash).await {
+            Ok(token_ids) => token_ids,
+            Err(e) => {
+                tracing::error!("Unable to get the tokens minted by {transaction_hash}: {e:#}");
+                return;
+            }
+        };
+    if token_ids.len() != token_senders.len() {
+        tracing::error!(
+            "Transaction {transaction_hash} minted {} tokens for {} requests.",
+            token_ids.len(),
+            token_senders.len()
+        );
+        return;
+    }
+    for (token_sender, token_id) in token_senders.into_iter().zip(token_ids) {
+        let _ = token_sender.send(token_id);
     }
 }
 
+/// Wait until the transaction is finalized and return the IDs of the tokens it
+/// minted, in order.
+async fn minted_token_ids(
+    concordium_client: &mut v2::Client,
+    contract_address: ContractAddress,
+    transaction_hash: &concordium::types::hashes::TransactionHash,
+) -> anyhow::Result<Vec<ProofId>> {
+    let mut retries_left = 10;
+    let summary = loop {
+        match concordium_client
+            .wait_until_finalized(transaction_hash)
+            .await
+        {
+            Ok((_, summary)) => break summary,
+            // The transaction sender might not have sent the transaction yet.
+            Err(e) if e.is_not_found() && retries_left > 0 => {
+                retries_left -= 1;
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    };
+    if let Some(reason) = summary.is_rejected_account_transaction() {
+        anyhow::bail!("The transaction was rejected: {reason:?}");
+    }
+    let token_ids = get_contract_events(&summary)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(address, _)| address == &contract_address)
+        .flat_map(|(_, events)| events)
+        .filter_map(|event| match event {
+            MysomeidEvent::TokenMinted(event) => Some(event.token_id),
+            MysomeidEvent::Cis2(_) => None,
+        })
+        .collect();
+    Ok(token_ids)
+}
+
 struct TxChannelData {
-    nonce:     concordium_rust_sdk::types::Nonce,
-    response:  tokio::sync::oneshot::Sender<()>,
-    bi:        BlockItem<EncodedPayload>,
-    sponsoree: AccountIndex,
+    nonce:      concordium_rust_sdk::types::Nonce,
+    response:   tokio::sync::oneshot::Sender<()>,
+    bi:         BlockItem<EncodedPayload>,
+    sponsorees: Vec<AccountIndex>,
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
@@ -1420,12 +3165,12 @@ async fn tx_sender(
         nonce,
         response,
         bi,
-        sponsoree,
+        sponsorees,
     }) = channel.recv().await
     {
         db_sender
             .send(db::DatabaseOperation::InsertTransaction {
-                sponsoree,
+                sponsorees,
                 tx_hash: bi.hash(),
                 tx: bi.clone(),
                 response,
@@ -1475,16 +3220,60 @@ async fn tx_sender(
 #[derive(concordium_std::Serial, concordium_std::Deserial)]
 struct ContractMintParams {
     /// Owner of the newly minted token.
-    owner:    AccountAddress,
+    owner:       AccountAddress,
     /// Platform associated with the newly minted token.
-    platform: SupportedPlatform,
+    platform:    SupportedPlatform,
     /// The data, which includes the proof, challenge, name, social media URL
     /// (e.g linkedin URL).
     #[concordium(size_length = 2)]
-    data:     Vec<u8>,
+    data:        Vec<u8>,
+    /// Time until which the token is valid. If not set the token does not
+    /// expire.
+    valid_until: Option<concordium_std::Timestamp>,
+    /// Commitment to the data if it is stored off-chain. In that case `data`
+    /// is empty.
+    commitment:  Option<DataCommitment>,
 }
 
-#[derive(Debug, thiserror::Error)]
+/// The parameter for the contract function `batchMint` which mints a token for
+/// each of the given mints.
+#[derive(concordium_std::Serial)]
+struct ContractBatchMintParams {
+    #[concordium(size_length = 2)]
+    mints: Vec<ContractMintParams>,
+}
+
+/// The parameter for the contract function `updateData` which replaces the data
+/// of a token.
+#[derive(concordium_std::Serial)]
+struct ContractUpdateDataParams {
+    /// The token to update.
+    token_id:   ContractTokenId,
+    /// The new data of the token.
+    #[concordium(size_length = 2)]
+    data:       Vec<u8>,
+    /// Commitment to the data if it is stored off-chain. In that case `data`
+    /// is empty.
+    commitment: Option<DataCommitment>,
+}
+
+/// The parameter for the contract function `recover` which moves a token to
+/// a new account.
+#[derive(concordium_std::Serial)]
+struct ContractRecoverParams {
+    /// The token to recover.
+    token_id:   ContractTokenId,
+    /// The new owner of the token.
+    owner:      AccountAddress,
+    /// The data, which includes a proof of the new owner.
+    #[concordium(size_length = 2)]
+    data:       Vec<u8>,
+    /// Commitment to the data if it is stored off-chain. In that case `data`
+    /// is empty.
+    commitment: Option<DataCommitment>,
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
 /// Possible errors returned by any of the endpoints.
 pub enum Error {
     #[error("Invalid request")]
@@ -1499,6 +3288,12 @@ pub enum Error {
     Busy,
     #[error("Too many requests in the last day.")]
     TooManyRequest,
+    #[error("The proof has been erased.")]
+    Erased,
+    #[error("The contract is paused.")]
+    Paused,
+    #[error("The challenge does not match the account, platform and user data.")]
+    ChallengeMismatch,
 }
 
 impl axum::response::IntoResponse for Error {
@@ -1525,6 +3320,22 @@ impl axum::response::IntoResponse for Error {
                 StatusCode::TOO_MANY_REQUESTS,
                 axum::Json("Too many mint requests in the last day.".into()),
             ),
+            Error::Erased => (
+                StatusCode::GONE,
+                axum::Json("The proof has been erased by its owner.".into()),
+            ),
+            Error::Paused => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                axum::Json("Minting is paused. Try later.".into()),
+            ),
+            Error::ChallengeMismatch => (
+                StatusCode::BAD_REQUEST,
+                axum::Json(
+                    "The challenge of the proof does not match the account, platform and user \
+                     data."
+                        .into(),
+                ),
+            ),
         };
         r.into_response()
     }
@@ -1566,6 +3377,45 @@ async fn set_shutdown(
     Ok(())
 }
 
+/// Periodically query the summary of the contract and export it as Prometheus
+/// gauges. Failures are logged and the query is retried at the next interval.
+async fn summary_metrics(mut contract_client: ContractClient, interval: std::time::Duration) {
+    let mut interval = tokio::time::interval(interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let summary = match contract_client.view_summary().await {
+            Ok(Some(summary)) => summary,
+            Ok(None) => {
+                tracing::warn!("The contract did not return a summary.");
+                continue;
+            }
+            Err(e) => {
+                tracing::warn!("Unable to query the summary of the contract: {e:#}");
+                continue;
+            }
+        };
+        metrics::gauge!("mysomeid_next_token_id", summary.next_token_id as f64);
+        for (platform, counts) in summary.platforms {
+            let platform = String::from_utf8_lossy(&platform).into_owned();
+            metrics::gauge!(
+                "mysomeid_tokens",
+                counts.active as f64,
+                "platform" => platform.clone(),
+                "status" => "active"
+            );
+            metrics::gauge!(
+                "mysomeid_tokens",
+                counts.revoked as f64,
+                "platform" => platform,
+                "status" => "revoked"
+            );
+        }
+        metrics::gauge!("mysomeid_owners", summary.owners as f64);
+        metrics::gauge!("mysomeid_role_holders", summary.role_holders as f64);
+    }
+}
+
 /// Like `tokio::spawn` but the provided future is modified so that
 /// once it terminates it sends a message on the provided channel.
 /// This is sent regardless of how the future terminates, as long as it
@@ -1651,7 +3501,7 @@ async fn listen_concordium_worker(
             );
             let mut transaction_events = Vec::new();
             for summary in summaries {
-                let events = get_cis2_events(&summary);
+                let events = get_contract_events(&summary);
                 if let Some(events) = events {
                     if !events.is_empty() {
                         transaction_events.push((summary.hash, events));
@@ -1774,23 +3624,25 @@ pub async fn listen_concordium(
     }
 }
 
-/// Attempt to extract CIS2 events from the block item.
+/// Attempt to extract CIS2 and `TokenMinted` events from the block item.
 /// If the transaction is a smart contract init or update transaction then
-/// attempt to parse the events as CIS2 events. If any of the events fail
-/// parsing then the logs for that section of execution are ignored, since it
-/// indicates an error in the contract.
+/// attempt to parse the events as CIS2 or `TokenMinted` events. If any of the
+/// events fail parsing then the logs for that section of execution are ignored,
+/// since it indicates an error in the contract.
 ///
-/// The return value of [`None`] means there are no understandable CIS2 logs
+/// The return value of [`None`] means there are no understandable logs
 /// produced.
-fn get_cis2_events(bi: &BlockItemSummary) -> Option<Vec<(ContractAddress, Vec<cis2::Event>)>> {
+fn get_contract_events(
+    bi: &BlockItemSummary,
+) -> Option<Vec<(ContractAddress, Vec<MysomeidEvent>)>> {
     match bi.contract_update_logs() {
         Some(log_iter) => Some(
             log_iter
                 .flat_map(|(ca, logs)| {
                     match logs
                         .iter()
-                        .map(cis2::Event::try_from)
-                        .collect::<Result<Vec<cis2::Event>, _>>()
+                        .map(MysomeidEvent::try_from)
+                        .collect::<Result<Vec<MysomeidEvent>, _>>()
                     {
                         Ok(events) => Some((ca, events)),
                         Err(_) => None,
@@ -1800,13 +3652,13 @@ fn get_cis2_events(bi: &BlockItemSummary) -> Option<Vec<(ContractAddress, Vec<ci
         ),
         None => {
             let init = bi.contract_init()?;
-            let cis2 = init
+            let events = init
                 .events
                 .iter()
-                .map(cis2::Event::try_from)
-                .collect::<Result<Vec<cis2::Event>, _>>()
+                .map(MysomeidEvent::try_from)
+                .collect::<Result<Vec<MysomeidEvent>, _>>()
                 .ok()?;
-            Some(vec![(init.address, cis2)])
+            Some(vec![(init.address, events)])
         }
     }
 }
@@ -1835,3 +3687,111 @@ async fn await_and_report<E: std::fmt::Display>(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: [u8; 32] = [7u8; 32];
+    const CONTRACT: ContractAddress = ContractAddress {
+        index:    1234,
+        subindex: 0,
+    };
+    const ACCOUNT: AccountAddress = AccountAddress([1u8; 32]);
+    const USER_DATA: &str = "john-doe";
+
+    fn expiry_in(duration: chrono::Duration) -> i64 {
+        (chrono::Utc::now() + duration).timestamp_millis()
+    }
+
+    fn check(
+        secret: &[u8],
+        contract_address: ContractAddress,
+        account: &AccountAddress,
+        challenge: &Challenge,
+        user_data: &str,
+    ) -> Result<db::IssuedChallenge, Error> {
+        check_challenge(
+            secret,
+            contract_address,
+            account,
+            challenge,
+            SupportedPlatform::LinkedIn,
+            user_data,
+        )
+    }
+
+    #[test]
+    /// Test that an issued challenge is accepted for the account, including its
+    /// aliases, the contract, the platform and the user data it was issued for.
+    fn test_check_challenge() {
+        let expiry = expiry_in(chrono::Duration::minutes(10));
+        let challenge = make_challenge(
+            &SECRET,
+            CONTRACT,
+            &ACCOUNT,
+            expiry,
+            SupportedPlatform::LinkedIn,
+            USER_DATA,
+        );
+        let issued = check(&SECRET, CONTRACT, &ACCOUNT, &challenge, USER_DATA)
+            .expect("The challenge is accepted.");
+        assert_eq!(issued.account, canonical_address(&ACCOUNT));
+        assert_eq!(issued.contract, CONTRACT);
+        assert_eq!(issued.platform, "li");
+        assert_eq!(issued.user_data, USER_DATA);
+        assert_eq!(issued.expiry.timestamp_millis(), expiry);
+
+        let mut alias = ACCOUNT;
+        alias.0[31] = 2;
+        check(&SECRET, CONTRACT, &alias, &challenge, USER_DATA)
+            .expect("The challenge is accepted for an alias.");
+    }
+
+    #[test]
+    /// Test that a challenge is rejected if it was modified, or was issued for
+    /// another account, contract or user data, or with another secret.
+    fn test_check_challenge_mismatch() {
+        let challenge = make_challenge(
+            &SECRET,
+            CONTRACT,
+            &ACCOUNT,
+            expiry_in(chrono::Duration::minutes(10)),
+            SupportedPlatform::LinkedIn,
+            USER_DATA,
+        );
+        let mut tampered_tag = challenge;
+        tampered_tag.challenge[31] ^= 1;
+        let mut tampered_expiry = challenge;
+        tampered_expiry.challenge[CHALLENGE_EXPIRY_LEN - 1] ^= 1;
+        let other_account = AccountAddress([2u8; 32]);
+        let other_contract = ContractAddress::new(1235, 0);
+        for result in [
+            check(&SECRET, CONTRACT, &ACCOUNT, &tampered_tag, USER_DATA),
+            check(&SECRET, CONTRACT, &ACCOUNT, &tampered_expiry, USER_DATA),
+            check(&SECRET, CONTRACT, &other_account, &challenge, USER_DATA),
+            check(&SECRET, other_contract, &ACCOUNT, &challenge, USER_DATA),
+            check(&SECRET, CONTRACT, &ACCOUNT, &challenge, "jane-doe"),
+            check(&[8u8; 32], CONTRACT, &ACCOUNT, &challenge, USER_DATA),
+        ] {
+            assert!(matches!(result, Err(Error::ChallengeMismatch)));
+        }
+    }
+
+    #[test]
+    /// Test that an expired challenge is rejected.
+    fn test_check_challenge_expired() {
+        let challenge = make_challenge(
+            &SECRET,
+            CONTRACT,
+            &ACCOUNT,
+            expiry_in(-chrono::Duration::seconds(1)),
+            SupportedPlatform::LinkedIn,
+            USER_DATA,
+        );
+        assert!(matches!(
+            check(&SECRET, CONTRACT, &ACCOUNT, &challenge, USER_DATA),
+            Err(Error::InvalidRequest(_))
+        ));
+    }
+}
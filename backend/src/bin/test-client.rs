@@ -50,6 +50,12 @@ struct Api {
         help = "URL of the linked in user. Just the user specific part."
     )]
     user_data: String,
+    #[clap(
+        long = "challenge-url",
+        help = "URL where challenges for proofs are issued.",
+        default_value = "http://localhost:8080/v1/proof/challenge"
+    )]
+    challenge_url: url::Url,
     #[clap(
         long = "mint-url",
         help = "URL where minting data is submitted.",
@@ -117,9 +123,33 @@ async fn main() -> anyhow::Result<()> {
             .reveal_attribute(AttributeTag(0))
             .reveal_attribute(AttributeTag(1)),
     };
-    let challenge = [0u8; 32]; // TODO: :)
+    let account = account_address_from_registration_id(credential.as_ref());
+    let platform = backend::SupportedPlatform::LinkedIn;
+
+    // The proof is made for a challenge issued by the service for the account,
+    // the platform and the user data.
+    let client = reqwest::Client::new();
+    let mut challenge_url = app.challenge_url;
+    challenge_url
+        .query_pairs_mut()
+        .append_pair("account", &account.to_string())
+        .append_pair("platform", &platform.to_string())
+        .append_pair("userData", &app.user_data);
+    let ChallengeResponse { challenge } = client
+        .get(challenge_url)
+        .send()
+        .await?
+        .error_for_status()
+        .context("Unable to get a challenge.")?
+        .json()
+        .await?;
     let proof = statement
-        .prove(&global_context, &challenge, &data.id_object.alist, &cc)
+        .prove(
+            &global_context,
+            &challenge.challenge,
+            &data.id_object.alist,
+            &cc,
+        )
         .context("Unable to prove.")?;
 
     let first_name = data
@@ -137,24 +167,24 @@ async fn main() -> anyhow::Result<()> {
         .context("Surname not present.")?
         .clone();
 
-    let account = account_address_from_registration_id(credential.as_ref());
-
     let mint_params = MintParams {
         account,
-        platform: backend::SupportedPlatform::LinkedIn,
+        platform,
         private: PrivateTokenData {
             first_name,
             surname,
             user_data: app.user_data,
-            challenge: Challenge { challenge },
+            challenge,
             proof: ProofWithContext {
                 credential,
                 proof: Versioned::new(VERSION_0, proof),
             },
+            statement_hash: None,
         },
+        designated_verifiers: false,
+        passphrase: None,
     };
 
-    let client = reqwest::Client::new();
     let mint_response: serde_json::Value = client
         .post(app.mint_url)
         .json(&mint_params)
@@ -167,6 +197,11 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[derive(serde::Deserialize)]
+struct ChallengeResponse {
+    challenge: Challenge,
+}
+
 #[derive(serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct StoredIdObject {
@@ -14,7 +14,8 @@ use postgres_types::FromSql;
 use tokio::task::JoinHandle;
 use tokio_postgres::NoTls;
 
-use crate::ProofId;
+use crate::{canonical_address, MysomeidEvent, ProofId};
+use std::collections::HashMap;
 
 const SCHEMA: &str = include_str!("../resources/schema.sql");
 
@@ -60,9 +61,11 @@ pub struct Database {
     pub client:        tokio_postgres::Client,
     connection_handle: JoinHandle<Result<(), tokio_postgres::Error>>,
     insert_tx:         tokio_postgres::Statement,
+    insert_sponsorees: tokio_postgres::Statement,
     insert_event:      tokio_postgres::Statement,
     update_checkpoint: tokio_postgres::Statement,
     mark_transaction:  tokio_postgres::Statement,
+    delete_superseded: tokio_postgres::Statement,
 }
 
 impl Database {
@@ -78,10 +81,18 @@ impl Database {
                  status) VALUES ($1, $2, $3, $4, $5) RETURNING id",
             )
             .await?;
+        let insert_sponsorees = client
+            .prepare(
+                "INSERT INTO concordium_transaction_sponsorees (tx_hash, account_index, \
+                 insert_time) SELECT $1, UNNEST($2::INT8[]), $3",
+            )
+            .await?;
         let insert_event = client
             .prepare(
                 "INSERT INTO concordium_events (id, tx_hash, event_type, owner, token_id, \
-                 block_time) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id",
+                 block_time, platform) VALUES ($1, $2, $3, $4, $5, $6, COALESCE($7, (SELECT \
+                 platform FROM concordium_events WHERE token_id = $5 AND event_type = 'mint' \
+                 LIMIT 1))) RETURNING id",
             )
             .await?;
         let update_checkpoint = client
@@ -95,6 +106,10 @@ impl Database {
             .prepare("UPDATE concordium_transactions SET status = $2 WHERE tx_hash = $1")
             .await?;
 
+        let delete_superseded = client
+            .prepare("DELETE FROM proof_data WHERE superseded_by = $1")
+            .await?;
+
         let starting_height = if let Some(row) = client
             .query_opt("SELECT last_processed_height FROM checkpoints LIMIT 1", &[])
             .await?
@@ -112,33 +127,58 @@ impl Database {
             client,
             connection_handle,
             insert_tx,
+            insert_sponsorees,
             insert_event,
             update_checkpoint,
             mark_transaction,
+            delete_superseded,
         };
         Ok((starting_height.map(|x| (x as u64).into()), id, db))
     }
 
+    /// Record a transaction sent for the given accounts. A transaction sent
+    /// for several accounts, or several times for the same account, has its
+    /// accounts recorded separately.
     pub async fn insert_tx(
         &self,
-        sponsoree: AccountIndex,
+        sponsorees: &[AccountIndex],
         tx_hash: TransactionHash,
         tx: &BlockItem<EncodedPayload>,
     ) -> anyhow::Result<()> {
         let tx_data = to_bytes(tx);
+        let account_index = match sponsorees {
+            [sponsoree] => Some(sponsoree.index as i64),
+            _ => None,
+        };
+        let now = chrono::Utc::now();
         let _ = self
             .client
             .query_opt(&self.insert_tx, &[
                 &tx_hash.as_ref(),
-                &(sponsoree.index as i64),
+                &account_index,
                 &&tx_data[..],
-                &chrono::Utc::now(),
+                &now,
                 &TransactionStatus::Pending,
             ])
             .await?;
+        if account_index.is_none() {
+            let indices = sponsorees
+                .iter()
+                .map(|sponsoree| sponsoree.index as i64)
+                .collect::<Vec<_>>();
+            self.client
+                .execute(&self.insert_sponsorees, &[
+                    &tx_hash.as_ref(),
+                    &indices,
+                    &now,
+                ])
+                .await?;
+        }
         Ok(())
     }
 
+    /// Record the status of a transaction. Once a transaction is finalized, the
+    /// off-chain data it replaced is deleted.
     pub async fn mark_transaction(
         &self,
         tx_hash: TransactionHash,
@@ -148,6 +188,11 @@ impl Database {
             .client
             .query_opt(&self.mark_transaction, &[&tx_hash.as_ref(), &status])
             .await?;
+        if matches!(status, TransactionStatus::Finalized) {
+            self.client
+                .execute(&self.delete_superseded, &[&tx_hash.as_ref()])
+                .await?;
+        }
         Ok(())
     }
 
@@ -190,7 +235,13 @@ async fn insert_event(
     owner: AccountAddress,
     token_id: ProofId,
     block_time: chrono::DateTime<chrono::Utc>,
+    // The platform of the token. If not known it is taken from the mint event
+    // of the token, if that is known.
+    platform: Option<&str>,
 ) -> anyhow::Result<i64> {
+    // Store the canonical address so that the history of an account can be
+    // queried using any of its aliases.
+    let owner = canonical_address(&owner);
     let assigned_id = db_tx
         .query_one(statement, &[
             &*id,
@@ -199,6 +250,7 @@ async fn insert_event(
             &&owner.0[..],
             &(token_id as i64),
             &block_time,
+            &platform,
         ])
         .await?;
     *id += 1;
@@ -206,7 +258,9 @@ async fn insert_event(
 }
 
 /// Check whether the summary contains any CIS2 events, and if so,
-/// parse them and insert them into the `cis2_tokens` table.
+/// parse them and insert them into the `concordium_events` table.
+/// The platform of minted tokens is taken from the `TokenMinted` events
+/// logged in the same transaction.
 pub async fn insert_events(
     db_tx: &tokio_postgres::Transaction<'_>,
     id: &mut i64,
@@ -214,13 +268,26 @@ pub async fn insert_events(
     target_contract: ContractAddress,
     block_time: chrono::DateTime<chrono::Utc>,
     tx_hash: TransactionHash,
-    effects: &[(ContractAddress, Vec<cis2::Event>)],
+    effects: &[(ContractAddress, Vec<MysomeidEvent>)],
 ) -> anyhow::Result<()> {
     for (ca, events) in effects {
         if ca != &target_contract {
             continue;
         }
+        let platforms = events
+            .iter()
+            .filter_map(|event| match event {
+                MysomeidEvent::TokenMinted(minted) => {
+                    Some((minted.token_id, minted.platform.as_str()))
+                }
+                MysomeidEvent::Cis2(_) => None,
+            })
+            .collect::<HashMap<_, _>>();
         for event in events {
+            let MysomeidEvent::Cis2(event) = event else {
+                // The platform of minted tokens has already been collected.
+                continue;
+            };
             match event {
                 cis2::Event::Mint {
                     token_id,
@@ -242,6 +309,7 @@ pub async fn insert_events(
                         *owner,
                         token_id,
                         block_time,
+                        platforms.get(&token_id).copied(),
                     )
                     .await?;
                 }
@@ -265,6 +333,7 @@ pub async fn insert_events(
                         *owner,
                         token_id,
                         block_time,
+                        None,
                     )
                     .await?;
                 }
@@ -282,7 +351,7 @@ pub async fn insert_events(
 }
 
 /// Events generated by a smart contract update transaction with the given hash.
-pub type TransactionContractEvents = (TransactionHash, Vec<(ContractAddress, Vec<cis2::Event>)>);
+pub type TransactionContractEvents = (TransactionHash, Vec<(ContractAddress, Vec<MysomeidEvent>)>);
 
 pub enum DatabaseOperation {
     InsertBlock {
@@ -290,10 +359,10 @@ pub enum DatabaseOperation {
         txs:   Vec<TransactionContractEvents>,
     },
     InsertTransaction {
-        sponsoree: AccountIndex,
-        tx_hash:   TransactionHash,
-        tx:        BlockItem<EncodedPayload>,
-        response:  tokio::sync::oneshot::Sender<()>,
+        sponsorees: Vec<AccountIndex>,
+        tx_hash:    TransactionHash,
+        tx:         BlockItem<EncodedPayload>,
+        response:   tokio::sync::oneshot::Sender<()>,
     },
     MarkConcordiumTransaction {
         tx_hash: TransactionHash,
@@ -346,15 +415,15 @@ async fn insert_into_db(
             }
         }
         DatabaseOperation::InsertTransaction {
-            sponsoree,
+            sponsorees,
             tx_hash,
             tx,
             response,
         } => {
-            if let Err(e) = db.insert_tx(sponsoree, tx_hash, &tx).await {
+            if let Err(e) = db.insert_tx(&sponsorees, tx_hash, &tx).await {
                 tracing::warn!("Failed database insertion: {e:#}");
                 return Err(DatabaseOperation::InsertTransaction {
-                    sponsoree,
+                    sponsorees,
                     tx_hash,
                     tx,
                     response,
@@ -500,6 +569,8 @@ pub struct DBEvent {
     event_type: EventType,
     token_id:   ProofId,
     owner:      AccountAddress,
+    // The platform of the token, if known.
+    platform:   Option<String>,
     event_time: chrono::DateTime<chrono::Utc>,
     // The event_time as a unix timestamp in milliseconds.
     timestamp:  u64,
@@ -529,6 +600,16 @@ pub struct ReadDatabase {
     pool:                   deadpool_postgres::Pool,
     get_events:             &'static str,
     num_submitted_last_day: &'static str,
+    insert_proof_data:      &'static str,
+    get_proof_data:         &'static str,
+    supersede_proof_data:   &'static str,
+    reserve_challenge:      &'static str,
+    set_challenge_tx:       &'static str,
+    release_challenge:      &'static str,
+    get_challenge:          &'static str,
+    is_challenge_used:      &'static str,
+    get_transaction_status: &'static str,
+    get_minted_tokens:      &'static str,
 }
 
 impl ReadDatabase {
@@ -545,35 +626,89 @@ impl ReadDatabase {
             .runtime(deadpool_postgres::Runtime::Tokio1)
             .build()?;
 
-        let get_events = "SELECT id, tx_hash, event_type, owner, token_id, block_time FROM \
-                          concordium_events WHERE owner = $1 AND id <= $2
+        let get_events = "SELECT id, tx_hash, event_type, owner, token_id, block_time, platform \
+                          FROM concordium_events WHERE owner = $1 AND id <= $2 AND ($4::TEXT IS \
+                          NULL OR platform = $4)
                  ORDER BY id DESC LIMIT $3;";
 
-        let num_submitted_last_day = "SELECT COUNT(id) FROM concordium_transactions WHERE \
+        let num_submitted_last_day = "SELECT (SELECT COUNT(id) FROM concordium_transactions WHERE \
                                       account_index = $1 AND insert_time >= (now() - interval '1 \
-                                      day')";
+                                      day')) + (SELECT COUNT(*) FROM \
+                                      concordium_transaction_sponsorees WHERE account_index = $1 \
+                                      AND insert_time >= (now() - interval '1 day'))";
+
+        let insert_proof_data = "INSERT INTO proof_data (hash, data, insert_time) VALUES ($1, $2, \
+                                 $3) ON CONFLICT (hash) DO NOTHING";
+
+        let get_proof_data = "SELECT data FROM proof_data WHERE hash = $1";
+
+        let supersede_proof_data = "UPDATE proof_data SET superseded_by = $2 WHERE hash = $1";
+
+        // A challenge that was used by a transaction that failed can be used again.
+        let reserve_challenge = "INSERT INTO proof_challenges (challenge, account, \
+                                 contract_index, contract_subindex, platform, user_data, expiry, \
+                                 used_time) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT \
+                                 (challenge) DO UPDATE SET used_time = $8, tx_hash = NULL WHERE \
+                                 EXISTS (SELECT 1 FROM concordium_transactions WHERE tx_hash = \
+                                 proof_challenges.tx_hash AND status = 'failed')";
+
+        let set_challenge_tx = "UPDATE proof_challenges SET tx_hash = $2 WHERE challenge = $1";
+
+        let release_challenge =
+            "DELETE FROM proof_challenges WHERE challenge = $1 AND tx_hash IS NULL";
+
+        let get_challenge = "SELECT account, contract_index, contract_subindex, platform, \
+                             user_data, expiry FROM proof_challenges WHERE challenge = $1";
+
+        let is_challenge_used = "SELECT EXISTS (SELECT 1 FROM proof_challenges WHERE challenge = \
+                                 $1 AND NOT EXISTS (SELECT 1 FROM concordium_transactions WHERE \
+                                 tx_hash = proof_challenges.tx_hash AND status = 'failed')) AS \
+                                 used";
+
+        let get_transaction_status =
+            "SELECT status FROM concordium_transactions WHERE tx_hash = $1";
+
+        let get_minted_tokens = "SELECT token_id, owner FROM concordium_events WHERE tx_hash = $1 \
+                                 AND event_type = 'mint' ORDER BY id";
 
         Ok(Self {
             pool,
             get_events,
             num_submitted_last_day,
+            insert_proof_data,
+            get_proof_data,
+            supersede_proof_data,
+            reserve_challenge,
+            set_challenge_tx,
+            release_challenge,
+            get_challenge,
+            is_challenge_used,
+            get_transaction_status,
+            get_minted_tokens,
         })
     }
 
     /// Retrieve a list of events for the given owner starting at the given `id`
-    /// and returning at most `limit` events. The response is ordered by
-    /// decresing `id`.
+    /// and returning at most `limit` events. If `platform` is given only events
+    /// of tokens of that platform are returned. The response is ordered by
+    /// decresing `id`. The owner may be any alias of the account.
     pub async fn get_events(
         &self,
         owner: AccountAddress,
         starting_id: Option<i64>,
         limit: u32,
+        platform: Option<&str>,
     ) -> anyhow::Result<Vec<DBEvent>> {
         let start = starting_id.unwrap_or(i64::MAX);
         let client = self.pool.get().await?;
         let statement = client.prepare(self.get_events).await?;
         let rows = client
-            .query(&statement, &[&&owner.0[..], &start, &(limit as i64)])
+            .query(&statement, &[
+                &&canonical_address(&owner).0[..],
+                &start,
+                &(limit as i64),
+                &platform,
+            ])
             .await?;
         let mut events = Vec::with_capacity(rows.len());
         for row in rows {
@@ -583,6 +718,7 @@ impl ReadDatabase {
             let owner = AccountAddress(row.try_get::<_, Fixed<32>>("owner")?.0);
             let token_id = row.try_get::<_, i64>("token_id")? as u64;
             let event_time = row.try_get("block_time")?;
+            let platform = row.try_get("platform")?;
             events.push(DBEvent {
                 id,
                 tx_hash,
@@ -590,6 +726,7 @@ impl ReadDatabase {
                 token_id,
                 event_time,
                 owner,
+                platform,
                 timestamp: event_time.timestamp() as u64,
             });
         }
@@ -605,4 +742,165 @@ impl ReadDatabase {
         let num = row.try_get(0)?;
         Ok(num)
     }
+
+    /// Store the encrypted data of a token whose data is kept off-chain. The
+    /// data is addressed by its SHA-256 `hash`, which is what the contract
+    /// stores.
+    pub async fn insert_proof_data(&self, hash: &[u8; 32], data: &[u8]) -> anyhow::Result<()> {
+        let client = self.pool.get().await?;
+        let statement = client.prepare(self.insert_proof_data).await?;
+        client
+            .execute(&statement, &[&&hash[..], &data, &chrono::Utc::now()])
+            .await?;
+        Ok(())
+    }
+
+    /// Retrieve the encrypted data of a token with the given SHA-256 `hash`, if
+    /// it is stored.
+    pub async fn get_proof_data(&self, hash: &[u8; 32]) -> anyhow::Result<Option<Vec<u8>>> {
+        let client = self.pool.get().await?;
+        let statement = client.prepare(self.get_proof_data).await?;
+        let row = client.query_opt(&statement, &[&&hash[..]]).await?;
+        Ok(row.map(|row| row.try_get("data")).transpose()?)
+    }
+
+    /// Record that the data with the given SHA-256 `hash` is replaced by the
+    /// transaction with `tx_hash`. The data is deleted once the transaction is
+    /// finalized.
+    pub async fn supersede_proof_data(
+        &self,
+        hash: &[u8; 32],
+        tx_hash: &TransactionHash,
+    ) -> anyhow::Result<()> {
+        let client = self.pool.get().await?;
+        let statement = client.prepare(self.supersede_proof_data).await?;
+        client
+            .execute(&statement, &[&&hash[..], &tx_hash.as_ref()])
+            .await?;
+        Ok(())
+    }
+
+    /// Record that a challenge is being used by a transaction, so that it
+    /// cannot be used by another request. Return `false` if the challenge is
+    /// already used by a transaction that has not failed, or is reserved by
+    /// another request.
+    pub async fn reserve_challenge(
+        &self,
+        challenge: &[u8; 32],
+        issued: &IssuedChallenge,
+    ) -> anyhow::Result<bool> {
+        let client = self.pool.get().await?;
+        let statement = client.prepare(self.reserve_challenge).await?;
+        let updated = client
+            .execute(&statement, &[
+                &&challenge[..],
+                &&canonical_address(&issued.account).0[..],
+                &(issued.contract.index as i64),
+                &(issued.contract.subindex as i64),
+                &issued.platform,
+                &issued.user_data,
+                &issued.expiry,
+                &chrono::Utc::now(),
+            ])
+            .await?;
+        Ok(updated == 1)
+    }
+
+    /// Record the transaction that used a reserved challenge.
+    pub async fn set_challenge_transaction(
+        &self,
+        challenge: &[u8; 32],
+        tx_hash: &TransactionHash,
+    ) -> anyhow::Result<()> {
+        let client = self.pool.get().await?;
+        let statement = client.prepare(self.set_challenge_tx).await?;
+        client
+            .execute(&statement, &[&&challenge[..], &tx_hash.as_ref()])
+            .await?;
+        Ok(())
+    }
+
+    /// Release a reserved challenge that was not used by a transaction, so that
+    /// it can be used again.
+    pub async fn release_challenge(&self, challenge: &[u8; 32]) -> anyhow::Result<()> {
+        let client = self.pool.get().await?;
+        let statement = client.prepare(self.release_challenge).await?;
+        client.execute(&statement, &[&&challenge[..]]).await?;
+        Ok(())
+    }
+
+    /// Look up a challenge that was used by a transaction, if it exists.
+    pub async fn get_challenge(
+        &self,
+        challenge: &[u8; 32],
+    ) -> anyhow::Result<Option<IssuedChallenge>> {
+        let client = self.pool.get().await?;
+        let statement = client.prepare(self.get_challenge).await?;
+        let Some(row) = client.query_opt(&statement, &[&&challenge[..]]).await? else {
+            return Ok(None);
+        };
+        let account = AccountAddress(row.try_get::<_, Fixed<32>>("account")?.0);
+        let contract = ContractAddress::new(
+            row.try_get::<_, i64>("contract_index")? as u64,
+            row.try_get::<_, i64>("contract_subindex")? as u64,
+        );
+        Ok(Some(IssuedChallenge {
+            account,
+            contract,
+            platform: row.try_get("platform")?,
+            user_data: row.try_get("user_data")?,
+            expiry: row.try_get("expiry")?,
+        }))
+    }
+
+    /// Return whether a challenge is used by a transaction that has not failed,
+    /// or is reserved by a request.
+    pub async fn is_challenge_used(&self, challenge: &[u8; 32]) -> anyhow::Result<bool> {
+        let client = self.pool.get().await?;
+        let statement = client.prepare(self.is_challenge_used).await?;
+        let row = client.query_one(&statement, &[&&challenge[..]]).await?;
+        Ok(row.try_get("used")?)
+    }
+
+    /// Retrieve the status of a transaction sent by the service, and the
+    /// tokens it minted together with their owners, in the order they were
+    /// minted. The tokens are only known once the indexer has processed the
+    /// block of the transaction. Return `None` if the service did not send the
+    /// transaction.
+    pub async fn get_minted_tokens(
+        &self,
+        tx_hash: &TransactionHash,
+    ) -> anyhow::Result<Option<(TransactionStatus, Vec<(ProofId, AccountAddress)>)>> {
+        let client = self.pool.get().await?;
+        let statement = client.prepare(self.get_transaction_status).await?;
+        let Some(row) = client.query_opt(&statement, &[&tx_hash.as_ref()]).await? else {
+            return Ok(None);
+        };
+        let status = row.try_get("status")?;
+        let statement = client.prepare(self.get_minted_tokens).await?;
+        let rows = client.query(&statement, &[&tx_hash.as_ref()]).await?;
+        let mut tokens = Vec::with_capacity(rows.len());
+        for row in rows {
+            let token_id = row.try_get::<_, i64>("token_id")? as u64;
+            let owner = AccountAddress(row.try_get::<_, Fixed<32>>("owner")?.0);
+            tokens.push((token_id, owner));
+        }
+        Ok(Some((status, tokens)))
+    }
+}
+
+/// A challenge issued by the service for a proof.
+#[derive(Debug)]
+pub struct IssuedChallenge {
+    /// The account the challenge was issued for. This is stored as the
+    /// canonical address of the account.
+    pub account:   AccountAddress,
+    /// The contract the challenge was issued for.
+    pub contract:  ContractAddress,
+    /// The platform the challenge was issued for.
+    pub platform:  String,
+    /// The user data the challenge was issued for.
+    pub user_data: String,
+    /// Time after which the challenge can no longer be used.
+    pub expiry:    chrono::DateTime<chrono::Utc>,
 }
@@ -1,22 +1,28 @@
 pub mod db;
+pub mod link;
+pub mod payload;
 
 use concordium::{
     base as concordium_base,
     base::{
         contracts_common::{self as concordium_std, AccountAddress, Amount},
-        smart_contracts::{OwnedParameter, OwnedReceiveName},
+        smart_contracts::{ContractEvent, OwnedParameter, OwnedReceiveName},
     },
     common::{self, Versioned},
     id::{
         constants::{ArCurve, AttributeKind},
-        id_proof_types::Proof,
+        id_proof_types::{AtomicProof, Proof},
     },
     smart_contracts,
     types::{smart_contracts::ContractContext, CredentialRegistrationID, Energy, RejectReason},
     v2::BlockIdentifier,
 };
 use concordium_rust_sdk as concordium;
-use concordium_rust_sdk::{cis2::TokenId, types::ContractAddress, v2};
+use concordium_rust_sdk::{
+    cis2::{self, TokenId},
+    types::ContractAddress,
+    v2,
+};
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
 use unicode_segmentation::UnicodeSegmentation;
@@ -28,25 +34,99 @@ pub struct ContractClient {
 
 pub type ProofId = u64;
 
+/// Aliases of an account share the first 29 bytes of the address.
+const ACCOUNT_ADDRESS_ALIAS_PREFIX_LEN: usize = 29;
+
+/// Return the canonical address of an account, i.e., the alias where the last
+/// bytes of the address are zero. This is the same for all aliases of an
+/// account.
+pub fn canonical_address(address: &AccountAddress) -> AccountAddress {
+    let mut canonical = [0u8; 32];
+    canonical[..ACCOUNT_ADDRESS_ALIAS_PREFIX_LEN]
+        .copy_from_slice(&address.0[..ACCOUNT_ADDRESS_ALIAS_PREFIX_LEN]);
+    AccountAddress(canonical)
+}
+
 #[derive(serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MintParams {
-    pub account:  AccountAddress,
-    pub platform: SupportedPlatform,
+    pub account:              AccountAddress,
+    pub platform:             SupportedPlatform,
     #[serde(flatten)]
-    pub private:  PrivateTokenData,
+    pub private:              PrivateTokenData,
+    /// Whether the data key should also be wrapped for the designated
+    /// verifiers configured in the service.
+    #[serde(default)]
+    pub designated_verifiers: bool,
+    /// If set, the key of the token is derived from this passphrase instead of
+    /// being random.
+    #[serde(default)]
+    pub passphrase:           Option<String>,
 }
 
-#[derive(serde::Deserialize, common::Serialize, serde::Serialize)]
+#[derive(serde::Deserialize, serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PrivateTokenData {
-    pub first_name: AttributeKind,
+    pub first_name:     AttributeKind,
     #[serde(rename = "surName")]
-    pub surname:    AttributeKind,
-    #[string_size_length = 4]
-    pub user_data:  String,
-    pub challenge:  Challenge,
-    pub proof:      ProofWithContext,
+    pub surname:        AttributeKind,
+    pub user_data:      String,
+    pub challenge:      Challenge,
+    pub proof:          ProofWithContext,
+    /// SHA-256 hash of the statement the proof was made for. This is set by
+    /// the service when minting. Tokens minted before it was recorded do not
+    /// have it, and their proofs are for the statement revealing the first and
+    /// last name.
+    #[serde(skip)]
+    pub statement_hash: Option<[u8; 32]>,
+}
+
+// The statement hash is serialized last, and only if it is present, so that
+// the data of tokens minted before it was recorded can still be parsed.
+impl common::Serial for PrivateTokenData {
+    fn serial<B: common::Buffer>(&self, out: &mut B) {
+        use common::Serial;
+        self.first_name.serial(out);
+        self.surname.serial(out);
+        (self.user_data.len() as u32).serial(out);
+        common::serial_string(&self.user_data, out);
+        self.challenge.serial(out);
+        self.proof.serial(out);
+        if let Some(statement_hash) = &self.statement_hash {
+            out.write_all(statement_hash)
+                .expect("Writing to a buffer succeeds.");
+        }
+    }
+}
+
+impl common::Deserial for PrivateTokenData {
+    fn deserial<R: common::ReadBytesExt>(source: &mut R) -> common::ParseResult<Self> {
+        use common::Get;
+        let first_name = source.get()?;
+        let surname = source.get()?;
+        let user_data_len: u32 = source.get()?;
+        let user_data = common::deserial_string(source, user_data_len as usize)?;
+        let challenge = source.get()?;
+        let proof = source.get()?;
+        let mut rest = Vec::new();
+        source.read_to_end(&mut rest)?;
+        let statement_hash = if rest.is_empty() {
+            None
+        } else {
+            let hash = rest
+                .try_into()
+                .map_err(|_| anyhow::anyhow!("Incorrect statement hash length."))?;
+            Some(hash)
+        };
+        Ok(Self {
+            first_name,
+            surname,
+            user_data,
+            challenge,
+            proof,
+            statement_hash,
+        })
+    }
 }
 
 #[derive(serde::Deserialize, Debug, Clone, common::Serialize, serde::Serialize)]
@@ -55,6 +135,21 @@ pub struct ProofWithContext {
     pub proof:      Versioned<Proof<ArCurve, AttributeKind>>,
 }
 
+/// Return the attribute values revealed by a proof, in the order in which they
+/// appear in the statement.
+pub fn revealed_attributes(proof: &ProofWithContext) -> Vec<&AttributeKind> {
+    proof
+        .proof
+        .value
+        .proofs
+        .iter()
+        .filter_map(|proof| match proof {
+            AtomicProof::RevealAttribute { attribute, .. } => Some(attribute),
+            _ => None,
+        })
+        .collect()
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Clone, Copy, common::Serialize)]
 #[serde(into = "String", try_from = "String")]
 pub struct Challenge {
@@ -85,18 +180,165 @@ impl TryFrom<String> for Challenge {
 #[derive(concordium_std::Deserial)]
 pub struct ViewData {
     /// Owner of the token.
-    pub owner:    AccountAddress,
+    pub owner:             AccountAddress,
     /// Whether the token is revoked or not.
-    pub revoked:  bool,
+    pub revoked:           bool,
     /// Platform associated with the token.
-    pub platform: SupportedPlatform,
+    pub platform:          SupportedPlatform,
     /// The data, which includes the proof, challenge, name, social media URL
-    /// (e.g linkedin URL).
+    /// (e.g linkedin URL). This is empty if the token was burned by its owner
+    /// or if the data is stored off-chain.
     #[concordium(size_length = 2)]
-    pub data:     Vec<u8>,
+    pub data:              Vec<u8>,
+    /// The reason the token was revoked, if it was revoked by an issuer.
+    pub revocation_reason: Option<RevocationReason>,
+    /// Slot time of the block in which the token was minted.
+    pub issued_at:         concordium_std::Timestamp,
+    /// Time until which the token is valid, if it expires.
+    pub valid_until:       Option<concordium_std::Timestamp>,
+    /// Time at which the token was burned by its owner, if it was. The data of
+    /// a burned token is erased.
+    pub burned_at:         Option<concordium_std::Timestamp>,
+    /// Commitment to the data if it is stored off-chain, in which case `data`
+    /// is empty.
+    pub commitment:        Option<DataCommitment>,
+    /// The token this token was reissued to replace, if any.
+    pub replaces:          Option<ContractTokenId>,
+    /// The token this token was replaced by when it was reissued, if any.
+    pub replaced_by:       Option<ContractTokenId>,
+}
+
+/// The number of tokens of a platform. Burned tokens count as revoked.
+#[derive(Debug, Clone, Copy, concordium_std::Deserial)]
+pub struct TokenCounts {
+    /// Tokens that are neither revoked nor burned.
+    pub active:  u64,
+    /// Tokens that are revoked or burned.
+    pub revoked: u64,
 }
 
-#[derive(Debug, serde::Deserialize, serde::Serialize, Eq, PartialEq)]
+/// A CIS-2 metadata URL as returned by the contract.
+#[derive(Debug, Clone, concordium_std::Deserial)]
+pub struct ContractMetadataUrl {
+    #[concordium(size_length = 2)]
+    pub url:  String,
+    pub hash: Option<[u8; 32]>,
+}
+
+/// The return type for the contract function `viewSummary`.
+#[derive(Debug, concordium_std::Deserial)]
+pub struct ViewSummary {
+    /// The ID of the next token to be minted.
+    pub next_token_id: u64,
+    /// The number of active and revoked tokens of each platform.
+    pub platforms:     Vec<([u8; 2], TokenCounts)>,
+    /// The number of accounts with active tokens.
+    pub owners:        u64,
+    /// The metadata URL of the tokens, unless overridden for a platform.
+    pub metadata_url:  ContractMetadataUrl,
+    /// The number of addresses with at least one role.
+    pub role_holders:  u32,
+}
+
+/// A token ID as serialized by the contract, i.e., a `u64` with a one byte
+/// length prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContractTokenId(pub ProofId);
+
+impl concordium_std::Deserial for ContractTokenId {
+    fn deserial<R: concordium_std::Read>(source: &mut R) -> concordium_std::ParseResult<Self> {
+        if source.read_u8()? != 8 {
+            return Err(concordium_std::ParseError {});
+        }
+        source.read_u64().map(Self)
+    }
+}
+
+impl concordium_std::Serial for ContractTokenId {
+    fn serial<W: concordium_std::Write>(&self, out: &mut W) -> Result<(), W::Err> {
+        out.write_u8(8)?;
+        out.write_u64(self.0)
+    }
+}
+
+/// A commitment to the data of a token which is stored off-chain.
+#[derive(Debug, Clone, concordium_std::Serial, concordium_std::Deserial)]
+pub struct DataCommitment {
+    /// The SHA-256 hash of the off-chain data.
+    pub hash:    [u8; 32],
+    /// Where the off-chain data can be retrieved from.
+    #[concordium(size_length = 2)]
+    pub locator: String,
+}
+
+/// The reason recorded in the contract when a token is revoked by an issuer.
+#[derive(Debug, Clone, Copy, concordium_std::Deserial, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RevocationReason {
+    Fraud,
+    IdentityCompromised,
+    UserRequest,
+    Superseded,
+}
+
+/// Tag of the `TokenMinted` event logged by the contract.
+pub const TOKEN_MINTED_EVENT_TAG: u8 = 5;
+
+/// The `TokenMinted` event which the contract logs in addition to the CIS-2
+/// `Mint` event.
+#[derive(Debug)]
+pub struct TokenMintedEvent {
+    pub token_id:  ProofId,
+    pub owner:     AccountAddress,
+    /// Platform associated with the token. This is kept as a string so that
+    /// tokens of platforms not supported by this service can still be indexed.
+    pub platform:  String,
+    pub issued_at: concordium_std::Timestamp,
+}
+
+impl concordium_std::Deserial for TokenMintedEvent {
+    fn deserial<R: concordium_std::Read>(source: &mut R) -> concordium_std::ParseResult<Self> {
+        use concordium_std::Deserial;
+        let ContractTokenId(token_id) = ContractTokenId::deserial(source)?;
+        let owner = AccountAddress::deserial(source)?;
+        let platform = <[u8; 2]>::deserial(source)?;
+        let platform =
+            String::from_utf8(platform.to_vec()).map_err(|_| concordium_std::ParseError {})?;
+        let issued_at = concordium_std::Timestamp::deserial(source)?;
+        Ok(Self {
+            token_id,
+            owner,
+            platform,
+            issued_at,
+        })
+    }
+}
+
+/// An event logged by the contract that is understood by the service.
+#[derive(Debug)]
+pub enum MysomeidEvent {
+    /// An event defined by the CIS-2 standard, or an unknown event.
+    Cis2(cis2::Event),
+    /// The custom `TokenMinted` event.
+    TokenMinted(TokenMintedEvent),
+}
+
+impl TryFrom<&ContractEvent> for MysomeidEvent {
+    type Error = concordium_std::ParseError;
+
+    fn try_from(event: &ContractEvent) -> Result<Self, Self::Error> {
+        match event.as_ref().split_first() {
+            Some((&TOKEN_MINTED_EVENT_TAG, rest)) => {
+                concordium_std::from_bytes(rest).map(MysomeidEvent::TokenMinted)
+            }
+            _ => cis2::Event::try_from(event)
+                .map(MysomeidEvent::Cis2)
+                .map_err(|_| concordium_std::ParseError {}),
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, Eq, PartialEq, Hash, Clone, Copy)]
 #[repr(u32)]
 pub enum SupportedPlatform {
     #[serde(rename = "li")]
@@ -130,6 +372,26 @@ impl std::fmt::Display for SupportedPlatform {
     }
 }
 
+impl SupportedPlatform {
+    /// The URL of the profile with the given user data on the platform.
+    pub fn profile_url(&self, user_data: &str) -> String {
+        match self {
+            SupportedPlatform::LinkedIn => format!("https://www.linkedin.com/in/{user_data}"),
+        }
+    }
+}
+
+impl std::str::FromStr for SupportedPlatform {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "li" => Ok(SupportedPlatform::LinkedIn),
+            _ => anyhow::bail!("Unsupported platform: {s}"),
+        }
+    }
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum ContractQueryError {
     #[error("Error querying the node: {0}")]
@@ -146,16 +408,49 @@ impl ContractClient {
         &mut self,
         id: &ProofId,
     ) -> Result<Option<ViewData>, ContractQueryError> {
+        let parameter =
+            OwnedParameter::from_serial(&TokenId::new_unchecked(id.to_le_bytes().to_vec()))
+                .expect("8 bytes fits.");
+        self.invoke_view::<Option<ViewData>>("mysomeid.viewData", parameter)
+            .await
+            .map(Option::flatten)
+    }
+
+    // Get the summary of the contract from the last finalized block.
+    pub async fn view_summary(&mut self) -> Result<Option<ViewSummary>, ContractQueryError> {
+        self.invoke_view("mysomeid.viewSummary", OwnedParameter::empty())
+            .await
+    }
+
+    // Check whether the contract is paused in the last finalized block. A
+    // contract without the `isPaused` entrypoint cannot be paused.
+    pub async fn is_paused(&mut self) -> Result<bool, ContractQueryError> {
+        match self
+            .invoke_view("mysomeid.isPaused", OwnedParameter::empty())
+            .await
+        {
+            Ok(paused) => Ok(paused.unwrap_or(false)),
+            Err(ContractQueryError::InvokeFailure(RejectReason::InvalidReceiveMethod {
+                ..
+            })) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    // Invoke a view function of the contract in the last finalized block and
+    // parse the return value, if any.
+    async fn invoke_view<A: concordium_std::Deserial>(
+        &mut self,
+        method: &str,
+        parameter: OwnedParameter,
+    ) -> Result<Option<A>, ContractQueryError> {
         let context = ContractContext {
-            invoker:   None,
-            contract:  self.address,
-            amount:    Amount::zero(),
-            method:    OwnedReceiveName::new_unchecked("mysomeid.viewData".into()),
-            parameter: OwnedParameter::from_serial(&TokenId::new_unchecked(
-                id.to_le_bytes().to_vec(),
-            ))
-            .expect("8 bytes fits."),
-            energy:    Energy::from(100_000),
+            invoker: None,
+            contract: self.address,
+            amount: Amount::zero(),
+            method: OwnedReceiveName::new_unchecked(method.into()),
+            parameter,
+            energy: Energy::from(100_000),
         };
         let response = self
             .client
@@ -167,7 +462,8 @@ impl ContractClient {
                 return_value,
                 ..
             } => match return_value {
-                Some(rv) => smart_contracts::common::from_bytes(&rv.value)
+                Some(rv) => smart_contracts::common::from_bytes::<A>(&rv.value)
+                    .map(Some)
                     .map_err(|_| ContractQueryError::ParseResponseFailure),
                 None => Ok(None),
             },
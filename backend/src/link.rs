@@ -0,0 +1,112 @@
+//! The compact format of links to proofs.
+//!
+//! A link is `version || base64url(key || checksum) || base58(token id)`,
+//! where the version is the character `1`, and the checksum is the first 4
+//! bytes of `SHA-256(version || token id || key)` with the token id as a
+//! big-endian `u64`. All characters are URL-safe, so the link can be used as a
+//! path segment without encoding.
+//!
+//! The token id is optional, in which case it must be given by the preceding
+//! path segment of the URL, as in the legacy format
+//! `base/:tokenId/:decryptionKey`.
+use base64::Engine;
+use sha2::Digest;
+
+/// Version prefix of the first compact format.
+pub const LINK_VERSION_1: char = '1';
+
+/// Length of the checksum.
+const CHECKSUM_LEN: usize = 4;
+
+/// Length of the encoded key and checksum.
+const ENCODED_KEY_LEN: usize = 48;
+
+fn checksum(token_id: u64, key: &[u8; 32]) -> [u8; CHECKSUM_LEN] {
+    let mut hasher = sha2::Sha256::new();
+    hasher.update([LINK_VERSION_1 as u8]);
+    hasher.update(token_id.to_be_bytes());
+    hasher.update(key);
+    let mut checksum = [0u8; CHECKSUM_LEN];
+    checksum.copy_from_slice(&hasher.finalize()[..CHECKSUM_LEN]);
+    checksum
+}
+
+/// Encode the link to the token with the given key.
+pub fn encode(token_id: u64, key: &[u8; 32]) -> String {
+    let mut key_and_checksum = key.to_vec();
+    key_and_checksum.extend_from_slice(&checksum(token_id, key));
+    // The token id is encoded without leading zero bytes, but with at least one
+    // byte.
+    let id_bytes = token_id.to_be_bytes();
+    let id_start = id_bytes.iter().position(|b| *b != 0).unwrap_or(7);
+    let mut link = String::from(LINK_VERSION_1);
+    link.push_str(&base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(key_and_checksum));
+    link.push_str(&bs58::encode(&id_bytes[id_start..]).into_string());
+    link
+}
+
+/// Decode a link. If the link does not contain the token id, `token_id` is
+/// used instead. Return [`None`] if the link is malformed, of an unknown
+/// version, or the checksum does not match.
+pub fn decode(link: &str, token_id: Option<u64>) -> Option<(u64, [u8; 32])> {
+    let rest = link.strip_prefix(LINK_VERSION_1)?;
+    if rest.len() < ENCODED_KEY_LEN || !rest.is_char_boundary(ENCODED_KEY_LEN) {
+        return None;
+    }
+    let (encoded_key, encoded_id) = rest.split_at(ENCODED_KEY_LEN);
+    let key_and_checksum = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(encoded_key)
+        .ok()?;
+    let (key, link_checksum) = key_and_checksum.split_at(32);
+    let key: [u8; 32] = key.try_into().ok()?;
+    let token_id = if encoded_id.is_empty() {
+        token_id?
+    } else {
+        let id_bytes = bs58::decode(encoded_id).into_vec().ok()?;
+        if id_bytes.is_empty() || id_bytes.len() > 8 {
+            return None;
+        }
+        let mut padded = [0u8; 8];
+        padded[8 - id_bytes.len()..].copy_from_slice(&id_bytes);
+        u64::from_be_bytes(padded)
+    };
+    if link_checksum != checksum(token_id, &key) {
+        return None;
+    }
+    Some((token_id, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Test that links can be decoded, with and without the token id.
+    fn test_encode_decode() {
+        let key = [0xfbu8; 32];
+        for token_id in [0, 1, 255, 256, 1_000_000, u64::MAX] {
+            let link = encode(token_id, &key);
+            assert!(link
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+            assert_eq!(decode(&link, None), Some((token_id, key)));
+            let without_id = &link[..1 + ENCODED_KEY_LEN];
+            assert_eq!(decode(without_id, Some(token_id)), Some((token_id, key)));
+            assert_eq!(decode(without_id, None), None);
+        }
+    }
+
+    #[test]
+    /// Test that the checksum catches changes to the key and the token id.
+    fn test_decode_checksum() {
+        let key = [7u8; 32];
+        let link = encode(17, &key);
+        let without_id = &link[..1 + ENCODED_KEY_LEN];
+        assert_eq!(decode(without_id, Some(18)), None);
+        let mut changed = link.into_bytes();
+        changed[5] = if changed[5] == b'A' { b'B' } else { b'A' };
+        let changed = String::from_utf8(changed).unwrap();
+        assert_eq!(decode(&changed, None), None);
+        assert_eq!(decode(&format!("2{}", &changed[1..]), None), None);
+    }
+}
@@ -0,0 +1,662 @@
+//! The format of the encrypted data stored for each token.
+//!
+//! Version 0 is `[0, 0, 0, 0] || nonce || AES-GCM(data)`.
+//!
+//! Version 1 is `[0, 0, 0, 1] || header || nonce || AES-GCM(payload)`, where
+//! everything before the ciphertext is authenticated as associated data. The
+//! header binds the data to the platform and the owner of the token, so that
+//! the data of one token cannot be presented as the data of another. The
+//! payload is the data, optionally compressed with DEFLATE.
+//!
+//! The key of version 1 data can also be wrapped for designated verifiers,
+//! which can then decrypt the data with their X25519 secret key instead of the
+//! key of the token. For this the header contains an ephemeral X25519 public
+//! key, and for each verifier its public key and the data key encrypted with
+//! AES-GCM under a key derived with HKDF-SHA256 from the shared secret of the
+//! ephemeral key and the verifier's key.
+//!
+//! Instead of a random key, the key of version 1 data can be derived from a
+//! passphrase with Argon2id. The salt and the parameters of Argon2id are then
+//! stored in the header.
+use crate::SupportedPlatform;
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm,
+};
+use concordium_rust_sdk::{
+    common::{self, Deserial, Serial},
+    smart_contracts::common::{self as concordium_std, AccountAddress},
+};
+use rand::Rng;
+use std::io::{Read, Write};
+use x25519_dalek::{PublicKey, SharedSecret, StaticSecret};
+
+/// Version prefix of the original format.
+pub const VERSION_0: [u8; 4] = [0, 0, 0, 0];
+/// Version prefix of the format with an authenticated header.
+pub const VERSION_1: [u8; 4] = [0, 0, 0, 1];
+
+/// Flag set in the header if the payload is compressed.
+const FLAG_COMPRESSED: u8 = 0b0000_0001;
+/// Flag set in the header if the key is wrapped for designated verifiers.
+const FLAG_VERIFIERS: u8 = 0b0000_0010;
+/// Flag set in the header if the key is derived from a passphrase.
+const FLAG_PASSPHRASE: u8 = 0b0000_0100;
+
+/// Maximum size of a decompressed payload.
+const MAX_PAYLOAD_SIZE: u64 = 1_000_000;
+
+/// Length of the AES-GCM nonce.
+const NONCE_LEN: usize = 12;
+
+/// Length of a data key encrypted for a verifier, i.e., the key and the tag.
+const WRAPPED_KEY_LEN: usize = 48;
+
+/// Info string for deriving the key that encrypts the data key for a verifier.
+const KEY_WRAP_INFO: &[u8] = b"mysomeid verifier key wrap v1";
+
+/// Length of the salt for deriving a key from a passphrase.
+const SALT_LEN: usize = 16;
+
+/// Memory size in KiB of Argon2id for deriving a key from a passphrase.
+const PASSPHRASE_MEMORY: u32 = 19 * 1024;
+
+/// Number of iterations of Argon2id for deriving a key from a passphrase.
+const PASSPHRASE_ITERATIONS: u32 = 2;
+
+/// Degree of parallelism of Argon2id for deriving a key from a passphrase.
+const PASSPHRASE_PARALLELISM: u32 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PayloadError {
+    #[error("The data cannot be parsed.")]
+    Malformed,
+    #[error("Unsupported version {0:?}.")]
+    UnsupportedVersion([u8; 4]),
+    #[error("Unable to decrypt the data.")]
+    Decryption,
+    #[error("Unable to encrypt the data.")]
+    Encryption,
+    #[error("The data is not encrypted for the verifier.")]
+    NotDesignated,
+    #[error("The key of the data is not derived from a passphrase.")]
+    NoPassphrase,
+}
+
+/// The salt and parameters for deriving the key of the data from a passphrase
+/// with Argon2id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassphraseParams {
+    pub salt:        [u8; SALT_LEN],
+    /// Memory size in KiB.
+    pub memory:      u32,
+    pub iterations:  u32,
+    pub parallelism: u32,
+}
+
+impl PassphraseParams {
+    /// Fresh salt with the parameters of Argon2id used by the service.
+    pub fn generate() -> Self {
+        Self {
+            salt:        rand::thread_rng().gen(),
+            memory:      PASSPHRASE_MEMORY,
+            iterations:  PASSPHRASE_ITERATIONS,
+            parallelism: PASSPHRASE_PARALLELISM,
+        }
+    }
+
+    /// Derive the key of the data from the passphrase. Parameters other than
+    /// those used by the service are rejected, since they are read from the
+    /// data and could otherwise make deriving the key arbitrarily expensive.
+    pub fn derive_key(&self, passphrase: &str) -> Result<[u8; 32], PayloadError> {
+        if self.memory != PASSPHRASE_MEMORY
+            || self.iterations != PASSPHRASE_ITERATIONS
+            || self.parallelism != PASSPHRASE_PARALLELISM
+        {
+            return Err(PayloadError::Malformed);
+        }
+        let params = argon2::Params::new(self.memory, self.iterations, self.parallelism, Some(32))
+            .map_err(|_| PayloadError::Malformed)?;
+        let mut key = [0u8; 32];
+        argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &self.salt, &mut key)
+            .map_err(|_| PayloadError::Malformed)?;
+        Ok(key)
+    }
+}
+
+/// The X25519 public key of a designated verifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerifierKey(pub [u8; 32]);
+
+impl std::str::FromStr for VerifierKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = hex::decode(s)?;
+        let key = bytes
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("A verifier key must be 32 bytes."))?;
+        Ok(Self(key))
+    }
+}
+
+impl VerifierKey {
+    /// The public key for the X25519 secret key.
+    pub fn from_secret(secret: &[u8; 32]) -> Self {
+        Self(PublicKey::from(&StaticSecret::from(*secret)).to_bytes())
+    }
+}
+
+/// Derive the key that encrypts the data key for the verifier with the given
+/// public key. Return [`None`] if the verifier key is of low order.
+fn key_encryption_key(
+    shared_secret: SharedSecret,
+    ephemeral: &[u8; 32],
+    verifier: &VerifierKey,
+) -> Option<[u8; 32]> {
+    if !shared_secret.was_contributory() {
+        return None;
+    }
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(ephemeral);
+    salt[32..].copy_from_slice(&verifier.0);
+    let mut key = [0u8; 32];
+    hkdf::Hkdf::<sha2::Sha256>::new(Some(&salt[..]), shared_secret.as_bytes())
+        .expand(KEY_WRAP_INFO, &mut key)
+        .expect("32 bytes is a valid output length.");
+    Some(key)
+}
+
+/// The data key encrypted for each of the verifiers of the header. Each key
+/// that encrypts the data key is only used once, so a fixed nonce is used.
+struct WrappedKeys {
+    ephemeral: [u8; 32],
+    keys:      Vec<[u8; WRAPPED_KEY_LEN]>,
+}
+
+impl WrappedKeys {
+    fn new(data_key: &[u8; 32], verifiers: &[VerifierKey]) -> Result<Self, PayloadError> {
+        let ephemeral_secret = StaticSecret::random_from_rng(rand::thread_rng());
+        let ephemeral = PublicKey::from(&ephemeral_secret).to_bytes();
+        let keys = verifiers
+            .iter()
+            .map(|verifier| {
+                let key = key_encryption_key(
+                    ephemeral_secret.diffie_hellman(&PublicKey::from(verifier.0)),
+                    &ephemeral,
+                    verifier,
+                )
+                .ok_or(PayloadError::Encryption)?;
+                let wrapped = Aes256Gcm::new((&key).into())
+                    .encrypt((&[0u8; NONCE_LEN]).into(), &data_key[..])
+                    .map_err(|_| PayloadError::Encryption)?;
+                wrapped.try_into().map_err(|_| PayloadError::Encryption)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { ephemeral, keys })
+    }
+
+    /// Decrypt the data key with the secret key of the verifier at `index`.
+    fn unwrap(
+        &self,
+        index: usize,
+        verifier: &VerifierKey,
+        secret: &[u8; 32],
+    ) -> Result<[u8; 32], PayloadError> {
+        let shared_secret =
+            StaticSecret::from(*secret).diffie_hellman(&PublicKey::from(self.ephemeral));
+        let key = key_encryption_key(shared_secret, &self.ephemeral, verifier)
+            .ok_or(PayloadError::Decryption)?;
+        let data_key = Aes256Gcm::new((&key).into())
+            .decrypt((&[0u8; NONCE_LEN]).into(), &self.keys[index][..])
+            .map_err(|_| PayloadError::Decryption)?;
+        data_key.try_into().map_err(|_| PayloadError::Decryption)
+    }
+}
+
+/// The header of version 1 data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayloadHeader {
+    /// Platform of the token.
+    pub platform:       SupportedPlatform,
+    /// Canonical address of the owner of the token.
+    pub owner:          AccountAddress,
+    /// URL of the profile the token is for.
+    pub profile_url:    String,
+    /// Time of minting, in milliseconds since the unix epoch.
+    pub minted_at:      u64,
+    /// SHA-256 hash of the statement the proof was made for.
+    pub statement_hash: [u8; 32],
+    /// Identifier of the key of the service that encrypted the data.
+    pub key_id:         u32,
+    /// The designated verifiers that can decrypt the data with their secret
+    /// key.
+    pub verifiers:      Vec<VerifierKey>,
+    /// The salt and parameters if the key is derived from a passphrase.
+    pub passphrase:     Option<PassphraseParams>,
+}
+
+impl PayloadHeader {
+    fn serial(&self, flags: u8, wrapped_keys: Option<&WrappedKeys>, out: &mut Vec<u8>) {
+        out.push(flags);
+        self.key_id.serial(out);
+        self.minted_at.serial(out);
+        out.extend_from_slice(&self.statement_hash);
+        out.extend_from_slice(&concordium_std::to_bytes(&self.platform));
+        out.extend_from_slice(&self.owner.0);
+        (self.profile_url.len() as u16).serial(out);
+        out.extend_from_slice(self.profile_url.as_bytes());
+        if let Some(wrapped_keys) = wrapped_keys {
+            out.extend_from_slice(&wrapped_keys.ephemeral);
+            (self.verifiers.len() as u8).serial(out);
+            for (verifier, key) in self.verifiers.iter().zip(&wrapped_keys.keys) {
+                out.extend_from_slice(&verifier.0);
+                out.extend_from_slice(key);
+            }
+        }
+        if let Some(params) = &self.passphrase {
+            out.extend_from_slice(&params.salt);
+            params.memory.serial(out);
+            params.iterations.serial(out);
+            params.parallelism.serial(out);
+        }
+    }
+
+    fn deserial(source: &mut impl Read) -> anyhow::Result<(u8, Self, Option<WrappedKeys>)> {
+        let flags = u8::deserial(source)?;
+        let key_id = u32::deserial(source)?;
+        let minted_at = u64::deserial(source)?;
+        let mut statement_hash = [0u8; 32];
+        source.read_exact(&mut statement_hash)?;
+        let mut platform = [0u8; 2];
+        source.read_exact(&mut platform)?;
+        let platform = concordium_std::from_bytes(&platform)
+            .map_err(|_| anyhow::anyhow!("Unsupported platform."))?;
+        let mut owner = [0u8; 32];
+        source.read_exact(&mut owner)?;
+        let owner = AccountAddress(owner);
+        let url_len = u16::deserial(source)?;
+        let profile_url = common::deserial_string(source, url_len.into())?;
+        let mut verifiers = Vec::new();
+        let wrapped_keys = if flags & FLAG_VERIFIERS != 0 {
+            let mut ephemeral = [0u8; 32];
+            source.read_exact(&mut ephemeral)?;
+            let len = u8::deserial(source)?;
+            let mut keys = Vec::with_capacity(len.into());
+            for _ in 0..len {
+                let mut verifier = [0u8; 32];
+                source.read_exact(&mut verifier)?;
+                verifiers.push(VerifierKey(verifier));
+                let mut key = [0u8; WRAPPED_KEY_LEN];
+                source.read_exact(&mut key)?;
+                keys.push(key);
+            }
+            Some(WrappedKeys { ephemeral, keys })
+        } else {
+            None
+        };
+        let passphrase = if flags & FLAG_PASSPHRASE != 0 {
+            let mut salt = [0u8; SALT_LEN];
+            source.read_exact(&mut salt)?;
+            Some(PassphraseParams {
+                salt,
+                memory: u32::deserial(source)?,
+                iterations: u32::deserial(source)?,
+                parallelism: u32::deserial(source)?,
+            })
+        } else {
+            None
+        };
+        Ok((
+            flags,
+            Self {
+                platform,
+                owner,
+                profile_url,
+                minted_at,
+                statement_hash,
+                key_id,
+                verifiers,
+                passphrase,
+            },
+            wrapped_keys,
+        ))
+    }
+}
+
+/// Data in either format, split into its parts.
+struct Parsed<'a> {
+    /// The flags and header of version 1 data.
+    header:     Option<(u8, PayloadHeader, Option<WrappedKeys>)>,
+    /// The associated data, which is everything before the ciphertext.
+    aad:        &'a [u8],
+    nonce:      [u8; NONCE_LEN],
+    ciphertext: &'a [u8],
+}
+
+impl<'a> Parsed<'a> {
+    fn new(data: &'a [u8]) -> Result<Self, PayloadError> {
+        let mut cursor = std::io::Cursor::new(data);
+        let mut version = [0u8; 4];
+        cursor
+            .read_exact(&mut version)
+            .map_err(|_| PayloadError::Malformed)?;
+        let header = match version {
+            VERSION_0 => None,
+            VERSION_1 => {
+                Some(PayloadHeader::deserial(&mut cursor).map_err(|_| PayloadError::Malformed)?)
+            }
+            _ => return Err(PayloadError::UnsupportedVersion(version)),
+        };
+        let mut nonce = [0u8; NONCE_LEN];
+        cursor
+            .read_exact(&mut nonce)
+            .map_err(|_| PayloadError::Malformed)?;
+        let ciphertext_start = cursor.position() as usize;
+        // Version 0 data has no associated data.
+        let aad: &[u8] = if header.is_some() {
+            &data[..ciphertext_start]
+        } else {
+            &[]
+        };
+        Ok(Self {
+            header,
+            aad,
+            nonce,
+            ciphertext: &data[ciphertext_start..],
+        })
+    }
+
+    /// Decrypt the data with the data key. Return the header, if any, and the
+    /// decrypted data.
+    fn decrypt(self, key: &[u8; 32]) -> Result<(Option<PayloadHeader>, Vec<u8>), PayloadError> {
+        let payload = Aes256Gcm::new(key.into())
+            .decrypt((&self.nonce).into(), Payload {
+                msg: self.ciphertext,
+                aad: self.aad,
+            })
+            .map_err(|_| PayloadError::Decryption)?;
+        let (flags, header) = match self.header {
+            Some((flags, header, _)) => (flags, header),
+            None => return Ok((None, payload)),
+        };
+        if flags & FLAG_COMPRESSED == 0 {
+            return Ok((Some(header), payload));
+        }
+        let mut decompressed = Vec::new();
+        flate2::read::DeflateDecoder::new(&payload[..])
+            .take(MAX_PAYLOAD_SIZE + 1)
+            .read_to_end(&mut decompressed)
+            .map_err(|_| PayloadError::Malformed)?;
+        if decompressed.len() as u64 > MAX_PAYLOAD_SIZE {
+            return Err(PayloadError::Malformed);
+        }
+        Ok((Some(header), decompressed))
+    }
+}
+
+/// Encrypt `data` in the version 1 format. The key is derived from the
+/// passphrase if the header has passphrase parameters, and is fresh otherwise.
+/// The data is compressed if that makes it smaller, and the key is wrapped for
+/// the verifiers of the header, if any. Return the encrypted data and the key.
+pub fn seal(
+    header: &PayloadHeader,
+    data: &[u8],
+    passphrase: Option<&str>,
+) -> Result<(Vec<u8>, [u8; 32]), PayloadError> {
+    let compressed = {
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder
+            .write_all(data)
+            .and_then(|_| encoder.finish())
+            .map_err(|_| PayloadError::Encryption)?
+    };
+    let (mut flags, payload) = if compressed.len() < data.len() {
+        (FLAG_COMPRESSED, &compressed[..])
+    } else {
+        (0, data)
+    };
+
+    let key: [u8; 32] = match (&header.passphrase, passphrase) {
+        (Some(params), Some(passphrase)) => {
+            flags |= FLAG_PASSPHRASE;
+            params.derive_key(passphrase)?
+        }
+        (None, None) => Aes256Gcm::generate_key(&mut rand::thread_rng()).into(),
+        _ => return Err(PayloadError::Encryption),
+    };
+    let nonce = rand::thread_rng().gen::<[u8; NONCE_LEN]>();
+
+    if header.profile_url.len() > u16::MAX.into() {
+        return Err(PayloadError::Encryption);
+    }
+
+    let wrapped_keys = if header.verifiers.is_empty() {
+        None
+    } else {
+        if header.verifiers.len() > u8::MAX.into() {
+            return Err(PayloadError::Encryption);
+        }
+        flags |= FLAG_VERIFIERS;
+        Some(WrappedKeys::new(&key, &header.verifiers)?)
+    };
+
+    let mut out = VERSION_1.to_vec();
+    header.serial(flags, wrapped_keys.as_ref(), &mut out);
+    out.extend_from_slice(&nonce);
+    let ciphertext = Aes256Gcm::new((&key).into())
+        .encrypt((&nonce).into(), Payload {
+            msg: payload,
+            aad: &out,
+        })
+        .map_err(|_| PayloadError::Encryption)?;
+    out.extend_from_slice(&ciphertext);
+    Ok((out, key))
+}
+
+/// Decrypt data in either format with the given key. Return the header, which
+/// is only present in version 1 data, and the decrypted data.
+pub fn open(data: &[u8], key: &[u8; 32]) -> Result<(Option<PayloadHeader>, Vec<u8>), PayloadError> {
+    Parsed::new(data)?.decrypt(key)
+}
+
+/// Decrypt version 1 data with the key derived from the passphrase. Return the
+/// header and the decrypted data.
+pub fn open_with_passphrase(
+    data: &[u8],
+    passphrase: &str,
+) -> Result<(PayloadHeader, Vec<u8>), PayloadError> {
+    let parsed = Parsed::new(data)?;
+    let key = match &parsed.header {
+        Some((_, header, _)) => match &header.passphrase {
+            Some(params) => params.derive_key(passphrase)?,
+            None => return Err(PayloadError::NoPassphrase),
+        },
+        None => return Err(PayloadError::NoPassphrase),
+    };
+    match parsed.decrypt(&key)? {
+        (Some(header), data) => Ok((header, data)),
+        (None, _) => Err(PayloadError::NoPassphrase),
+    }
+}
+
+/// Decrypt version 1 data with the X25519 secret key of a designated verifier.
+/// Return the header and the decrypted data. This is meant to be run by the
+/// verifier on the data from `/v1/proof/encrypted/:proofId`, so that its secret
+/// key is never sent to the service.
+pub fn open_as_verifier(
+    data: &[u8],
+    secret: &[u8; 32],
+) -> Result<(PayloadHeader, Vec<u8>), PayloadError> {
+    let parsed = Parsed::new(data)?;
+    let verifier = VerifierKey::from_secret(secret);
+    let key = match &parsed.header {
+        Some((_, header, Some(wrapped_keys))) => {
+            let index = header
+                .verifiers
+                .iter()
+                .position(|v| v == &verifier)
+                .ok_or(PayloadError::NotDesignated)?;
+            wrapped_keys.unwrap(index, &verifier, secret)?
+        }
+        _ => return Err(PayloadError::NotDesignated),
+    };
+    match parsed.decrypt(&key)? {
+        (Some(header), data) => Ok((header, data)),
+        (None, _) => Err(PayloadError::NotDesignated),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> PayloadHeader {
+        PayloadHeader {
+            platform:       SupportedPlatform::LinkedIn,
+            owner:          AccountAddress([1u8; 32]),
+            profile_url:    "https://www.linkedin.com/in/john-doe".into(),
+            minted_at:      1_700_000_000_000,
+            statement_hash: [2u8; 32],
+            key_id:         3,
+            verifiers:      Vec::new(),
+            passphrase:     None,
+        }
+    }
+
+    #[test]
+    /// Test that version 1 data can be decrypted, with and without compression.
+    fn test_seal_open() {
+        for data in [vec![7u8; 1000], b"abc".to_vec()] {
+            let (sealed, key) = seal(&header(), &data, None).expect("Encryption succeeds.");
+            assert_eq!(sealed[..4], VERSION_1);
+            let (opened_header, opened) = open(&sealed, &key).expect("Decryption succeeds.");
+            assert_eq!(opened_header, Some(header()));
+            assert_eq!(opened, data);
+        }
+    }
+
+    #[test]
+    /// Test that version 0 data can still be decrypted.
+    fn test_open_version_0() {
+        let key = [4u8; 32];
+        let nonce = [5u8; NONCE_LEN];
+        let mut sealed = VERSION_0.to_vec();
+        sealed.extend_from_slice(&nonce);
+        sealed.extend(
+            Aes256Gcm::new((&key).into())
+                .encrypt((&nonce).into(), &b"data"[..])
+                .unwrap(),
+        );
+        let (opened_header, opened) = open(&sealed, &key).expect("Decryption succeeds.");
+        assert_eq!(opened_header, None);
+        assert_eq!(opened, b"data");
+    }
+
+    #[test]
+    /// Test that the header cannot be modified, and that a wrong key is
+    /// rejected.
+    fn test_open_tampered() {
+        let (mut sealed, key) = seal(&header(), b"data", None).expect("Encryption succeeds.");
+        assert!(matches!(
+            open(&sealed, &[0u8; 32]),
+            Err(PayloadError::Decryption)
+        ));
+        // The first byte of the owner.
+        let owner_start = 4 + 1 + 4 + 8 + 32 + 2;
+        sealed[owner_start] ^= 1;
+        assert!(matches!(open(&sealed, &key), Err(PayloadError::Decryption)));
+    }
+
+    #[test]
+    /// Test that a profile URL whose length does not fit in the header is
+    /// rejected instead of being truncated.
+    fn test_seal_long_profile_url() {
+        let mut long = header();
+        long.profile_url = "a".repeat(usize::from(u16::MAX) + 1);
+        assert!(matches!(
+            seal(&long, b"data", None),
+            Err(PayloadError::Encryption)
+        ));
+        long.profile_url.pop();
+        let (sealed, key) = seal(&long, b"data", None).expect("Encryption succeeds.");
+        let (opened_header, _) = open(&sealed, &key).expect("Decryption succeeds.");
+        assert_eq!(opened_header, Some(long));
+    }
+
+    #[test]
+    /// Test that designated verifiers can decrypt the data with their secret
+    /// key, and that others cannot.
+    fn test_open_as_verifier() {
+        let secrets = [[8u8; 32], [9u8; 32]];
+        let mut header = header();
+        header.verifiers = secrets.iter().map(VerifierKey::from_secret).collect();
+        let (sealed, key) = seal(&header, b"data", None).expect("Encryption succeeds.");
+        let (opened_header, opened) = open(&sealed, &key).expect("Decryption succeeds.");
+        assert_eq!(opened_header, Some(header.clone()));
+        assert_eq!(opened, b"data");
+        for secret in &secrets {
+            let (opened_header, opened) =
+                open_as_verifier(&sealed, secret).expect("Decryption succeeds.");
+            assert_eq!(opened_header, header);
+            assert_eq!(opened, b"data");
+        }
+        assert!(matches!(
+            open_as_verifier(&sealed, &[10u8; 32]),
+            Err(PayloadError::NotDesignated)
+        ));
+    }
+
+    #[test]
+    /// Test that data with a key derived from a passphrase can be decrypted
+    /// with the passphrase, and not with another one.
+    fn test_open_with_passphrase() {
+        let mut protected = header();
+        protected.passphrase = Some(PassphraseParams::generate());
+        let (sealed, key) =
+            seal(&protected, b"data", Some("correct horse")).expect("Encryption succeeds.");
+        let (opened_header, opened) =
+            open_with_passphrase(&sealed, "correct horse").expect("Decryption succeeds.");
+        assert_eq!(opened_header, protected);
+        assert_eq!(opened, b"data");
+        let (_, opened) = open(&sealed, &key).expect("Decryption succeeds.");
+        assert_eq!(opened, b"data");
+        assert!(matches!(
+            open_with_passphrase(&sealed, "battery staple"),
+            Err(PayloadError::Decryption)
+        ));
+        let (sealed, _) = seal(&header(), b"data", None).expect("Encryption succeeds.");
+        assert!(matches!(
+            open_with_passphrase(&sealed, "correct horse"),
+            Err(PayloadError::NoPassphrase)
+        ));
+    }
+
+    #[test]
+    /// Test that keys are only derived with the parameters used by the service.
+    fn test_derive_key_params() {
+        let params = PassphraseParams::generate();
+        assert!(params.derive_key("correct horse").is_ok());
+        for params in [
+            PassphraseParams {
+                memory: PASSPHRASE_MEMORY + 1,
+                ..params.clone()
+            },
+            PassphraseParams {
+                iterations: u32::MAX,
+                ..params.clone()
+            },
+            PassphraseParams {
+                parallelism: 2,
+                ..params.clone()
+            },
+        ] {
+            assert!(matches!(
+                params.derive_key("correct horse"),
+                Err(PayloadError::Malformed)
+            ));
+        }
+    }
+}
@@ -1,6 +1,3 @@
-// TODO:
-// - allow anybody to mint for themselves (future version of contract).
-
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use concordium_cis2::*;
@@ -15,6 +12,34 @@ const SUPPORTS_STANDARDS: [StandardIdentifier<'static>; 2] =
 pub const GRANT_ROLE_EVENT_TAG: u8 = 0;
 /// Tag for the RevokeRole event.
 pub const REVOKE_ROLE_EVENT_TAG: u8 = 1;
+/// Tag for the AddIssuerKey event.
+pub const ADD_ISSUER_KEY_EVENT_TAG: u8 = 2;
+/// Tag for the RemoveIssuerKey event.
+pub const REMOVE_ISSUER_KEY_EVENT_TAG: u8 = 3;
+/// Tag for the RevokeByIssuer event.
+pub const REVOKE_BY_ISSUER_EVENT_TAG: u8 = 4;
+/// Tag for the TokenMinted event.
+pub const TOKEN_MINTED_EVENT_TAG: u8 = 5;
+/// Tag for the Pause event.
+pub const PAUSE_EVENT_TAG: u8 = 6;
+/// Tag for the Unpause event.
+pub const UNPAUSE_EVENT_TAG: u8 = 7;
+/// Tag for the ProposeAdmin event.
+pub const PROPOSE_ADMIN_EVENT_TAG: u8 = 8;
+/// Tag for the AcceptAdmin event.
+pub const ACCEPT_ADMIN_EVENT_TAG: u8 = 9;
+/// Tag for the Reissue event.
+pub const REISSUE_EVENT_TAG: u8 = 10;
+/// Tag for the PlatformMetadataUrl event.
+pub const PLATFORM_METADATA_URL_EVENT_TAG: u8 = 11;
+/// Tag for the UpdateData event.
+pub const UPDATE_DATA_EVENT_TAG: u8 = 12;
+/// Tag for the Nonce event. The same tag is used by the CIS-3 standard.
+pub const NONCE_EVENT_TAG: u8 = 250;
+
+/// Version of the layout of the contract state. This must be incremented,
+/// and a migration added to `migrate`, whenever the layout changes.
+const STATE_VERSION: u32 = 1;
 
 // Types
 
@@ -27,7 +52,7 @@ pub type ContractTokenId = TokenIdU64;
 type ContractTokenAmount = TokenAmountU8;
 
 /// Platform is a string of two bytes that are a valid UTF8 string
-#[derive(Serial, SchemaType, Clone, Copy)]
+#[derive(Serial, SchemaType, Clone, Copy, PartialEq, Eq)]
 struct Platform([u8; 2]);
 
 impl Deserial for Platform {
@@ -47,6 +72,29 @@ impl core::fmt::Display for Platform {
     }
 }
 
+/// The reason recorded when a token is revoked by an issuer.
+#[derive(Serialize, SchemaType, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RevocationReason {
+    /// The token was obtained fraudulently, e.g., by impersonating someone.
+    Fraud,
+    /// The identity used to obtain the token has been compromised.
+    IdentityCompromised,
+    /// The owner asked for the token to be revoked.
+    UserRequest,
+    /// The token has been replaced by a newer one.
+    Superseded,
+}
+
+/// A commitment to the data of a token which is stored off-chain.
+#[derive(Serialize, SchemaType, Clone, Debug, PartialEq, Eq)]
+struct DataCommitment {
+    /// The SHA-256 hash of the off-chain data.
+    hash: [u8; 32],
+    /// Where the off-chain data can be retrieved from, e.g., a URL.
+    #[concordium(size_length = 2)]
+    locator: String,
+}
+
 // State
 
 /// The state for each address.
@@ -75,7 +123,25 @@ struct TokenState<S: HasStateApi> {
     /// Platform associated with the token.
     platform: Platform,
     /// The data, which includes the proof, challenge, name, social media URL (e.g linkedin URL).
-    data: StateBox<Vec<u8>, S>,
+    /// This is `None` if the data is stored off-chain or the token was burned.
+    data: Option<StateBox<Vec<u8>, S>>,
+    /// Commitment to the data if it is stored off-chain instead of in `data`.
+    commitment: Option<DataCommitment>,
+    /// The reason the token was revoked, if it was revoked by an issuer.
+    revocation_reason: Option<RevocationReason>,
+    /// Slot time of the block in which the token was minted.
+    issued_at: Timestamp,
+    /// Time until which the token is valid, if it expires.
+    valid_until: Option<Timestamp>,
+    /// Time at which the token was burned by its owner. The data of a burned
+    /// token is erased.
+    burned_at: Option<Timestamp>,
+    /// The token this token was reissued to replace, if any.
+    replaces: Option<ContractTokenId>,
+    /// The token this token was replaced by when it was reissued, if any.
+    replaced_by: Option<ContractTokenId>,
+    /// SHA-256 hash of the metadata document of the token, if recorded.
+    metadata_hash: Option<[u8; 32]>,
 }
 
 #[derive(Serial, DeserialWithState, Deletable)]
@@ -94,7 +160,7 @@ struct State<S: HasStateApi> {
     next_token_id: u64,
     /// Roles that are granted to special addresses.
     roles: StateMap<Address, AddressRoleState<S>, S>,
-    /// The state for each address.
+    /// The state for each account, keyed by its canonical address.
     state: StateMap<AccountAddress, AddressState<S>, S>,
     /// All of the token IDs
     all_tokens: StateMap<ContractTokenId, TokenState<S>, S>,
@@ -106,6 +172,108 @@ struct State<S: HasStateApi> {
     /// in the situations when one wants to do a partial update not touching
     /// this field, which can be large.
     metadata_url: StateBox<concordium_cis2::MetadataUrl, S>,
+    /// Metadata URLs of the tokens of specific platforms. Tokens of platforms
+    /// without an entry use `metadata_url`.
+    platform_metadata_urls: StateMap<Platform, String, S>,
+    /// Public keys of the issuers whose signed permits are accepted by `permit`.
+    issuer_keys: StateSet<PublicKeyEd25519, S>,
+    /// The nonce expected in the next permit of each account, keyed by its
+    /// canonical address.
+    nonces: StateMap<AccountAddress, u64, S>,
+    /// Whether the contract is paused. While paused no tokens can be minted or
+    /// updated and no platform metadata URLs set, but tokens can still be
+    /// burned and revoked, and roles and keys managed.
+    paused: bool,
+    /// A pending handover of the Admin role, which takes effect once accepted.
+    admin_proposal: Option<AdminProposal>,
+    /// The number of active and revoked tokens of each platform.
+    platform_counts: StateMap<Platform, TokenCounts, S>,
+    /// The number of active tokens of each account, keyed by its canonical
+    /// address. Accounts without active tokens have no entry.
+    owner_token_counts: StateMap<AccountAddress, u64, S>,
+    /// The number of accounts with active tokens.
+    owner_count: u64,
+    /// Version of the layout of the state, which `migrate` converts the state
+    /// from. The layout from before the state was versioned has no version,
+    /// and is recognised by not being readable in the current layout.
+    version: u32,
+    /// Tokens that are still stored in the layout of an older version and have
+    /// to be migrated by `migrate`.
+    token_migration: Option<TokenMigration>,
+}
+
+/// The number of tokens of a platform. Burned tokens count as revoked.
+#[derive(Serialize, SchemaType, Clone, Copy, Default)]
+struct TokenCounts {
+    /// Tokens that are neither revoked nor burned.
+    active: u64,
+    /// Tokens that are revoked or burned.
+    revoked: u64,
+}
+
+/// A proposal to hand over the Admin role of `proposer` to `address`.
+#[derive(Serialize, Clone, Copy)]
+struct AdminProposal {
+    /// The admin handing over the role.
+    proposer: Address,
+    /// The address that has to accept the role.
+    address: Address,
+}
+
+/// The range of tokens still to be migrated to the current layout.
+#[derive(Serialize, Clone, Copy)]
+struct TokenMigration {
+    /// The next token to migrate.
+    next: u64,
+    /// The ID of the first token minted after the migration started. Tokens
+    /// from here on are stored in the current layout.
+    end: u64,
+}
+
+/// The layout of `TokenState` before the state was versioned.
+#[derive(Serial, DeserialWithState)]
+#[concordium(state_parameter = "S")]
+struct TokenStateV0<S: HasStateApi> {
+    revoked: bool,
+    owner: AccountAddress,
+    platform: Platform,
+    data: StateBox<Vec<u8>, S>,
+}
+
+/// The layout of `State` before the state was versioned. The values of
+/// `all_tokens` are stored in the layout of `TokenStateV0`, and are read
+/// entry by entry with `State::take_token_v0` since the map cannot read them.
+#[derive(Serial, DeserialWithState)]
+#[concordium(state_parameter = "S")]
+struct StateV0<S: HasStateApi> {
+    next_token_id: u64,
+    roles: StateMap<Address, AddressRoleState<S>, S>,
+    state: StateMap<AccountAddress, AddressState<S>, S>,
+    all_tokens: StateMap<ContractTokenId, TokenState<S>, S>,
+    implementors: StateMap<StandardIdentifierOwned, Vec<ContractAddress>, S>,
+    metadata_url: StateBox<concordium_cis2::MetadataUrl, S>,
+}
+
+impl<S: HasStateApi> TokenState<S> {
+    /// Replace the data of the token, deleting the previously stored data.
+    /// Empty data is not stored.
+    fn replace_data(&mut self, data: Vec<u8>, state_builder: &mut StateBuilder<S>) {
+        let data = if data.is_empty() {
+            None
+        } else {
+            Some(state_builder.new_box(data))
+        };
+        if let Some(old_data) = core::mem::replace(&mut self.data, data) {
+            old_data.delete();
+        }
+    }
+
+    /// The data of the token, or empty data if none is stored.
+    fn data(&self) -> Vec<u8> {
+        self.data
+            .as_ref()
+            .map_or_else(Vec::new, |data| data.to_vec())
+    }
 }
 
 // Functions for creating, updating and querying the contract state.
@@ -122,6 +290,16 @@ impl<S: HasStateApi> State<S> {
             all_tokens: state_builder.new_map(),
             implementors: state_builder.new_map(),
             metadata_url: state_builder.new_box(metadata_url),
+            platform_metadata_urls: state_builder.new_map(),
+            issuer_keys: state_builder.new_set(),
+            nonces: state_builder.new_map(),
+            paused: false,
+            admin_proposal: None,
+            platform_counts: state_builder.new_map(),
+            owner_token_counts: state_builder.new_map(),
+            owner_count: 0,
+            version: STATE_VERSION,
+            token_migration: None,
         }
     }
 
@@ -150,52 +328,387 @@ impl<S: HasStateApi> State<S> {
         });
     }
 
+    /// Revoke a role, unless it is the Admin role of the last admin.
+    fn revoke_role_checked(&mut self, account: &Address, role: Roles) -> ContractResult<()> {
+        ensure!(
+            self.has_role(account, role),
+            ContractError::Custom(CustomContractError::RoleNotAssigned)
+        );
+        ensure!(
+            role != Roles::Admin || self.admin_count() > 1,
+            ContractError::Custom(CustomContractError::LastAdmin)
+        );
+        self.revoke_role(account, role);
+        Ok(())
+    }
+
+    /// The number of addresses with the Admin role.
+    fn admin_count(&self) -> usize {
+        self.roles
+            .iter()
+            .filter(|(_, entry)| entry.roles.contains(&Roles::Admin))
+            .count()
+    }
+
+    /// Count a new token of `owner` with the given platform.
+    fn count_token(&mut self, owner: &AccountAddress, platform: Platform, revoked: bool) {
+        let mut counts = self
+            .platform_counts
+            .entry(platform)
+            .or_insert_with(TokenCounts::default);
+        if revoked {
+            counts.revoked += 1;
+        } else {
+            counts.active += 1;
+        }
+        drop(counts);
+        if !revoked {
+            self.add_owner_token(owner);
+        }
+    }
+
+    /// Count an active token of `owner` with the given platform as revoked.
+    fn count_revoked(&mut self, owner: &AccountAddress, platform: Platform) {
+        if let Some(mut counts) = self.platform_counts.get_mut(&platform) {
+            counts.active = counts.active.saturating_sub(1);
+            counts.revoked += 1;
+        }
+        self.remove_owner_token(owner);
+    }
+
+    /// Count an active token for `owner`.
+    fn add_owner_token(&mut self, owner: &AccountAddress) {
+        let mut count = self
+            .owner_token_counts
+            .entry(canonical_address(owner))
+            .or_insert_with(|| 0);
+        if *count == 0 {
+            self.owner_count += 1;
+        }
+        *count += 1;
+    }
+
+    /// Stop counting an active token for `owner`.
+    fn remove_owner_token(&mut self, owner: &AccountAddress) {
+        let owner = canonical_address(owner);
+        let remaining = match self.owner_token_counts.get_mut(&owner) {
+            Some(mut count) => {
+                *count = count.saturating_sub(1);
+                *count
+            }
+            None => return,
+        };
+        if remaining == 0 {
+            self.owner_token_counts.remove(&owner);
+            self.owner_count = self.owner_count.saturating_sub(1);
+        }
+    }
+
+    /// Check that the contract is not paused.
+    fn ensure_not_paused(&self) -> ContractResult<()> {
+        ensure!(
+            !self.paused,
+            ContractError::Custom(CustomContractError::Paused)
+        );
+        Ok(())
+    }
+
+    /// Check that all tokens are stored in the current layout. Tokens of an
+    /// older layout cannot be read until they are converted by `migrate`.
+    fn ensure_migrated(&self) -> ContractResult<()> {
+        ensure!(
+            self.token_migration.is_none(),
+            ContractError::Custom(CustomContractError::MigrationInProgress)
+        );
+        Ok(())
+    }
+
+    /// Check that the nonce matches the next nonce expected from the account,
+    /// and if so increment the stored nonce.
+    fn use_nonce(&mut self, account: AccountAddress, nonce: u64) -> ContractResult<()> {
+        let mut entry = self
+            .nonces
+            .entry(canonical_address(&account))
+            .or_insert_with(|| 0);
+        ensure!(
+            *entry == nonce,
+            ContractError::Custom(CustomContractError::NonceMismatch)
+        );
+        *entry += 1;
+        Ok(())
+    }
+
+    /// Return the addresses the tokens of `owner` may be stored under: its
+    /// canonical address, and `owner` itself for tokens stored before the state
+    /// was keyed by canonical addresses.
+    fn owner_keys(owner: &AccountAddress) -> Vec<AccountAddress> {
+        let canonical = canonical_address(owner);
+        if canonical == *owner {
+            vec![canonical]
+        } else {
+            vec![canonical, *owner]
+        }
+    }
+
+    /// Return the addresses `token_id` may be stored under if it is held by
+    /// `owner`. Besides the keys of `owner` this is the address the token was
+    /// minted to, which may be another alias of the same account.
+    fn token_keys(
+        &self,
+        owner: &AccountAddress,
+        token_id: &ContractTokenId,
+    ) -> Vec<AccountAddress> {
+        let mut keys = Self::owner_keys(owner);
+        if let Some(minted_to) = self.all_tokens.get(token_id).map(|token| token.owner) {
+            if canonical_address(&minted_to) == canonical_address(owner)
+                && !keys.contains(&minted_to)
+            {
+                keys.push(minted_to);
+            }
+        }
+        keys
+    }
+
+    /// Check whether `owner` holds `token_id`.
+    fn owns_token(&self, owner: &AccountAddress, token_id: &ContractTokenId) -> bool {
+        self.token_keys(owner, token_id).iter().any(|key| {
+            self.state.get(key).map_or(false, |address_state| {
+                address_state.owned_tokens.contains(token_id)
+            })
+        })
+    }
+
+    /// Remove `token_id` from the tokens held by `owner`. Return whether the
+    /// owner held the token.
+    fn remove_owned_token(&mut self, owner: &AccountAddress, token_id: &ContractTokenId) -> bool {
+        self.token_keys(owner, token_id).iter().any(|key| {
+            self.state.get_mut(key).map_or(false, |mut address_state| {
+                address_state.owned_tokens.remove(token_id)
+            })
+        })
+    }
+
     /// Mint a new token with a given address as the owner.
+    /// The data is either stored in the state, or, if `commitment` is set,
+    /// off-chain in which case `data` must be empty.
+    /// The token is issued at `issued_at` and, if `valid_until` is set, is valid
+    /// until then.
     /// Return the ID of the newly minted token.
+    #[allow(clippy::too_many_arguments)]
     fn mint(
         &mut self,
         owner: AccountAddress,
         platform: Platform,
         data: Vec<u8>,
+        commitment: Option<DataCommitment>,
+        issued_at: Timestamp,
+        valid_until: Option<Timestamp>,
         state_builder: &mut StateBuilder<S>,
     ) -> ContractResult<ContractTokenId> {
+        ensure!(
+            commitment.is_none() || data.is_empty(),
+            ContractError::Custom(CustomContractError::DataAndCommitment)
+        );
+        if let Some(valid_until) = valid_until {
+            ensure!(
+                valid_until > issued_at,
+                ContractError::Custom(CustomContractError::ValidUntilInPast)
+            );
+        }
         let token = TokenIdU64(self.next_token_id);
         self.next_token_id += 1;
-        let token_state = TokenState {
-            data: state_builder.new_box(data),
+        let mut token_state = TokenState {
+            data: None,
+            commitment,
             revoked: false,
             platform,
             owner,
+            revocation_reason: None,
+            issued_at,
+            valid_until,
+            burned_at: None,
+            replaces: None,
+            replaced_by: None,
+            metadata_hash: None,
         };
+        token_state.replace_data(data, state_builder);
         self.all_tokens.insert(token, token_state);
         let mut owner_state = self
             .state
-            .entry(owner)
+            .entry(canonical_address(&owner))
             .or_insert_with(|| AddressState::empty(state_builder));
         owner_state.owned_tokens.insert(token);
+        drop(owner_state);
+        self.count_token(&owner, platform, false);
         Ok(token)
     }
 
-    fn burn(&mut self, token_id: &ContractTokenId, owner: &Address) -> ContractResult<()> {
+    /// Burn a token of the given owner at time `now`. The data of the token is
+    /// erased and only a tombstone with the owner, platform and the time it was
+    /// burned is kept.
+    fn burn(
+        &mut self,
+        token_id: &ContractTokenId,
+        owner: &Address,
+        now: Timestamp,
+        state_builder: &mut StateBuilder<S>,
+    ) -> ContractResult<()> {
         let owner = match owner {
             Address::Account(owner) => owner,
             Address::Contract(_) => {
                 return Err(ContractError::InsufficientFunds);
             }
         };
-        let mut owner_address_state = self
-            .state
-            .get_mut(owner)
-            .ok_or(ContractError::InsufficientFunds)?;
-
-        let owner_had_the_token = owner_address_state.owned_tokens.remove(token_id);
+        let owner_had_the_token = self.remove_owned_token(owner, token_id);
         ensure!(owner_had_the_token, ContractError::InsufficientFunds);
 
-        if let Some(mut r) = self.all_tokens.get_mut(token_id) {
+        let platform = if let Some(mut r) = self.all_tokens.get_mut(token_id) {
             r.revoked = true;
-            Ok(())
+            r.burned_at = Some(now);
+            r.replace_data(Vec::new(), state_builder);
+            r.commitment = None;
+            // The metadata URL changes for a burned token.
+            r.metadata_hash = None;
+            r.platform
         } else {
-            Err(ContractError::InvalidTokenId)
+            return Err(ContractError::InvalidTokenId);
+        };
+        self.count_revoked(owner, platform);
+        Ok(())
+    }
+
+    /// Revoke a token regardless of who owns it and record the reason.
+    /// Return the owner of the token.
+    fn revoke(
+        &mut self,
+        token_id: &ContractTokenId,
+        reason: RevocationReason,
+    ) -> ContractResult<AccountAddress> {
+        let mut token = self
+            .all_tokens
+            .get_mut(token_id)
+            .ok_or(ContractError::InvalidTokenId)?;
+        ensure!(
+            !token.revoked,
+            ContractError::Custom(CustomContractError::TokenRevoked)
+        );
+        token.revoked = true;
+        token.revocation_reason = Some(reason);
+        // The metadata URL changes for a revoked token.
+        token.metadata_hash = None;
+        let owner = token.owner;
+        let platform = token.platform;
+        drop(token);
+
+        self.remove_owned_token(&owner, token_id);
+        self.count_revoked(&owner, platform);
+        Ok(owner)
+    }
+
+    /// Move a token which is not revoked to a new owner, replacing its data
+    /// with `data` or `commitment`, which must be a proof of the new owner.
+    /// Return the previous owner of the token.
+    fn recover(
+        &mut self,
+        token_id: &ContractTokenId,
+        new_owner: AccountAddress,
+        data: Vec<u8>,
+        commitment: Option<DataCommitment>,
+        state_builder: &mut StateBuilder<S>,
+    ) -> ContractResult<AccountAddress> {
+        ensure!(
+            commitment.is_none() || data.is_empty(),
+            ContractError::Custom(CustomContractError::DataAndCommitment)
+        );
+        let mut token = self
+            .all_tokens
+            .get_mut(token_id)
+            .ok_or(ContractError::InvalidTokenId)?;
+        ensure!(
+            !token.revoked,
+            ContractError::Custom(CustomContractError::TokenRevoked)
+        );
+        let owner = token.owner;
+        ensure!(
+            canonical_address(&owner) != canonical_address(&new_owner),
+            ContractError::Custom(CustomContractError::SameOwner)
+        );
+        token.owner = new_owner;
+        token.replace_data(data, state_builder);
+        token.commitment = commitment;
+        drop(token);
+
+        self.remove_owned_token(&owner, token_id);
+        let mut new_owner_state = self
+            .state
+            .entry(canonical_address(&new_owner))
+            .or_insert_with(|| AddressState::empty(state_builder));
+        new_owner_state.owned_tokens.insert(*token_id);
+        drop(new_owner_state);
+        self.remove_owner_token(&owner);
+        self.add_owner_token(&new_owner);
+        Ok(owner)
+    }
+
+    /// Replace the data of a token which is not revoked with `data` or
+    /// `commitment`, e.g., when the data is encrypted with a new key.
+    fn update_data(
+        &mut self,
+        token_id: &ContractTokenId,
+        data: Vec<u8>,
+        commitment: Option<DataCommitment>,
+        state_builder: &mut StateBuilder<S>,
+    ) -> ContractResult<()> {
+        ensure!(
+            commitment.is_none() || data.is_empty(),
+            ContractError::Custom(CustomContractError::DataAndCommitment)
+        );
+        let mut token = self
+            .all_tokens
+            .get_mut(token_id)
+            .ok_or(ContractError::InvalidTokenId)?;
+        ensure!(
+            !token.revoked,
+            ContractError::Custom(CustomContractError::TokenRevoked)
+        );
+        token.replace_data(data, state_builder);
+        token.commitment = commitment;
+        Ok(())
+    }
+
+    /// Find a token owned by `owner` which is neither revoked nor expired at
+    /// time `now`. If `platform` is given the token must be associated with it,
+    /// and if `token_id` is given only that token is considered.
+    fn active_token(
+        &self,
+        owner: &AccountAddress,
+        platform: Option<Platform>,
+        token_id: Option<ContractTokenId>,
+        now: Timestamp,
+    ) -> Option<ContractTokenId> {
+        let is_active = |token_id: &ContractTokenId| {
+            self.all_tokens.get(token_id).map_or(false, |token| {
+                !token.revoked
+                    && platform.map_or(true, |p| p == token.platform)
+                    && token.valid_until.map_or(true, |t| t > now)
+            })
+        };
+        match token_id {
+            Some(token_id) => {
+                if self.owns_token(owner, &token_id) && is_active(&token_id) {
+                    Some(token_id)
+                } else {
+                    None
+                }
+            }
+            None => Self::owner_keys(owner).iter().find_map(|key| {
+                self.state
+                    .get(key)?
+                    .owned_tokens
+                    .iter()
+                    .map(|token_id| *token_id)
+                    .find(|token_id| is_active(token_id))
+            }),
         }
     }
 
@@ -217,11 +730,7 @@ impl<S: HasStateApi> State<S> {
         ensure!(self.contains_token(token_id), ContractError::InvalidTokenId);
         match address {
             Address::Account(address) => {
-                let balance = self
-                    .state
-                    .get(address)
-                    .map(|address_state| u8::from(address_state.owned_tokens.contains(token_id)))
-                    .unwrap_or(0);
+                let balance = u8::from(self.owns_token(address, token_id));
                 Ok(balance.into())
             }
             Address::Contract(_) => Ok(0.into()),
@@ -247,23 +756,173 @@ impl<S: HasStateApi> State<S> {
     }
 
     /// Build a string from the metadata_url appended with the tokenId, platform and if it is revoked using the URL query string format.
+    /// If the token expires the expiry time, in milliseconds since the unix epoch, is appended as well.
+    /// If the platform has its own metadata URL that is used instead of metadata_url.
     fn build_token_metadata_url(
         &self,
         token_id: &ContractTokenId,
         platform: Platform,
         revoked: bool,
+        valid_until: Option<Timestamp>,
     ) -> String {
-        let mut token_metadata_url = self.metadata_url.url.clone();
+        let mut token_metadata_url = match self.platform_metadata_urls.get(&platform) {
+            Some(url) => url.clone(),
+            None => self.metadata_url.url.clone(),
+        };
 
         let _ = write!(
             token_metadata_url,
             "{}",
             &format!("{}?p={}&r={}", token_id.0, platform, u8::from(revoked))
         );
+        if let Some(valid_until) = valid_until {
+            let _ = write!(token_metadata_url, "&e={}", valid_until.timestamp_millis());
+        }
         token_metadata_url
     }
 }
 
+// Functions for migrating the contract state from older layouts.
+impl<S: HasStateApi> State<S> {
+    /// Read the state, converting the top-level state from an older layout if
+    /// necessary. The tokens are not converted, but are recorded in
+    /// `token_migration` to be converted by `migrate_tokens`. A state of a
+    /// later version than this contract, e.g., after a downgrade, is rejected.
+    fn migrate_root(state_api: &S) -> ParseResult<Self> {
+        if let Ok(state) = state_api.read_root::<State<S>>() {
+            return match state.version {
+                STATE_VERSION => Ok(state),
+                _ => Err(ParseError::default()),
+            };
+        }
+        let old: StateV0<S> = state_api.read_root()?;
+        let mut state_builder = StateBuilder::open(state_api.clone());
+        let token_migration = if old.next_token_id > 0 {
+            Some(TokenMigration {
+                next: 0,
+                end: old.next_token_id,
+            })
+        } else {
+            None
+        };
+        Ok(State {
+            next_token_id: old.next_token_id,
+            roles: old.roles,
+            state: old.state,
+            all_tokens: old.all_tokens,
+            implementors: old.implementors,
+            metadata_url: old.metadata_url,
+            platform_metadata_urls: state_builder.new_map(),
+            issuer_keys: state_builder.new_set(),
+            nonces: state_builder.new_map(),
+            paused: false,
+            admin_proposal: None,
+            platform_counts: state_builder.new_map(),
+            owner_token_counts: state_builder.new_map(),
+            owner_count: 0,
+            version: STATE_VERSION,
+            token_migration,
+        })
+    }
+
+    /// Remove the entry of `token_id` stored in the layout of `TokenStateV0`
+    /// from `all_tokens` and return it, reading the entry directly since the
+    /// map can only read the current layout.
+    fn take_token_v0(
+        &self,
+        state_api: &mut S,
+        token_id: &ContractTokenId,
+    ) -> ParseResult<Option<TokenStateV0<S>>> {
+        // Entries of a map are keyed by the prefix of the map followed by the
+        // serialized key.
+        let mut key = to_bytes(&self.all_tokens);
+        key.extend(to_bytes(token_id));
+        let mut entry = match state_api.lookup_entry(&key) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let token = TokenStateV0::deserial_with_state(state_api, &mut entry)?;
+        state_api
+            .delete_entry(entry)
+            .map_err(|_| ParseError::default())?;
+        Ok(Some(token))
+    }
+
+    /// Convert at most `limit` tokens stored in an older layout to the current
+    /// layout. Tokens owned by an alias are moved to the canonical address of
+    /// the account. Tokens that were burned lose their data and are recorded as
+    /// burned at `now`, since the time they were burned was not recorded.
+    /// Return whether all tokens have been converted.
+    fn migrate_tokens(&mut self, state_api: &S, limit: u32, now: Timestamp) -> ParseResult<bool> {
+        let migration = match self.token_migration {
+            Some(migration) => migration,
+            None => return Ok(true),
+        };
+        let mut state_api = state_api.clone();
+        let mut state_builder = StateBuilder::open(state_api.clone());
+        let end = core::cmp::min(migration.next.saturating_add(limit.into()), migration.end);
+        for id in migration.next..end {
+            let token_id = TokenIdU64(id);
+            let old = match self.take_token_v0(&mut state_api, &token_id)? {
+                Some(old) => old,
+                None => continue,
+            };
+            let canonical = canonical_address(&old.owner);
+            if canonical != old.owner {
+                let owned = self
+                    .state
+                    .get_mut(&old.owner)
+                    .map_or(false, |mut address_state| {
+                        address_state.owned_tokens.remove(&token_id)
+                    });
+                if owned {
+                    let mut owner_state = self
+                        .state
+                        .entry(canonical)
+                        .or_insert_with(|| AddressState::empty(&mut state_builder));
+                    owner_state.owned_tokens.insert(token_id);
+                }
+            }
+            self.count_token(&old.owner, old.platform, old.revoked);
+            // Before the state was versioned tokens could only be revoked by
+            // burning them.
+            let (data, burned_at) = if old.revoked {
+                old.data.delete();
+                (None, Some(now))
+            } else {
+                (Some(old.data), None)
+            };
+            self.all_tokens.insert(
+                token_id,
+                TokenState {
+                    revoked: old.revoked,
+                    owner: old.owner,
+                    platform: old.platform,
+                    data,
+                    commitment: None,
+                    revocation_reason: None,
+                    // The time of issuance was not recorded.
+                    issued_at: Timestamp::from_timestamp_millis(0),
+                    valid_until: None,
+                    burned_at,
+                    replaces: None,
+                    replaced_by: None,
+                    metadata_hash: None,
+                },
+            );
+        }
+        self.token_migration = if end < migration.end {
+            Some(TokenMigration {
+                next: end,
+                end: migration.end,
+            })
+        } else {
+            None
+        };
+        Ok(self.token_migration.is_none())
+    }
+}
+
 // Errors
 
 /// The custom errors the contract can produce.
@@ -288,6 +947,36 @@ enum CustomContractError {
     FailedUpgradeUnsupportedModuleVersion,
     // Role is not assigned.
     RoleNotAssigned,
+    /// The permit was not signed by a registered issuer key.
+    UnknownIssuerKey,
+    /// The signature of the permit is invalid.
+    WrongSignature,
+    /// The nonce of the permit does not match the nonce of the account.
+    NonceMismatch,
+    /// The permit is intended for a different contract.
+    WrongContract,
+    /// The permit has expired.
+    Expired,
+    /// The token has already been revoked.
+    TokenRevoked,
+    /// The end of the validity window of the token is not after the time it is
+    /// issued.
+    ValidUntilInPast,
+    /// The page of tokens to view is empty.
+    ZeroLimit,
+    /// Both data and a commitment to off-chain data were given.
+    DataAndCommitment,
+    /// Tokens are still stored in the layout of an older version and have to
+    /// be migrated by `migrate` first.
+    MigrationInProgress,
+    /// The contract is paused.
+    Paused,
+    /// The Admin role of the last admin cannot be revoked.
+    LastAdmin,
+    /// The owner of the token to reissue is not the owner of the new token.
+    WrongOwner,
+    /// The token to recover is already owned by the given account.
+    SameOwner,
 }
 
 /// Wrapping the custom errors in a type with CIS2 errors.
@@ -295,6 +984,19 @@ type ContractError = Cis2Error<CustomContractError>;
 
 type ContractResult<A> = Result<A, ContractError>;
 
+/// Aliases of an account share the first 29 bytes of the address.
+const ACCOUNT_ADDRESS_ALIAS_PREFIX_LEN: usize = 29;
+
+/// Return the canonical address of an account, i.e., the alias where the last
+/// bytes of the address are zero. All aliases of an account have the same
+/// canonical address, which is what the state is keyed by.
+fn canonical_address(address: &AccountAddress) -> AccountAddress {
+    let mut canonical = [0u8; 32];
+    canonical[..ACCOUNT_ADDRESS_ALIAS_PREFIX_LEN]
+        .copy_from_slice(&address.0[..ACCOUNT_ADDRESS_ALIAS_PREFIX_LEN]);
+    AccountAddress(canonical)
+}
+
 /// Mapping the logging errors to CustomContractError.
 impl From<LogError> for CustomContractError {
     fn from(le: LogError) -> Self {
@@ -344,6 +1046,60 @@ struct MintParams {
     /// The data, which includes the proof, challenge, name, social media URL (e.g linkedin URL).
     #[concordium(size_length = 2)]
     data: Vec<u8>,
+    /// Time until which the token is valid. If not set the token does not
+    /// expire.
+    valid_until: Option<Timestamp>,
+    /// Commitment to the data if it is stored off-chain. In that case `data`
+    /// must be empty.
+    commitment: Option<DataCommitment>,
+}
+
+/// The message signed by an issuer to authorize an account to mint a token
+/// for itself.
+#[derive(Serialize, SchemaType)]
+struct PermitMessage {
+    /// The contract instance the permit is intended for.
+    contract_address: ContractAddress,
+    /// Nonce of the owner of the token. Prevents replaying the permit.
+    nonce: u64,
+    /// Time until which the permit can be used.
+    timestamp: Timestamp,
+    /// The token to be minted.
+    mint: MintParams,
+}
+
+/// The parameter type for the contract function `permit`.
+#[derive(Serialize, SchemaType)]
+struct PermitParams {
+    /// The issuer key that signed the message.
+    signer: PublicKeyEd25519,
+    /// Signature of the issuer on the SHA-256 hash of the serialized message.
+    signature: SignatureEd25519,
+    /// The signed message.
+    message: PermitMessage,
+}
+
+/// The parameter type for the contract function `nonceOf`.
+#[derive(Serialize, SchemaType)]
+struct NonceOfQueryParams {
+    /// Accounts to query the next nonce of.
+    #[concordium(size_length = 2)]
+    queries: Vec<AccountAddress>,
+}
+
+/// The response which is sent back when calling the contract function
+/// `nonceOf`. It contains the next nonce of each queried account, in the order
+/// of the queries.
+#[derive(Serialize, SchemaType)]
+struct NonceOfQueryResponse(#[concordium(size_length = 2)] Vec<u64>);
+
+/// The parameter type for the contract function `revokeByIssuer`.
+#[derive(Serialize, SchemaType)]
+struct RevokeByIssuerParams {
+    /// The token to revoke.
+    token_id: ContractTokenId,
+    /// The reason for revoking the token.
+    reason: RevocationReason,
 }
 
 /// The parameter type for the contract function `upgrade`.
@@ -366,6 +1122,25 @@ struct SetMetadataUrlParams {
     url: String,
 }
 
+/// The parameter type for the contract function `setPlatformMetadataUrl`.
+#[derive(Serialize, SchemaType)]
+struct SetPlatformMetadataUrlParams {
+    /// The platform to set the metadata URL for.
+    platform: Platform,
+    /// The URL following the specification RFC1738. If not set, tokens of the
+    /// platform use the global metadata URL again.
+    url: Option<String>,
+}
+
+/// The parameter type for the contract function `updateTokenMetadata`.
+#[derive(Serialize, SchemaType)]
+struct UpdateTokenMetadataParams {
+    /// The token to update.
+    token_id: ContractTokenId,
+    /// SHA-256 hash of the current metadata document of the token.
+    metadata_hash: Option<[u8; 32]>,
+}
+
 /// The parameter type for the contract function `setImplementors`.
 /// Takes a standard identifier and list of contract addresses providing
 /// implementations of this standard.
@@ -388,6 +1163,12 @@ pub enum Roles {
     Upgrader,
     /// The setter can update the metadata URL.
     Setter,
+    /// The revoker can revoke any token.
+    Revoker,
+    /// The pauser can pause and unpause the contract.
+    Pauser,
+    /// The recoverer can move a token to a new account of the same identity.
+    Recoverer,
 }
 
 /// Manual implementation of the `Roles` schema.
@@ -398,6 +1179,9 @@ impl schema::SchemaType for Roles {
             ("Minter".to_string(), schema::Fields::None),
             ("Upgrader".to_string(), schema::Fields::None),
             ("Setter".to_string(), schema::Fields::None),
+            ("Revoker".to_string(), schema::Fields::None),
+            ("Pauser".to_string(), schema::Fields::None),
+            ("Recoverer".to_string(), schema::Fields::None),
         ])
     }
 }
@@ -455,10 +1239,109 @@ pub struct RevokeRoleEvent {
     role: Roles,
 }
 
+/// An IssuerKeyEvent introduced by this smart contract. It is logged both when
+/// a key is added and when it is removed.
+#[derive(Serialize, SchemaType)]
+pub struct IssuerKeyEvent {
+    /// The issuer key that was added or removed.
+    key: PublicKeyEd25519,
+}
+
+/// A RevokeByIssuerEvent introduced by this smart contract.
+#[derive(Serialize, SchemaType)]
+pub struct RevokeByIssuerEvent {
+    /// The token that was revoked.
+    token_id: ContractTokenId,
+    /// The reason the token was revoked.
+    reason: RevocationReason,
+}
+
+/// A TokenMintedEvent introduced by this smart contract. It is logged in
+/// addition to the CIS-2 `Mint` event and carries the platform of the token.
+#[derive(Serialize, SchemaType)]
+pub struct TokenMintedEvent {
+    /// The token that was minted.
+    token_id: ContractTokenId,
+    /// Owner of the token.
+    owner: AccountAddress,
+    /// Platform associated with the token.
+    platform: Platform,
+    /// Slot time of the block in which the token was minted.
+    issued_at: Timestamp,
+}
+
+/// A PauseEvent introduced by this smart contract. It is logged both when the
+/// contract is paused and when it is unpaused.
+#[derive(Serialize, SchemaType)]
+pub struct PauseEvent {
+    /// The address that paused or unpaused the contract.
+    address: Address,
+}
+
+/// An AdminHandoverEvent introduced by this smart contract. It is logged both
+/// when a handover of the Admin role is proposed and when it is accepted.
+#[derive(Serialize, SchemaType)]
+pub struct AdminHandoverEvent {
+    /// The admin handing over the role.
+    proposer: Address,
+    /// The address receiving the role.
+    address: Address,
+}
+
+/// A ReissueEvent introduced by this smart contract.
+#[derive(Serialize, SchemaType)]
+pub struct ReissueEvent {
+    /// The token that was superseded.
+    replaced: ContractTokenId,
+    /// The token that replaces it.
+    token_id: ContractTokenId,
+}
+
+/// A NonceEvent introduced by this smart contract.
+#[derive(Serialize, SchemaType)]
+pub struct NonceEvent {
+    /// Account that submitted the permit.
+    account: AccountAddress,
+    /// The nonce that was used by the permit.
+    nonce: u64,
+}
+
+/// A PlatformMetadataUrlEvent introduced by this smart contract. It is logged
+/// when the metadata URL of a platform is set or removed.
+#[derive(Serialize, SchemaType)]
+pub struct PlatformMetadataUrlEvent {
+    /// The platform whose metadata URL changed.
+    platform: Platform,
+    /// The new metadata URL of the platform, or `None` if tokens of the
+    /// platform use the global metadata URL again.
+    url: Option<String>,
+}
+
+/// An UpdateDataEvent introduced by this smart contract.
+#[derive(Serialize, SchemaType)]
+pub struct UpdateDataEvent {
+    /// The token whose data was replaced.
+    token_id: ContractTokenId,
+    /// The address that replaced the data.
+    address: Address,
+}
+
 /// Tagged event to be serialized for the event log.
 pub enum Event {
     GrantRole(GrantRoleEvent),
     RevokeRole(RevokeRoleEvent),
+    AddIssuerKey(IssuerKeyEvent),
+    RemoveIssuerKey(IssuerKeyEvent),
+    RevokeByIssuer(RevokeByIssuerEvent),
+    TokenMinted(TokenMintedEvent),
+    Pause(PauseEvent),
+    Unpause(PauseEvent),
+    ProposeAdmin(AdminHandoverEvent),
+    AcceptAdmin(AdminHandoverEvent),
+    Reissue(ReissueEvent),
+    Nonce(NonceEvent),
+    PlatformMetadataUrl(PlatformMetadataUrlEvent),
+    UpdateData(UpdateDataEvent),
     Cis2Event(Cis2Event<ContractTokenId, ContractTokenAmount>),
 }
 
@@ -473,6 +1356,54 @@ impl Serial for Event {
                 out.write_u8(REVOKE_ROLE_EVENT_TAG)?;
                 event.serial(out)
             }
+            Event::AddIssuerKey(event) => {
+                out.write_u8(ADD_ISSUER_KEY_EVENT_TAG)?;
+                event.serial(out)
+            }
+            Event::RemoveIssuerKey(event) => {
+                out.write_u8(REMOVE_ISSUER_KEY_EVENT_TAG)?;
+                event.serial(out)
+            }
+            Event::RevokeByIssuer(event) => {
+                out.write_u8(REVOKE_BY_ISSUER_EVENT_TAG)?;
+                event.serial(out)
+            }
+            Event::TokenMinted(event) => {
+                out.write_u8(TOKEN_MINTED_EVENT_TAG)?;
+                event.serial(out)
+            }
+            Event::Pause(event) => {
+                out.write_u8(PAUSE_EVENT_TAG)?;
+                event.serial(out)
+            }
+            Event::Unpause(event) => {
+                out.write_u8(UNPAUSE_EVENT_TAG)?;
+                event.serial(out)
+            }
+            Event::ProposeAdmin(event) => {
+                out.write_u8(PROPOSE_ADMIN_EVENT_TAG)?;
+                event.serial(out)
+            }
+            Event::AcceptAdmin(event) => {
+                out.write_u8(ACCEPT_ADMIN_EVENT_TAG)?;
+                event.serial(out)
+            }
+            Event::Reissue(event) => {
+                out.write_u8(REISSUE_EVENT_TAG)?;
+                event.serial(out)
+            }
+            Event::Nonce(event) => {
+                out.write_u8(NONCE_EVENT_TAG)?;
+                event.serial(out)
+            }
+            Event::PlatformMetadataUrl(event) => {
+                out.write_u8(PLATFORM_METADATA_URL_EVENT_TAG)?;
+                event.serial(out)
+            }
+            Event::UpdateData(event) => {
+                out.write_u8(UPDATE_DATA_EVENT_TAG)?;
+                event.serial(out)
+            }
             Event::Cis2Event(event) => event.serial(out),
         }
     }
@@ -505,41 +1436,151 @@ impl schema::SchemaType for Event {
             ),
         );
         event_map.insert(
-            TRANSFER_EVENT_TAG,
+            ADD_ISSUER_KEY_EVENT_TAG,
             (
-                "Transfer".to_string(),
-                schema::Fields::Named(vec![
-                    (String::from("token_id"), ContractTokenId::get_type()),
-                    (String::from("amount"), ContractTokenAmount::get_type()),
-                    (String::from("from"), Address::get_type()),
-                    (String::from("to"), Address::get_type()),
-                ]),
+                "AddIssuerKey".to_string(),
+                schema::Fields::Named(vec![(String::from("key"), PublicKeyEd25519::get_type())]),
             ),
         );
         event_map.insert(
-            MINT_EVENT_TAG,
+            REMOVE_ISSUER_KEY_EVENT_TAG,
             (
-                "Mint".to_string(),
+                "RemoveIssuerKey".to_string(),
+                schema::Fields::Named(vec![(String::from("key"), PublicKeyEd25519::get_type())]),
+            ),
+        );
+        event_map.insert(
+            REVOKE_BY_ISSUER_EVENT_TAG,
+            (
+                "RevokeByIssuer".to_string(),
                 schema::Fields::Named(vec![
                     (String::from("token_id"), ContractTokenId::get_type()),
-                    (String::from("amount"), ContractTokenAmount::get_type()),
-                    (String::from("owner"), Address::get_type()),
+                    (String::from("reason"), RevocationReason::get_type()),
                 ]),
             ),
         );
         event_map.insert(
-            BURN_EVENT_TAG,
+            TOKEN_MINTED_EVENT_TAG,
             (
-                "Burn".to_string(),
+                "TokenMinted".to_string(),
                 schema::Fields::Named(vec![
                     (String::from("token_id"), ContractTokenId::get_type()),
-                    (String::from("amount"), ContractTokenAmount::get_type()),
-                    (String::from("owner"), Address::get_type()),
+                    (String::from("owner"), AccountAddress::get_type()),
+                    (String::from("platform"), Platform::get_type()),
+                    (String::from("issued_at"), Timestamp::get_type()),
                 ]),
             ),
         );
         event_map.insert(
-            UPDATE_OPERATOR_EVENT_TAG,
+            PAUSE_EVENT_TAG,
+            (
+                "Pause".to_string(),
+                schema::Fields::Named(vec![(String::from("address"), Address::get_type())]),
+            ),
+        );
+        event_map.insert(
+            UNPAUSE_EVENT_TAG,
+            (
+                "Unpause".to_string(),
+                schema::Fields::Named(vec![(String::from("address"), Address::get_type())]),
+            ),
+        );
+        event_map.insert(
+            PROPOSE_ADMIN_EVENT_TAG,
+            (
+                "ProposeAdmin".to_string(),
+                schema::Fields::Named(vec![
+                    (String::from("proposer"), Address::get_type()),
+                    (String::from("address"), Address::get_type()),
+                ]),
+            ),
+        );
+        event_map.insert(
+            ACCEPT_ADMIN_EVENT_TAG,
+            (
+                "AcceptAdmin".to_string(),
+                schema::Fields::Named(vec![
+                    (String::from("proposer"), Address::get_type()),
+                    (String::from("address"), Address::get_type()),
+                ]),
+            ),
+        );
+        event_map.insert(
+            REISSUE_EVENT_TAG,
+            (
+                "Reissue".to_string(),
+                schema::Fields::Named(vec![
+                    (String::from("replaced"), ContractTokenId::get_type()),
+                    (String::from("token_id"), ContractTokenId::get_type()),
+                ]),
+            ),
+        );
+        event_map.insert(
+            PLATFORM_METADATA_URL_EVENT_TAG,
+            (
+                "PlatformMetadataUrl".to_string(),
+                schema::Fields::Named(vec![
+                    (String::from("platform"), Platform::get_type()),
+                    (String::from("url"), Option::<String>::get_type()),
+                ]),
+            ),
+        );
+        event_map.insert(
+            UPDATE_DATA_EVENT_TAG,
+            (
+                "UpdateData".to_string(),
+                schema::Fields::Named(vec![
+                    (String::from("token_id"), ContractTokenId::get_type()),
+                    (String::from("address"), Address::get_type()),
+                ]),
+            ),
+        );
+        event_map.insert(
+            NONCE_EVENT_TAG,
+            (
+                "Nonce".to_string(),
+                schema::Fields::Named(vec![
+                    (String::from("account"), AccountAddress::get_type()),
+                    (String::from("nonce"), u64::get_type()),
+                ]),
+            ),
+        );
+        event_map.insert(
+            TRANSFER_EVENT_TAG,
+            (
+                "Transfer".to_string(),
+                schema::Fields::Named(vec![
+                    (String::from("token_id"), ContractTokenId::get_type()),
+                    (String::from("amount"), ContractTokenAmount::get_type()),
+                    (String::from("from"), Address::get_type()),
+                    (String::from("to"), Address::get_type()),
+                ]),
+            ),
+        );
+        event_map.insert(
+            MINT_EVENT_TAG,
+            (
+                "Mint".to_string(),
+                schema::Fields::Named(vec![
+                    (String::from("token_id"), ContractTokenId::get_type()),
+                    (String::from("amount"), ContractTokenAmount::get_type()),
+                    (String::from("owner"), Address::get_type()),
+                ]),
+            ),
+        );
+        event_map.insert(
+            BURN_EVENT_TAG,
+            (
+                "Burn".to_string(),
+                schema::Fields::Named(vec![
+                    (String::from("token_id"), ContractTokenId::get_type()),
+                    (String::from("amount"), ContractTokenAmount::get_type()),
+                    (String::from("owner"), Address::get_type()),
+                ]),
+            ),
+        );
+        event_map.insert(
+            UPDATE_OPERATOR_EVENT_TAG,
             (
                 "UpdateOperator".to_string(),
                 schema::Fields::Named(vec![
@@ -613,8 +1654,24 @@ struct ViewData {
     /// Platform associated with the token.
     pub platform: Platform,
     /// The data, which includes the proof, challenge, name, social media URL (e.g linkedin URL).
+    /// This is empty if the token was burned by its owner or if the data is
+    /// stored off-chain.
     #[concordium(size_length = 2)]
     pub data: Vec<u8>,
+    /// The reason the token was revoked, if it was revoked by an issuer.
+    pub revocation_reason: Option<RevocationReason>,
+    /// Slot time of the block in which the token was minted.
+    pub issued_at: Timestamp,
+    /// Time until which the token is valid, if it expires.
+    pub valid_until: Option<Timestamp>,
+    /// Time at which the token was burned by its owner, if it was.
+    pub burned_at: Option<Timestamp>,
+    /// Commitment to the data if it is stored off-chain.
+    pub commitment: Option<DataCommitment>,
+    /// The token this token was reissued to replace, if any.
+    pub replaces: Option<ContractTokenId>,
+    /// The token this token was replaced by when it was reissued, if any.
+    pub replaced_by: Option<ContractTokenId>,
 }
 
 /// View the token data.
@@ -630,13 +1687,21 @@ fn contract_view_data<S: HasStateApi>(
     host: &impl HasHost<State<S>, StateApiType = S>,
 ) -> ContractResult<Option<ViewData>> {
     let param: ContractTokenId = ctx.parameter_cursor().get()?;
+    host.state().ensure_migrated()?;
     let token = host.state().all_tokens.get(&param);
     if let Some(token) = token {
         let data = ViewData {
             owner: token.owner,
             revoked: token.revoked,
             platform: token.platform,
-            data: token.data.clone(),
+            data: token.data(),
+            revocation_reason: token.revocation_reason,
+            issued_at: token.issued_at,
+            valid_until: token.valid_until,
+            burned_at: token.burned_at,
+            commitment: token.commitment.clone(),
+            replaces: token.replaces,
+            replaced_by: token.replaced_by,
         };
         Ok(Some(data))
     } else {
@@ -664,28 +1729,239 @@ fn contract_list_owned_tokens<S: HasStateApi>(
     host: &impl HasHost<State<S>, StateApiType = S>,
 ) -> ContractResult<ListTokens> {
     let param: AccountAddress = ctx.parameter_cursor().get()?;
-    let tokens = host.state().state.get(&param);
     let mut ret = Vec::new();
-    if let Some(tokens) = tokens {
-        for token in tokens.owned_tokens.iter() {
-            ret.push(*token);
+    for key in State::<S>::owner_keys(&param) {
+        if let Some(tokens) = host.state().state.get(&key) {
+            for token in tokens.owned_tokens.iter() {
+                ret.push(*token);
+            }
         }
     }
     Ok(ListTokens { tokens: ret })
 }
 
+/// The parameter type for the contract function `viewTokens`.
+#[derive(Serialize, SchemaType)]
+struct ViewTokensParams {
+    /// The first token ID of the page.
+    from: ContractTokenId,
+    /// The number of token IDs the page covers. Must not be zero.
+    limit: u16,
+    /// If set, only tokens associated with this platform are returned.
+    platform: Option<Platform>,
+    /// If set, only tokens with this revoked status are returned.
+    revoked: Option<bool>,
+}
+
+/// Summary of a token as returned by `viewTokens`. It does not include the
+/// data of the token.
+#[derive(Serial, SchemaType)]
+struct TokenSummary {
+    /// The ID of the token.
+    pub token_id: ContractTokenId,
+    /// Owner of the token.
+    pub owner: AccountAddress,
+    /// Whether the token is revoked or not.
+    pub revoked: bool,
+    /// Platform associated with the token.
+    pub platform: Platform,
+    /// The reason the token was revoked, if it was revoked by an issuer.
+    pub revocation_reason: Option<RevocationReason>,
+    /// Slot time of the block in which the token was minted.
+    pub issued_at: Timestamp,
+    /// Time until which the token is valid, if it expires.
+    pub valid_until: Option<Timestamp>,
+    /// Time at which the token was burned by its owner, if it was.
+    pub burned_at: Option<Timestamp>,
+}
+
+/// The return type for the contract function `viewTokens`.
+#[derive(Serial, SchemaType)]
+struct ViewTokens {
+    /// The tokens in the page that match the filters, in increasing order of
+    /// token ID.
+    #[concordium(size_length = 2)]
+    pub tokens: Vec<TokenSummary>,
+    /// The first token ID of the next page, if there are more tokens.
+    pub next: Option<ContractTokenId>,
+}
+
+/// View a page of all the tokens in the contract. The page covers the token
+/// IDs from `from` (inclusive) to `from + limit` (exclusive), and only the
+/// tokens matching the optional filters are returned. Use `next` of the
+/// response as `from` to get the next page.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The limit is zero.
+/// - The migration of the tokens to the current layout is not complete.
+#[receive(
+    contract = "mysomeid",
+    name = "viewTokens",
+    return_value = "ViewTokens",
+    parameter = "ViewTokensParams",
+    error = "ContractError"
+)]
+fn contract_view_tokens<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<ViewTokens> {
+    let params: ViewTokensParams = ctx.parameter_cursor().get()?;
+    ensure!(
+        params.limit > 0,
+        ContractError::Custom(CustomContractError::ZeroLimit)
+    );
+    let state = host.state();
+    state.ensure_migrated()?;
+    let end = params
+        .from
+        .0
+        .saturating_add(params.limit.into())
+        .min(state.next_token_id);
+    let mut tokens = Vec::new();
+    for id in params.from.0..end {
+        let token_id = TokenIdU64(id);
+        let token = match state.all_tokens.get(&token_id) {
+            Some(token) => token,
+            None => continue,
+        };
+        if params.platform.map_or(false, |p| p != token.platform)
+            || params.revoked.map_or(false, |r| r != token.revoked)
+        {
+            continue;
+        }
+        tokens.push(TokenSummary {
+            token_id,
+            owner: token.owner,
+            revoked: token.revoked,
+            platform: token.platform,
+            revocation_reason: token.revocation_reason,
+            issued_at: token.issued_at,
+            valid_until: token.valid_until,
+            burned_at: token.burned_at,
+        });
+    }
+    let next = if end < state.next_token_id {
+        Some(TokenIdU64(end))
+    } else {
+        None
+    };
+    Ok(ViewTokens { tokens, next })
+}
+
+/// The return type for the contract function `viewSummary`.
+#[derive(Serial, SchemaType)]
+struct ViewSummary {
+    /// The ID of the next token to be minted.
+    pub next_token_id: u64,
+    /// The number of active and revoked tokens of each platform.
+    pub platforms: Vec<(Platform, TokenCounts)>,
+    /// The number of accounts with active tokens.
+    pub owners: u64,
+    /// The metadata URL of the tokens, unless overridden for a platform.
+    pub metadata_url: MetadataUrl,
+    /// The number of addresses with at least one role.
+    pub role_holders: u32,
+}
+
+/// View a summary of the tokens in the contract for monitoring. The counts are
+/// maintained as tokens are minted, revoked and burned. Tokens of an older
+/// layout are only counted once they have been migrated.
+#[receive(
+    contract = "mysomeid",
+    name = "viewSummary",
+    return_value = "ViewSummary",
+    error = "ContractError"
+)]
+fn contract_view_summary<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<ViewSummary> {
+    let state = host.state();
+    let platforms = state
+        .platform_counts
+        .iter()
+        .map(|(platform, counts)| (*platform, *counts))
+        .collect();
+    let role_holders = state
+        .roles
+        .iter()
+        .filter(|(_, entry)| entry.roles.iter().next().is_some())
+        .count() as u32;
+    Ok(ViewSummary {
+        next_token_id: state.next_token_id,
+        platforms,
+        owners: state.owner_count,
+        metadata_url: MetadataUrl {
+            url: state.metadata_url.url.clone(),
+            hash: state.metadata_url.hash,
+        },
+        role_holders,
+    })
+}
+
+/// The parameter type for the contract function `isVerified`.
+#[derive(Serialize, SchemaType)]
+struct IsVerifiedQueryParams {
+    /// The account to check.
+    owner: AccountAddress,
+    /// If set, the token must be associated with this platform.
+    platform: Option<Platform>,
+    /// If set, only this token is considered.
+    token_id: Option<ContractTokenId>,
+}
+
+/// The response which is sent back when calling the contract function
+/// `isVerified`. It contains the ID of an active token of the owner, if there
+/// is one.
+#[derive(Serialize, SchemaType)]
+struct IsVerifiedQueryResponse(Option<ContractTokenId>);
+
+/// Check whether an account holds an active token, i.e., one that is neither
+/// revoked nor expired. This is meant to be called by other contracts that
+/// require their users to be verified, and does not return any token data.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - The migration of the tokens to the current layout is not complete.
+#[receive(
+    contract = "mysomeid",
+    name = "isVerified",
+    parameter = "IsVerifiedQueryParams",
+    return_value = "IsVerifiedQueryResponse",
+    error = "ContractError"
+)]
+fn contract_is_verified<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<IsVerifiedQueryResponse> {
+    let query: IsVerifiedQueryParams = ctx.parameter_cursor().get()?;
+    host.state().ensure_migrated()?;
+    let token_id = host.state().active_token(
+        &query.owner,
+        query.platform,
+        query.token_id,
+        ctx.metadata().slot_time(),
+    );
+    Ok(IsVerifiedQueryResponse(token_id))
+}
+
 /// Mint new tokens with a given address as the owner of these tokens.
 /// Can only be called by an address with Roles::Minter.
-/// Logs a `Mint` and a `TokenMetadata` event for each token.
+/// Logs a `Mint`, a `TokenMetadata` and a `TokenMinted` event for each token.
 /// The url for the token metadata is the metadata_url appended with the tokenId, platform and if it is revoked using the URL query string format.
 ///
 /// It rejects if:
 /// - Sender is not an address with Roles::Minter.
+/// - The contract is paused.
 /// - Fails to parse parameter.
+/// - The migration of the tokens to the current layout is not complete.
 /// - Any of the tokens fails to be minted, which could be if:
 ///     - The minted token ID already exists.
+///     - The end of the validity window is not after the current time.
 ///     - Fails to log Mint event
 ///     - Fails to log TokenMetadata event
+///     - Fails to log TokenMinted event
 #[receive(
     contract = "mysomeid",
     name = "mint",
@@ -707,12 +1983,89 @@ fn contract_mint<S: HasStateApi>(
         state.has_role(&sender, Roles::Minter),
         ContractError::Unauthorized
     );
+    state.ensure_not_paused()?;
+    state.ensure_migrated()?;
 
     // Parse the parameter.
     let params: MintParams = ctx.parameter_cursor().get()?;
 
+    mint_and_log(state, builder, params, ctx.metadata().slot_time(), logger)?;
+    Ok(())
+}
+
+/// The parameter type for the contract function `batchMint`.
+#[derive(Serialize, SchemaType)]
+struct BatchMintParams {
+    /// The tokens to mint.
+    #[concordium(size_length = 2)]
+    mints: Vec<MintParams>,
+}
+
+/// Mint a number of tokens in one transaction, each as if minted by `mint`.
+/// Can only be called by an address with Roles::Minter.
+/// Logs a `Mint`, a `TokenMetadata` and a `TokenMinted` event for each token,
+/// in the order of the parameter.
+/// Returns the IDs of the minted tokens in the order of the parameter.
+///
+/// It rejects if:
+/// - Sender is not an address with Roles::Minter.
+/// - The contract is paused.
+/// - Fails to parse parameter.
+/// - Any of the tokens fails to be minted, in which case none are minted.
+/// - The migration of the tokens to the current layout is not complete.
+#[receive(
+    contract = "mysomeid",
+    name = "batchMint",
+    parameter = "BatchMintParams",
+    return_value = "Vec<ContractTokenId>",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_batch_mint<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<Vec<ContractTokenId>> {
+    let (state, builder) = host.state_and_builder();
+    ensure!(
+        state.has_role(&ctx.sender(), Roles::Minter),
+        ContractError::Unauthorized
+    );
+    state.ensure_not_paused()?;
+    state.ensure_migrated()?;
+
+    // Parse the parameter.
+    let params: BatchMintParams = ctx.parameter_cursor().get()?;
+
+    let now = ctx.metadata().slot_time();
+    let mut token_ids = Vec::with_capacity(params.mints.len());
+    for mint in params.mints {
+        token_ids.push(mint_and_log(state, builder, mint, now, logger)?);
+    }
+    Ok(token_ids)
+}
+
+/// Mint the token in the state, issued at `now`, and log the `Mint`,
+/// `TokenMetadata` and `TokenMinted` events for it. Return the ID of the newly
+/// minted token.
+fn mint_and_log<S: HasStateApi>(
+    state: &mut State<S>,
+    builder: &mut StateBuilder<S>,
+    params: MintParams,
+    now: Timestamp,
+    logger: &mut impl HasLogger,
+) -> ContractResult<ContractTokenId> {
     // Mint the token in the state.
-    let token = state.mint(params.owner, params.platform, params.data, builder)?;
+    let token = state.mint(
+        params.owner,
+        params.platform,
+        params.data,
+        params.commitment,
+        now,
+        params.valid_until,
+        builder,
+    )?;
 
     // Event for minted NFT.
     logger.log(&Cis2Event::Mint(MintEvent {
@@ -726,692 +2079,3003 @@ fn contract_mint<S: HasStateApi>(
         TokenMetadataEvent {
             token_id: token,
             metadata_url: MetadataUrl {
-                url: host
-                    .state()
-                    .build_token_metadata_url(&token, params.platform, false),
+                url: state.build_token_metadata_url(
+                    &token,
+                    params.platform,
+                    false,
+                    params.valid_until,
+                ),
+                // The metadata document depends on the ID of the token, so its
+                // hash is only recorded by `updateTokenMetadata`.
                 hash: None,
             },
         },
     ))?;
-    Ok(())
+
+    // Event carrying the platform of the NFT.
+    logger.log(&Event::TokenMinted(TokenMintedEvent {
+        token_id: token,
+        owner: params.owner,
+        platform: params.platform,
+        issued_at: now,
+    }))?;
+    Ok(token)
 }
 
-/// Burns/Revokes a token.
-/// Can only be called by the owner of the tokenId.
-/// Logs a `Burn` event.
+/// Mint a token for the sender, authorized by a permit signed by a registered
+/// issuer key. This allows the owner of the token to submit, and pay for, the
+/// mint transaction themselves.
+/// Logs a `Nonce`, a `Mint`, a `TokenMetadata` and a `TokenMinted` event.
 ///
 /// It rejects if:
-/// - Sender is not in possession of the tokenId.
 /// - Fails to parse parameter.
-/// - Fails to log Burn event
+/// - The contract is paused.
+/// - The signer is not a registered issuer key.
+/// - The permit is intended for a different contract.
+/// - Sender is not the owner of the token to be minted.
+/// - The permit has expired.
+/// - The nonce does not match the next nonce of the sender.
+/// - The signature is invalid.
+/// - Fails to log any of the events.
+/// - The migration of the tokens to the current layout is not complete.
 #[receive(
     contract = "mysomeid",
-    name = "burn",
-    parameter = "ContractTokenId",
+    name = "permit",
+    parameter = "PermitParams",
     error = "ContractError",
+    crypto_primitives,
     enable_logger,
     mutable
 )]
-fn contract_burn<S: HasStateApi>(
+fn contract_permit<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
     host: &mut impl HasHost<State<S>, StateApiType = S>,
     logger: &mut impl HasLogger,
+    crypto_primitives: &impl HasCryptoPrimitives,
 ) -> ContractResult<()> {
-    let sender = ctx.sender();
-
-    ensure!(sender.is_account(), ContractError::Unauthorized);
-
     // Parse the parameter.
-    let token_id: ContractTokenId = ctx.parameter_cursor().get()?;
+    let params: PermitParams = ctx.parameter_cursor().get()?;
+    let message = params.message;
 
-    let state = host.state_mut();
+    let (state, builder) = host.state_and_builder();
+    state.ensure_not_paused()?;
+    state.ensure_migrated()?;
+    ensure!(
+        state.issuer_keys.contains(&params.signer),
+        ContractError::Custom(CustomContractError::UnknownIssuerKey)
+    );
+    ensure_eq!(
+        message.contract_address,
+        ctx.self_address(),
+        ContractError::Custom(CustomContractError::WrongContract)
+    );
+    ensure!(
+        ctx.sender().matches_account(&message.mint.owner),
+        ContractError::Unauthorized
+    );
+    ensure!(
+        message.timestamp > ctx.metadata().slot_time(),
+        ContractError::Custom(CustomContractError::Expired)
+    );
 
-    state.burn(&token_id, &sender)?;
+    state.use_nonce(message.mint.owner, message.nonce)?;
 
-    // Event for minted NFT.
-    logger.log(&Cis2Event::Burn(BurnEvent {
-        token_id,
-        amount: ContractTokenAmount::from(1),
-        owner: sender,
+    // Check the signature on the hash of the message.
+    let message_hash = crypto_primitives.hash_sha2_256(&to_bytes(&message));
+    ensure!(
+        crypto_primitives.verify_ed25519_signature(
+            params.signer,
+            params.signature,
+            &message_hash.0
+        ),
+        ContractError::Custom(CustomContractError::WrongSignature)
+    );
+
+    logger.log(&Event::Nonce(NonceEvent {
+        account: message.mint.owner,
+        nonce: message.nonce,
     }))?;
 
+    mint_and_log(
+        state,
+        builder,
+        message.mint,
+        ctx.metadata().slot_time(),
+        logger,
+    )?;
     Ok(())
 }
 
-#[allow(dead_code)]
-type TransferParameter = TransferParams<ContractTokenId, ContractTokenAmount>;
-
-/// The `transfer` function of the CIS-2 standard.
-/// This function always rejects.
+/// Get the nonce expected in the next permit of each of the given accounts.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
 #[receive(
     contract = "mysomeid",
-    name = "transfer",
-    parameter = "TransferParameter",
+    name = "nonceOf",
+    parameter = "NonceOfQueryParams",
+    return_value = "NonceOfQueryResponse",
     error = "ContractError"
 )]
-fn contract_transfer<S: HasStateApi>(
-    _ctx: &impl HasReceiveContext,
-    _host: &impl HasHost<State<S>, StateApiType = S>,
-) -> ContractResult<()> {
-    Err(ContractError::Unauthorized)
+fn contract_nonce_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<NonceOfQueryResponse> {
+    // Parse the parameter.
+    let params: NonceOfQueryParams = ctx.parameter_cursor().get()?;
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for account in params.queries {
+        let nonce = host
+            .state()
+            .nonces
+            .get(&canonical_address(&account))
+            .map_or(0, |n| *n);
+        response.push(nonce);
+    }
+    Ok(NonceOfQueryResponse(response))
 }
 
-/// The `updateOperator` function of the CIS-2 standard.
-/// This function always rejects.
+/// Register a public key of an issuer whose permits are accepted by `permit`.
+/// Logs an `AddIssuerKey` event.
+///
+/// It rejects if:
+/// - Sender is not an address with Roles::Admin.
+/// - It fails to parse the parameter.
+/// - Fails to log AddIssuerKey event.
 #[receive(
     contract = "mysomeid",
-    name = "updateOperator",
-    parameter = "UpdateOperatorParams",
-    error = "ContractError"
-)]
-fn contract_update_operator<S: HasStateApi>(
-    _ctx: &impl HasReceiveContext,
-    _host: &impl HasHost<State<S>, StateApiType = S>,
-) -> ContractResult<()> {
-    Err(ContractError::Unauthorized)
-}
-
-/// Takes a list of queries. Each query is an owner address and some address to
-/// check as an operator of the owner address. It returns `false` for every query.
-///
-/// It rejects if:
-/// - It fails to parse the parameter.
-#[receive(
-    contract = "mysomeid",
-    name = "operatorOf",
-    parameter = "OperatorOfQueryParams",
-    return_value = "OperatorOfQueryResponse",
-    error = "ContractError"
+    name = "addIssuerKey",
+    parameter = "PublicKeyEd25519",
+    error = "ContractError",
+    enable_logger,
+    mutable
 )]
-fn contract_operator_of<S: HasStateApi>(
+fn contract_add_issuer_key<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
-    _host: &impl HasHost<State<S>, StateApiType = S>,
-) -> ContractResult<OperatorOfQueryResponse> {
-    // Parse the parameter.
-    let params: OperatorOfQueryParams = ctx.parameter_cursor().get()?;
-    // Build the response.
-    let response = vec![false; params.queries.len()];
-    let result = OperatorOfQueryResponse::from(response);
-    Ok(result)
-}
-
-/// Parameter type for the CIS-2 function `balanceOf` specialized to the subset
-/// of TokenIDs used by this contract.
-type ContractBalanceOfQueryParams = BalanceOfQueryParams<ContractTokenId>;
-/// Response type for the CIS-2 function `balanceOf` specialized to the subset
-/// of TokenAmounts used by this contract.
-type ContractBalanceOfQueryResponse = BalanceOfQueryResponse<ContractTokenAmount>;
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure!(
+        host.state().has_role(&ctx.sender(), Roles::Admin),
+        ContractError::Unauthorized
+    );
 
-/// Get the balance of given token IDs and addresses.
-///
-/// It rejects if:
-/// - It fails to parse the parameter.
-/// - Any of the queried `token_id` does not exist.
-#[receive(
-    contract = "mysomeid",
-    name = "balanceOf",
-    parameter = "ContractBalanceOfQueryParams",
-    return_value = "ContractBalanceOfQueryResponse",
-    error = "ContractError"
-)]
-fn contract_balance_of<S: HasStateApi>(
-    ctx: &impl HasReceiveContext,
-    host: &impl HasHost<State<S>, StateApiType = S>,
-) -> ContractResult<ContractBalanceOfQueryResponse> {
     // Parse the parameter.
-    let params: ContractBalanceOfQueryParams = ctx.parameter_cursor().get()?;
-    // Build the response.
-    let mut response = Vec::with_capacity(params.queries.len());
-    for query in params.queries {
-        // Query the state for balance.
-        let amount = host.state().balance(&query.token_id, &query.address)?;
-        response.push(amount);
-    }
-    let result = ContractBalanceOfQueryResponse::from(response);
-    Ok(result)
-}
-
-/// Parameter type for the CIS-2 function `tokenMetadata` specialized to the
-/// subset of TokenIDs used by this contract.
-type ContractTokenMetadataQueryParams = TokenMetadataQueryParams<ContractTokenId>;
+    let key: PublicKeyEd25519 = ctx.parameter_cursor().get()?;
 
-/// Get the token metadata URLs and checksums given a list of token IDs.
-///
-/// It rejects if:
-/// - It fails to parse the parameter.
-/// - Any of the queried `token_id` does not exist.
-#[receive(
-    contract = "mysomeid",
-    name = "tokenMetadata",
-    parameter = "ContractTokenMetadataQueryParams",
-    return_value = "TokenMetadataQueryResponse",
-    error = "ContractError"
-)]
-fn contract_token_metadata<S: HasStateApi>(
-    ctx: &impl HasReceiveContext,
-    host: &impl HasHost<State<S>, StateApiType = S>,
-) -> ContractResult<TokenMetadataQueryResponse> {
-    // Parse the parameter.
-    let params: ContractTokenMetadataQueryParams = ctx.parameter_cursor().get()?;
-    // Build the response.
-    let mut response = Vec::with_capacity(params.queries.len());
-    for token_id in params.queries {
-        // Check the token exists.
-        if let Some(v) = host.state().all_tokens.get(&token_id) {
-            let metadata_url = MetadataUrl {
-                url: host
-                    .state()
-                    .build_token_metadata_url(&token_id, v.platform, v.revoked),
-                hash: None,
-            };
-            response.push(metadata_url);
-        } else {
-            return Err(ContractError::InvalidTokenId);
-        };
-    }
-    let result = TokenMetadataQueryResponse::from(response);
-    Ok(result)
-}
+    host.state_mut().issuer_keys.insert(key);
 
-/// Get the supported standards or addresses for a implementation given list of
-/// standard identifiers.
-///
-/// It rejects if:
-/// - It fails to parse the parameter.
-#[receive(
-    contract = "mysomeid",
-    name = "supports",
-    parameter = "SupportsQueryParams",
-    return_value = "SupportsQueryResponse",
-    error = "ContractError"
-)]
-fn contract_supports<S: HasStateApi>(
-    ctx: &impl HasReceiveContext,
-    host: &impl HasHost<State<S>, StateApiType = S>,
-) -> ContractResult<SupportsQueryResponse> {
-    // Parse the parameter.
-    let params: SupportsQueryParams = ctx.parameter_cursor().get()?;
+    logger.log(&Event::AddIssuerKey(IssuerKeyEvent { key }))?;
 
-    // Build the response.
-    let mut response = Vec::with_capacity(params.queries.len());
-    for std_id in params.queries {
-        if SUPPORTS_STANDARDS.contains(&std_id.as_standard_identifier()) {
-            response.push(SupportResult::Support);
-        } else {
-            response.push(host.state().have_implementors(&std_id));
-        }
-    }
-    let result = SupportsQueryResponse::from(response);
-    Ok(result)
+    Ok(())
 }
 
-/// Set the addresses for an implementation given a standard identifier and a
-/// list of contract addresses.
+/// Remove a registered issuer key. Permits signed by the key are no longer
+/// accepted.
+/// Logs a `RemoveIssuerKey` event.
 ///
 /// It rejects if:
-/// - Sender is not the owner of the contract instance.
+/// - Sender is not an address with Roles::Admin.
 /// - It fails to parse the parameter.
+/// - The key is not registered.
+/// - Fails to log RemoveIssuerKey event.
 #[receive(
     contract = "mysomeid",
-    name = "setImplementors",
-    parameter = "SetImplementorsParams",
+    name = "removeIssuerKey",
+    parameter = "PublicKeyEd25519",
     error = "ContractError",
+    enable_logger,
     mutable
 )]
-fn contract_set_implementor<S: HasStateApi>(
+fn contract_remove_issuer_key<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
     host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
 ) -> ContractResult<()> {
-    // Authorize the sender.
     ensure!(
-        ctx.sender().matches_account(&ctx.owner()),
+        host.state().has_role(&ctx.sender(), Roles::Admin),
         ContractError::Unauthorized
     );
+
     // Parse the parameter.
-    let params: SetImplementorsParams = ctx.parameter_cursor().get()?;
-    // Update the implementors in the state
-    host.state_mut()
-        .set_implementors(params.id, params.implementors);
+    let key: PublicKeyEd25519 = ctx.parameter_cursor().get()?;
+
+    ensure!(
+        host.state_mut().issuer_keys.remove(&key),
+        ContractError::Custom(CustomContractError::UnknownIssuerKey)
+    );
+
+    logger.log(&Event::RemoveIssuerKey(IssuerKeyEvent { key }))?;
+
     Ok(())
 }
 
-/// Update the metadata URL in this smart contract instance.
+/// Pause the contract. While paused no tokens can be minted.
+/// Logs a `Pause` event.
 ///
 /// It rejects if:
-/// - Sender is not an address with Roles::Setter.
-/// - It fails to parse the parameter.
+/// - Sender is not an address with Roles::Pauser.
+/// - Fails to log Pause event.
 #[receive(
     contract = "mysomeid",
-    name = "setMetadataUrl",
-    parameter = "SetMetadataUrlParams",
+    name = "pause",
     error = "ContractError",
+    enable_logger,
     mutable
 )]
-fn contract_state_set_metadata_url<S: HasStateApi>(
+fn contract_pause<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
     host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
 ) -> ContractResult<()> {
-    // Get the sender of the transaction
     let sender = ctx.sender();
-
-    // Check that only an address with Roles::Setter is authorized to update the URL.
     ensure!(
-        host.state().has_role(&sender, Roles::Setter),
+        host.state().has_role(&sender, Roles::Pauser),
         ContractError::Unauthorized
     );
 
-    // Parse the parameter.
-    let params: SetMetadataUrlParams = ctx.parameter_cursor().get()?;
-
-    // Create the metadata_url
-    let metadata_url = MetadataUrl {
-        url: params.url,
-        hash: None,
-    };
+    host.state_mut().paused = true;
 
-    // Update the hash variable.
-    *host.state_mut().metadata_url = metadata_url;
+    logger.log(&Event::Pause(PauseEvent { address: sender }))?;
 
     Ok(())
 }
 
-/// Upgrade this smart contract instance to a new module and call optionally a
-/// migration function after the upgrade.
+/// Unpause the contract.
+/// Logs an `Unpause` event.
 ///
 /// It rejects if:
-/// - Sender is not an address with Roles::Upgrader.
-/// - It fails to parse the parameter.
-/// - If the ugrade fails.
-/// - If the migration invoke fails.
-///
-/// This function is marked as `low_level`. This is **necessary** since the
-/// high-level mutable functions store the state of the contract at the end of
-/// execution. This conflicts with migration since the shape of the state
-/// **might** be changed by the migration function. If the state is then written
-/// by this function it would overwrite the state stored by the migration
-/// function.
+/// - Sender is not an address with Roles::Pauser.
+/// - Fails to log Unpause event.
 #[receive(
     contract = "mysomeid",
-    name = "upgrade",
-    parameter = "UpgradeParams",
+    name = "unpause",
     error = "ContractError",
-    low_level
+    enable_logger,
+    mutable
 )]
-fn contract_upgrade<S: HasStateApi>(
+fn contract_unpause<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
-    host: &mut impl HasHost<S>,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
 ) -> ContractResult<()> {
-    // Get the sender of the transaction
     let sender = ctx.sender();
-
-    // Read the top-level contract state.
-    let state: State<S> = host.state().read_root()?;
-
-    // Check that only and address with Roles::Upgrader is authorized to upgrade the smart contract.
     ensure!(
-        state.has_role(&sender, Roles::Upgrader),
+        host.state().has_role(&sender, Roles::Pauser),
         ContractError::Unauthorized
     );
-    // Parse the parameter.
-    let params: UpgradeParams = ctx.parameter_cursor().get()?;
-    // Trigger the upgrade.
-    host.upgrade(params.module)?;
-    // Call the migration function if provided.
-    if let Some((func, parameters)) = params.migrate {
-        host.invoke_contract_raw(
-            &ctx.self_address(),
-            parameters.as_parameter(),
-            func.as_entrypoint_name(),
-            Amount::zero(),
-        )?;
-    }
+
+    host.state_mut().paused = false;
+
+    logger.log(&Event::Unpause(PauseEvent { address: sender }))?;
+
     Ok(())
 }
 
-/// Check if an address has a role.
-///
-/// It rejects if:
-/// - It fails to parse the parameter.
+/// Check whether the contract is paused.
 #[receive(
     contract = "mysomeid",
-    name = "hasRole",
-    parameter = "HasRoleQueryParamaters",
-    return_value = "HasRoleQueryResponse"
+    name = "isPaused",
+    return_value = "bool",
+    error = "ContractError"
 )]
-fn contract_has_role<S: HasStateApi>(
-    ctx: &impl HasReceiveContext,
+fn contract_is_paused<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
     host: &impl HasHost<State<S>, StateApiType = S>,
-) -> ContractResult<HasRoleQueryResponse> {
-    let query: HasRoleQueryParamaters = ctx.parameter_cursor().get()?;
-    let has_role = host.state().has_role(&query.address, query.role);
-    Ok(HasRoleQueryResponse::from(has_role))
+) -> ContractResult<bool> {
+    Ok(host.state().paused)
 }
 
-/// Grant Permission to an address
+/// Burns/Revokes a token.
+/// Can only be called by the owner of the tokenId.
+/// The data of the token is erased, only the owner, platform, revoked status
+/// and the time it was burned are kept.
+/// Logs a `Burn` event.
 ///
 /// It rejects if:
-/// - Sender is not an address with Roles::Admin.
-/// - It fails to parse the parameter.
+/// - Sender is not in possession of the tokenId.
+/// - Fails to parse parameter.
+/// - Fails to log Burn event
+/// - The migration of the tokens to the current layout is not complete.
 #[receive(
     contract = "mysomeid",
-    name = "grantRole",
-    parameter = "GrantRoleParams",
+    name = "burn",
+    parameter = "ContractTokenId",
+    error = "ContractError",
     enable_logger,
     mutable
 )]
-fn contract_grant_role<S: HasStateApi>(
+fn contract_burn<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
     host: &mut impl HasHost<State<S>, StateApiType = S>,
     logger: &mut impl HasLogger,
 ) -> ContractResult<()> {
-    // Get the sender who invoked this contract function.
     let sender = ctx.sender();
 
-    let (state, state_builder) = host.state_and_builder();
+    ensure!(sender.is_account(), ContractError::Unauthorized);
+
+    // Parse the parameter.
+    let token_id: ContractTokenId = ctx.parameter_cursor().get()?;
+
+    let (state, builder) = host.state_and_builder();
+    state.ensure_migrated()?;
+
+    state.burn(&token_id, &sender, ctx.metadata().slot_time(), builder)?;
+
+    // Event for minted NFT.
+    logger.log(&Cis2Event::Burn(BurnEvent {
+        token_id,
+        amount: ContractTokenAmount::from(1),
+        owner: sender,
+    }))?;
+
+    Ok(())
+}
+
+/// Revokes any token and records the reason for revoking it.
+/// Can only be called by an address with Roles::Revoker.
+/// Logs a `Burn` and a `RevokeByIssuer` event.
+///
+/// It rejects if:
+/// - Sender is not an address with Roles::Revoker.
+/// - Fails to parse parameter.
+/// - The token does not exist or is already revoked.
+/// - Fails to log Burn event
+/// - Fails to log RevokeByIssuer event
+/// - The migration of the tokens to the current layout is not complete.
+#[receive(
+    contract = "mysomeid",
+    name = "revokeByIssuer",
+    parameter = "RevokeByIssuerParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_revoke_by_issuer<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
     ensure!(
-        state.has_role(&sender, Roles::Admin),
+        host.state().has_role(&ctx.sender(), Roles::Revoker),
         ContractError::Unauthorized
     );
 
     // Parse the parameter.
-    let params: GrantRoleParams = ctx.parameter_cursor().get()?;
+    let params: RevokeByIssuerParams = ctx.parameter_cursor().get()?;
+    host.state().ensure_migrated()?;
 
-    state.grant_role(&params.address, params.role, state_builder);
+    let owner = host.state_mut().revoke(&params.token_id, params.reason)?;
 
-    logger.log(&Event::GrantRole(GrantRoleEvent {
-        address: params.address,
-        role: params.role,
+    // Event for the owner losing the NFT.
+    logger.log(&Cis2Event::Burn(BurnEvent {
+        token_id: params.token_id,
+        amount: ContractTokenAmount::from(1),
+        owner: owner.into(),
+    }))?;
+
+    logger.log(&Event::RevokeByIssuer(RevokeByIssuerEvent {
+        token_id: params.token_id,
+        reason: params.reason,
     }))?;
 
     Ok(())
 }
 
-/// Revoke Permission to an address
+/// The parameter type for the contract function `reissue`.
+#[derive(Serialize, SchemaType)]
+struct ReissueParams {
+    /// The token to supersede.
+    token_id: ContractTokenId,
+    /// The token to mint in its place.
+    mint: MintParams,
+}
+
+/// Revoke a token and mint a new one replacing it in the same transaction,
+/// e.g., when the name or profile of the owner has changed. The old token is
+/// revoked with the reason `Superseded` and records the token replacing it, and
+/// the new token records the token it replaces.
+/// Can only be called by an address with Roles::Minter.
+/// Logs a `Burn` and a `RevokeByIssuer` event for the old token, a `Mint`, a
+/// `TokenMetadata` and a `TokenMinted` event for the new token, and a
+/// `Reissue` event.
 ///
 /// It rejects if:
-/// - Sender is not an address with Roles::Admin.
-/// - It fails to parse the parameter.
-/// - The address does not have the role.
+/// - Sender is not an address with Roles::Minter.
+/// - The contract is paused.
+/// - Fails to parse parameter.
+/// - The old token does not exist or is already revoked.
+/// - The owner of the new token is not the owner of the old token.
+/// - The new token fails to be minted.
+/// - Fails to log any of the events.
+/// - The migration of the tokens to the current layout is not complete.
 #[receive(
     contract = "mysomeid",
-    name = "revokeRole",
-    parameter = "RevokeRoleParams",
+    name = "reissue",
+    parameter = "ReissueParams",
+    return_value = "ContractTokenId",
+    error = "ContractError",
     enable_logger,
     mutable
 )]
-fn contract_revoke_role<S: HasStateApi>(
+fn contract_reissue<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
     host: &mut impl HasHost<State<S>, StateApiType = S>,
     logger: &mut impl HasLogger,
-) -> ContractResult<()> {
-    // Get the sender who invoked this contract function.
-    let sender = ctx.sender();
-
-    let (state, _) = host.state_and_builder();
+) -> ContractResult<ContractTokenId> {
+    let (state, builder) = host.state_and_builder();
     ensure!(
-        state.has_role(&sender, Roles::Admin),
+        state.has_role(&ctx.sender(), Roles::Minter),
         ContractError::Unauthorized
     );
+    state.ensure_not_paused()?;
+    state.ensure_migrated()?;
 
     // Parse the parameter.
-    let params: RevokeRoleParams = ctx.parameter_cursor().get()?;
+    let params: ReissueParams = ctx.parameter_cursor().get()?;
 
+    let owner = state
+        .all_tokens
+        .get(&params.token_id)
+        .map(|token| token.owner)
+        .ok_or(ContractError::InvalidTokenId)?;
     ensure!(
-        state.has_role(&params.address, params.role),
-        ContractError::Custom(CustomContractError::RoleNotAssigned)
+        canonical_address(&owner) == canonical_address(&params.mint.owner),
+        ContractError::Custom(CustomContractError::WrongOwner)
     );
+    state.revoke(&params.token_id, RevocationReason::Superseded)?;
 
-    state.revoke_role(&params.address, params.role);
-
-    logger.log(&Event::RevokeRole(RevokeRoleEvent {
-        address: params.address,
-        role: params.role,
+    // Event for the owner losing the old NFT.
+    logger.log(&Cis2Event::Burn(BurnEvent {
+        token_id: params.token_id,
+        amount: ContractTokenAmount::from(1),
+        owner: owner.into(),
+    }))?;
+    logger.log(&Event::RevokeByIssuer(RevokeByIssuerEvent {
+        token_id: params.token_id,
+        reason: RevocationReason::Superseded,
     }))?;
 
-    Ok(())
-}
+    let token_id = mint_and_log(
+        state,
+        builder,
+        params.mint,
+        ctx.metadata().slot_time(),
+        logger,
+    )?;
+    if let Some(mut token) = state.all_tokens.get_mut(&token_id) {
+        token.replaces = Some(params.token_id);
+    }
+    if let Some(mut token) = state.all_tokens.get_mut(&params.token_id) {
+        token.replaced_by = Some(token_id);
+    }
 
-/// Part of the return parameter of the `viewRoles` function.
-#[derive(Serialize, SchemaType, PartialEq)]
-struct ViewRolesState {
-    /// Vector of roles.
-    roles: Vec<Roles>,
+    logger.log(&Event::Reissue(ReissueEvent {
+        replaced: params.token_id,
+        token_id,
+    }))?;
+
+    Ok(token_id)
 }
 
-/// Return parameter of the `viewRoles` function.
+/// The parameter type for the contract function `recover`.
 #[derive(Serialize, SchemaType)]
-struct ViewAllRolesState {
-    /// Vector specifiying for each address a vector of its associated roles.
-    all_roles: Vec<(Address, ViewRolesState)>,
+struct RecoverParams {
+    /// The token to recover.
+    token_id: ContractTokenId,
+    /// The new owner of the token.
+    owner: AccountAddress,
+    /// The data of the token, which includes a proof of the new owner.
+    #[concordium(size_length = 2)]
+    data: Vec<u8>,
+    /// Commitment to the data if it is stored off-chain. In that case `data`
+    /// must be empty.
+    commitment: Option<DataCommitment>,
 }
 
-/// View function that returns the entire `roles` content of the state. Meant
-/// for monitoring.
+/// Move a token to a new account of the same identity, e.g., when the owner
+/// lost access to their account. The data of the token is replaced by a proof
+/// of the new owner. Tokens are otherwise not transferable.
+/// Can only be called by an address with Roles::Recoverer.
+/// Logs a `Burn` event for the previous owner, and a `Mint` and a
+/// `TokenMinted` event for the new owner.
+///
+/// It rejects if:
+/// - Sender is not an address with Roles::Recoverer.
+/// - The contract is paused.
+/// - Fails to parse parameter.
+/// - Both data and a commitment are given.
+/// - The token does not exist or is revoked.
+/// - The token is already owned by the new owner.
+/// - Fails to log Burn event
+/// - Fails to log Mint event
+/// - Fails to log TokenMinted event
+/// - The migration of the tokens to the current layout is not complete.
 #[receive(
     contract = "mysomeid",
-    name = "viewRoles",
-    return_value = "ViewAllRolesState"
+    name = "recover",
+    parameter = "RecoverParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
 )]
-fn contract_view_roles<S: HasStateApi>(
-    _ctx: &impl HasReceiveContext,
-    host: &impl HasHost<State<S>, StateApiType = S>,
-) -> ReceiveResult<ViewAllRolesState> {
-    let state = host.state();
+fn contract_recover<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let (state, builder) = host.state_and_builder();
+    ensure!(
+        state.has_role(&ctx.sender(), Roles::Recoverer),
+        ContractError::Unauthorized
+    );
+    state.ensure_not_paused()?;
+    state.ensure_migrated()?;
 
-    let mut all_roles = Vec::new();
-    for (address, a_state) in state.roles.iter() {
-        let roles: Vec<Roles> = a_state.roles.iter().map(|x| *x).collect();
+    // Parse the parameter.
+    let params: RecoverParams = ctx.parameter_cursor().get()?;
+
+    let owner = state.recover(
+        &params.token_id,
+        params.owner,
+        params.data,
+        params.commitment,
+        builder,
+    )?;
+    let (platform, issued_at) = state
+        .all_tokens
+        .get(&params.token_id)
+        .map(|token| (token.platform, token.issued_at))
+        .ok_or(ContractError::InvalidTokenId)?;
+
+    // Event for the previous owner losing the NFT.
+    logger.log(&Cis2Event::Burn(BurnEvent {
+        token_id: params.token_id,
+        amount: ContractTokenAmount::from(1),
+        owner: owner.into(),
+    }))?;
 
-        all_roles.push((*address, ViewRolesState { roles }));
-    }
+    // Event for the new owner receiving the NFT.
+    logger.log(&Cis2Event::Mint(MintEvent {
+        token_id: params.token_id,
+        amount: ContractTokenAmount::from(1),
+        owner: params.owner.into(),
+    }))?;
 
-    Ok(ViewAllRolesState { all_roles })
+    // Event carrying the platform of the NFT for the new owner.
+    logger.log(&Event::TokenMinted(TokenMintedEvent {
+        token_id: params.token_id,
+        owner: params.owner,
+        platform,
+        issued_at,
+    }))?;
+
+    Ok(())
 }
 
-// Tests
+/// The parameter type for the contract function `updateData`.
+#[derive(Serialize, SchemaType)]
+struct UpdateDataParams {
+    /// The token to update.
+    token_id: ContractTokenId,
+    /// The new data of the token.
+    #[concordium(size_length = 2)]
+    data: Vec<u8>,
+    /// Commitment to the data if it is stored off-chain. In that case `data`
+    /// must be empty.
+    commitment: Option<DataCommitment>,
+}
 
-#[concordium_cfg_test]
-mod tests {
-    use super::*;
-    use test_infrastructure::*;
+/// Replace the data of a token, e.g., to encrypt it with a new key when the
+/// previous key has leaked. The token keeps its id, owner and history.
+/// Can only be called by the owner of the token, or by an address with
+/// Roles::Minter on behalf of the owner.
+/// Logs an `UpdateData` event.
+///
+/// It rejects if:
+/// - The contract is paused.
+/// - Fails to parse parameter.
+/// - The token does not exist or is revoked.
+/// - Sender is neither the owner of the token nor an address with
+///   Roles::Minter.
+/// - Both data and a commitment are given.
+/// - Fails to log UpdateData event
+/// - The migration of the tokens to the current layout is not complete.
+#[receive(
+    contract = "mysomeid",
+    name = "updateData",
+    parameter = "UpdateDataParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_update_data<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let (state, builder) = host.state_and_builder();
+    state.ensure_not_paused()?;
+    state.ensure_migrated()?;
 
-    const ACCOUNT_0: AccountAddress = AccountAddress([0u8; 32]);
-    const ADDRESS_0: Address = Address::Account(ACCOUNT_0);
-    const ACCOUNT_1: AccountAddress = AccountAddress([1u8; 32]);
-    const ADDRESS_1: Address = Address::Account(ACCOUNT_1);
-    const ADMIN_ACCOUNT: AccountAddress = AccountAddress([2u8; 32]);
-    const ADMIN_ADDRESS: Address = Address::Account(ADMIN_ACCOUNT);
-    const SETTER_ACCOUNT: AccountAddress = AccountAddress([3u8; 32]);
-    const SETTER_ADDRESS: Address = Address::Account(SETTER_ACCOUNT);
-    const MINTER_ACCOUNT: AccountAddress = AccountAddress([4u8; 32]);
-    const MINTER_ADDRESS: Address = Address::Account(MINTER_ACCOUNT);
-    const TOKEN_0: ContractTokenId = TokenIdU64(0);
+    // Parse the parameter.
+    let params: UpdateDataParams = ctx.parameter_cursor().get()?;
 
-    const PLATFORM: Platform = Platform([0x01, 0x01]);
+    let owner = state
+        .all_tokens
+        .get(&params.token_id)
+        .map(|token| token.owner)
+        .ok_or(ContractError::InvalidTokenId)?;
+    let sender = ctx.sender();
+    let is_owner = match sender {
+        Address::Account(account) => canonical_address(&account) == canonical_address(&owner),
+        Address::Contract(_) => false,
+    };
+    ensure!(
+        is_owner || state.has_role(&sender, Roles::Minter),
+        ContractError::Unauthorized
+    );
 
-    // The metadata url for this nft contract.
-    const TOKEN_METADATA_BASE_URL: &str = "https://api.mysomeid.dev/v1/proof/meta/";
+    state.update_data(&params.token_id, params.data, params.commitment, builder)?;
 
-    fn initial_state<S: HasStateApi>(state_builder: &mut StateBuilder<S>) -> State<S> {
-        let metadata_url = MetadataUrl {
-            url: TOKEN_METADATA_BASE_URL.to_string(),
-            hash: None,
-        };
-        let mut state = State::empty(state_builder, metadata_url);
-        state.grant_role(&ADMIN_ADDRESS, Roles::Admin, state_builder);
-        state
-    }
+    logger.log(&Event::UpdateData(UpdateDataEvent {
+        token_id: params.token_id,
+        address: sender,
+    }))?;
 
-    // Test initialization succeeds.
-    #[concordium_test]
-    fn test_init() {
-        // Setup the context
-        let mut ctx = TestInitContext::empty();
-        let mut builder = TestStateBuilder::new();
+    Ok(())
+}
 
-        let mut logger = TestLogger::init();
+#[allow(dead_code)]
+type TransferParameter = TransferParams<ContractTokenId, ContractTokenAmount>;
 
-        ctx.set_init_origin(ADMIN_ACCOUNT);
+/// The `transfer` function of the CIS-2 standard.
+/// This function always rejects.
+#[receive(
+    contract = "mysomeid",
+    name = "transfer",
+    parameter = "TransferParameter",
+    error = "ContractError"
+)]
+fn contract_transfer<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    Err(ContractError::Unauthorized)
+}
 
-        // Set up the parameter.
-        let parameter = SetMetadataUrlParams {
-            url: TOKEN_METADATA_BASE_URL.to_string(),
-        };
-        let parameter_bytes = to_bytes(&parameter);
-        ctx.set_parameter(&parameter_bytes);
+/// The `updateOperator` function of the CIS-2 standard.
+/// This function always rejects.
+#[receive(
+    contract = "mysomeid",
+    name = "updateOperator",
+    parameter = "UpdateOperatorParams",
+    error = "ContractError"
+)]
+fn contract_update_operator<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    Err(ContractError::Unauthorized)
+}
 
-        // Call the contract function.
-        let result = contract_init(&ctx, &mut builder, &mut logger);
+/// Takes a list of queries. Each query is an owner address and some address to
+/// check as an operator of the owner address. It returns `false` for every query.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+#[receive(
+    contract = "mysomeid",
+    name = "operatorOf",
+    parameter = "OperatorOfQueryParams",
+    return_value = "OperatorOfQueryResponse",
+    error = "ContractError"
+)]
+fn contract_operator_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<OperatorOfQueryResponse> {
+    // Parse the parameter.
+    let params: OperatorOfQueryParams = ctx.parameter_cursor().get()?;
+    // Build the response.
+    let response = vec![false; params.queries.len()];
+    let result = OperatorOfQueryResponse::from(response);
+    Ok(result)
+}
+
+/// Parameter type for the CIS-2 function `balanceOf` specialized to the subset
+/// of TokenIDs used by this contract.
+type ContractBalanceOfQueryParams = BalanceOfQueryParams<ContractTokenId>;
+/// Response type for the CIS-2 function `balanceOf` specialized to the subset
+/// of TokenAmounts used by this contract.
+type ContractBalanceOfQueryResponse = BalanceOfQueryResponse<ContractTokenAmount>;
+
+/// Get the balance of given token IDs and addresses.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - Any of the queried `token_id` does not exist.
+/// - The migration of the tokens to the current layout is not complete.
+#[receive(
+    contract = "mysomeid",
+    name = "balanceOf",
+    parameter = "ContractBalanceOfQueryParams",
+    return_value = "ContractBalanceOfQueryResponse",
+    error = "ContractError"
+)]
+fn contract_balance_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<ContractBalanceOfQueryResponse> {
+    // Parse the parameter.
+    let params: ContractBalanceOfQueryParams = ctx.parameter_cursor().get()?;
+    host.state().ensure_migrated()?;
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for query in params.queries {
+        // Query the state for balance.
+        let amount = host.state().balance(&query.token_id, &query.address)?;
+        response.push(amount);
+    }
+    let result = ContractBalanceOfQueryResponse::from(response);
+    Ok(result)
+}
+
+/// Parameter type for the CIS-2 function `tokenMetadata` specialized to the
+/// subset of TokenIDs used by this contract.
+type ContractTokenMetadataQueryParams = TokenMetadataQueryParams<ContractTokenId>;
+
+/// Get the token metadata URLs and checksums given a list of token IDs.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+/// - Any of the queried `token_id` does not exist.
+/// - The migration of the tokens to the current layout is not complete.
+#[receive(
+    contract = "mysomeid",
+    name = "tokenMetadata",
+    parameter = "ContractTokenMetadataQueryParams",
+    return_value = "TokenMetadataQueryResponse",
+    error = "ContractError"
+)]
+fn contract_token_metadata<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<TokenMetadataQueryResponse> {
+    // Parse the parameter.
+    let params: ContractTokenMetadataQueryParams = ctx.parameter_cursor().get()?;
+    host.state().ensure_migrated()?;
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for token_id in params.queries {
+        // Check the token exists.
+        if let Some(v) = host.state().all_tokens.get(&token_id) {
+            let metadata_url = MetadataUrl {
+                url: host.state().build_token_metadata_url(
+                    &token_id,
+                    v.platform,
+                    v.revoked,
+                    v.valid_until,
+                ),
+                hash: v.metadata_hash,
+            };
+            response.push(metadata_url);
+        } else {
+            return Err(ContractError::InvalidTokenId);
+        };
+    }
+    let result = TokenMetadataQueryResponse::from(response);
+    Ok(result)
+}
+
+/// Get the supported standards or addresses for a implementation given list of
+/// standard identifiers.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+#[receive(
+    contract = "mysomeid",
+    name = "supports",
+    parameter = "SupportsQueryParams",
+    return_value = "SupportsQueryResponse",
+    error = "ContractError"
+)]
+fn contract_supports<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<SupportsQueryResponse> {
+    // Parse the parameter.
+    let params: SupportsQueryParams = ctx.parameter_cursor().get()?;
+
+    // Build the response.
+    let mut response = Vec::with_capacity(params.queries.len());
+    for std_id in params.queries {
+        if SUPPORTS_STANDARDS.contains(&std_id.as_standard_identifier()) {
+            response.push(SupportResult::Support);
+        } else {
+            response.push(host.state().have_implementors(&std_id));
+        }
+    }
+    let result = SupportsQueryResponse::from(response);
+    Ok(result)
+}
+
+/// Set the addresses for an implementation given a standard identifier and a
+/// list of contract addresses.
+///
+/// It rejects if:
+/// - Sender is not the owner of the contract instance.
+/// - It fails to parse the parameter.
+#[receive(
+    contract = "mysomeid",
+    name = "setImplementors",
+    parameter = "SetImplementorsParams",
+    error = "ContractError",
+    mutable
+)]
+fn contract_set_implementor<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    // Authorize the sender.
+    ensure!(
+        ctx.sender().matches_account(&ctx.owner()),
+        ContractError::Unauthorized
+    );
+    // Parse the parameter.
+    let params: SetImplementorsParams = ctx.parameter_cursor().get()?;
+    // Update the implementors in the state
+    host.state_mut()
+        .set_implementors(params.id, params.implementors);
+    Ok(())
+}
+
+/// Update the metadata URL in this smart contract instance.
+///
+/// It rejects if:
+/// - Sender is not an address with Roles::Setter.
+/// - It fails to parse the parameter.
+#[receive(
+    contract = "mysomeid",
+    name = "setMetadataUrl",
+    parameter = "SetMetadataUrlParams",
+    error = "ContractError",
+    mutable
+)]
+fn contract_state_set_metadata_url<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    // Get the sender of the transaction
+    let sender = ctx.sender();
+
+    // Check that only an address with Roles::Setter is authorized to update the URL.
+    ensure!(
+        host.state().has_role(&sender, Roles::Setter),
+        ContractError::Unauthorized
+    );
+
+    // Parse the parameter.
+    let params: SetMetadataUrlParams = ctx.parameter_cursor().get()?;
+
+    // Create the metadata_url
+    let metadata_url = MetadataUrl {
+        url: params.url,
+        hash: None,
+    };
+
+    // Update the hash variable.
+    *host.state_mut().metadata_url = metadata_url;
+
+    Ok(())
+}
+
+/// Set or remove the metadata URL used for the tokens of a platform instead of
+/// the global metadata URL.
+/// Logs a `PlatformMetadataUrl` event.
+///
+/// It rejects if:
+/// - Sender is not an address with Roles::Setter.
+/// - The contract is paused.
+/// - It fails to parse the parameter.
+/// - Fails to log PlatformMetadataUrl event.
+#[receive(
+    contract = "mysomeid",
+    name = "setPlatformMetadataUrl",
+    parameter = "SetPlatformMetadataUrlParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_set_platform_metadata_url<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure!(
+        host.state().has_role(&ctx.sender(), Roles::Setter),
+        ContractError::Unauthorized
+    );
+    host.state().ensure_not_paused()?;
+
+    // Parse the parameter.
+    let params: SetPlatformMetadataUrlParams = ctx.parameter_cursor().get()?;
+
+    let state = host.state_mut();
+    match &params.url {
+        Some(url) => {
+            state
+                .platform_metadata_urls
+                .insert(params.platform, url.clone());
+        }
+        None => {
+            state.platform_metadata_urls.remove(&params.platform);
+        }
+    }
+
+    logger.log(&Event::PlatformMetadataUrl(PlatformMetadataUrlEvent {
+        platform: params.platform,
+        url: params.url,
+    }))?;
+
+    Ok(())
+}
+
+/// Record the SHA-256 hash of the metadata document of a token, e.g., after the
+/// document has changed.
+/// Logs a `TokenMetadata` event.
+///
+/// It rejects if:
+/// - Sender is not an address with Roles::Setter.
+/// - The contract is paused.
+/// - It fails to parse the parameter.
+/// - The token does not exist.
+/// - Fails to log TokenMetadata event.
+/// - The migration of the tokens to the current layout is not complete.
+#[receive(
+    contract = "mysomeid",
+    name = "updateTokenMetadata",
+    parameter = "UpdateTokenMetadataParams",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_update_token_metadata<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure!(
+        host.state().has_role(&ctx.sender(), Roles::Setter),
+        ContractError::Unauthorized
+    );
+    host.state().ensure_not_paused()?;
+
+    // Parse the parameter.
+    let params: UpdateTokenMetadataParams = ctx.parameter_cursor().get()?;
+
+    let state = host.state_mut();
+    state.ensure_migrated()?;
+    let (platform, revoked, valid_until) = {
+        let mut token = state
+            .all_tokens
+            .get_mut(&params.token_id)
+            .ok_or(ContractError::InvalidTokenId)?;
+        token.metadata_hash = params.metadata_hash;
+        (token.platform, token.revoked, token.valid_until)
+    };
+
+    logger.log(&Cis2Event::TokenMetadata::<_, ContractTokenAmount>(
+        TokenMetadataEvent {
+            token_id: params.token_id,
+            metadata_url: MetadataUrl {
+                url: state.build_token_metadata_url(
+                    &params.token_id,
+                    platform,
+                    revoked,
+                    valid_until,
+                ),
+                hash: params.metadata_hash,
+            },
+        },
+    ))?;
+
+    Ok(())
+}
+
+/// Upgrade this smart contract instance to a new module and call optionally a
+/// migration function after the upgrade. When the new module changes the layout
+/// of the state, the migration function should be its `migrate` entrypoint.
+///
+/// It rejects if:
+/// - Sender is not an address with Roles::Upgrader.
+/// - It fails to parse the parameter.
+/// - If the ugrade fails.
+/// - If the migration invoke fails.
+///
+/// This function is marked as `low_level`. This is **necessary** since the
+/// high-level mutable functions store the state of the contract at the end of
+/// execution. This conflicts with migration since the shape of the state
+/// **might** be changed by the migration function. If the state is then written
+/// by this function it would overwrite the state stored by the migration
+/// function.
+#[receive(
+    contract = "mysomeid",
+    name = "upgrade",
+    parameter = "UpgradeParams",
+    error = "ContractError",
+    low_level
+)]
+fn contract_upgrade<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<S>,
+) -> ContractResult<()> {
+    // Get the sender of the transaction
+    let sender = ctx.sender();
+
+    // Read the top-level contract state.
+    let state: State<S> = host.state().read_root()?;
+
+    // Check that only and address with Roles::Upgrader is authorized to upgrade the smart contract.
+    ensure!(
+        state.has_role(&sender, Roles::Upgrader),
+        ContractError::Unauthorized
+    );
+    // Parse the parameter.
+    let params: UpgradeParams = ctx.parameter_cursor().get()?;
+    // Trigger the upgrade.
+    host.upgrade(params.module)?;
+    // Call the migration function if provided.
+    if let Some((func, parameters)) = params.migrate {
+        host.invoke_contract_raw(
+            &ctx.self_address(),
+            parameters.as_parameter(),
+            func.as_entrypoint_name(),
+            Amount::zero(),
+        )?;
+    }
+    Ok(())
+}
+
+/// The parameter type for the contract function `migrate`.
+#[derive(Serialize, SchemaType)]
+struct MigrateParams {
+    /// The maximum number of tokens to convert in this call.
+    limit: u32,
+}
+
+/// Migrate the state from an older layout to the current one. The top-level
+/// state is converted in the first call, while tokens are converted in batches
+/// of at most `limit` tokens. Returns whether the migration is complete, and
+/// should be called until it is. It is meant to be called by `upgrade` after
+/// upgrading from a module with an older layout.
+///
+/// It rejects if:
+/// - Sender is neither this contract nor an address with Roles::Upgrader.
+/// - It fails to parse the parameter.
+/// - The state cannot be read in the current or any older layout.
+///
+/// This function is marked as `low_level` since it writes the state in the
+/// current layout, which the high-level functions cannot read before the
+/// migration.
+#[receive(
+    contract = "mysomeid",
+    name = "migrate",
+    parameter = "MigrateParams",
+    return_value = "bool",
+    error = "ContractError",
+    low_level
+)]
+fn contract_migrate<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<S>,
+) -> ContractResult<bool> {
+    let sender = ctx.sender();
+    // Parse the parameter.
+    let params: MigrateParams = ctx.parameter_cursor().get()?;
+    let mut state = State::migrate_root(host.state())?;
+    ensure!(
+        sender == Address::Contract(ctx.self_address()) || state.has_role(&sender, Roles::Upgrader),
+        ContractError::Unauthorized
+    );
+    let complete = state.migrate_tokens(host.state(), params.limit, ctx.metadata().slot_time())?;
+    host.state_mut().write_root(&state);
+    Ok(complete)
+}
+
+/// Check if an address has a role.
+///
+/// It rejects if:
+/// - It fails to parse the parameter.
+#[receive(
+    contract = "mysomeid",
+    name = "hasRole",
+    parameter = "HasRoleQueryParamaters",
+    return_value = "HasRoleQueryResponse"
+)]
+fn contract_has_role<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<HasRoleQueryResponse> {
+    let query: HasRoleQueryParamaters = ctx.parameter_cursor().get()?;
+    let has_role = host.state().has_role(&query.address, query.role);
+    Ok(HasRoleQueryResponse::from(has_role))
+}
+
+/// Grant Permission to an address
+///
+/// It rejects if:
+/// - Sender is not an address with Roles::Admin.
+/// - It fails to parse the parameter.
+#[receive(
+    contract = "mysomeid",
+    name = "grantRole",
+    parameter = "GrantRoleParams",
+    enable_logger,
+    mutable
+)]
+fn contract_grant_role<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    // Get the sender who invoked this contract function.
+    let sender = ctx.sender();
+
+    let (state, state_builder) = host.state_and_builder();
+    ensure!(
+        state.has_role(&sender, Roles::Admin),
+        ContractError::Unauthorized
+    );
+
+    // Parse the parameter.
+    let params: GrantRoleParams = ctx.parameter_cursor().get()?;
+
+    state.grant_role(&params.address, params.role, state_builder);
+
+    logger.log(&Event::GrantRole(GrantRoleEvent {
+        address: params.address,
+        role: params.role,
+    }))?;
+
+    Ok(())
+}
+
+/// Revoke Permission to an address
+///
+/// It rejects if:
+/// - Sender is not an address with Roles::Admin.
+/// - It fails to parse the parameter.
+/// - The address does not have the role.
+/// - The role is the Admin role of the last admin.
+#[receive(
+    contract = "mysomeid",
+    name = "revokeRole",
+    parameter = "RevokeRoleParams",
+    enable_logger,
+    mutable
+)]
+fn contract_revoke_role<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    // Get the sender who invoked this contract function.
+    let sender = ctx.sender();
+
+    let (state, _) = host.state_and_builder();
+    ensure!(
+        state.has_role(&sender, Roles::Admin),
+        ContractError::Unauthorized
+    );
+
+    // Parse the parameter.
+    let params: RevokeRoleParams = ctx.parameter_cursor().get()?;
+
+    state.revoke_role_checked(&params.address, params.role)?;
+
+    logger.log(&Event::RevokeRole(RevokeRoleEvent {
+        address: params.address,
+        role: params.role,
+    }))?;
+
+    Ok(())
+}
+
+/// Propose to hand over the Admin role of the sender to another address. The
+/// handover takes effect when the address calls `acceptAdmin`. A new proposal
+/// replaces any pending one.
+/// Logs a `ProposeAdmin` event.
+///
+/// It rejects if:
+/// - Sender is not an address with Roles::Admin.
+/// - It fails to parse the parameter.
+/// - Fails to log ProposeAdmin event.
+#[receive(
+    contract = "mysomeid",
+    name = "proposeAdmin",
+    parameter = "Address",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_propose_admin<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let sender = ctx.sender();
+    ensure!(
+        host.state().has_role(&sender, Roles::Admin),
+        ContractError::Unauthorized
+    );
+
+    // Parse the parameter.
+    let address: Address = ctx.parameter_cursor().get()?;
+
+    host.state_mut().admin_proposal = Some(AdminProposal {
+        proposer: sender,
+        address,
+    });
+
+    logger.log(&Event::ProposeAdmin(AdminHandoverEvent {
+        proposer: sender,
+        address,
+    }))?;
+
+    Ok(())
+}
+
+/// Accept a pending handover of the Admin role. The sender is granted the
+/// Admin role, and the role is revoked from the admin that proposed the
+/// handover.
+/// Logs an `AcceptAdmin`, a `GrantRole` and a `RevokeRole` event.
+///
+/// It rejects if:
+/// - Sender is not the address of the pending handover.
+/// - The proposer of the handover is no longer an admin.
+/// - Fails to log any of the events.
+#[receive(
+    contract = "mysomeid",
+    name = "acceptAdmin",
+    error = "ContractError",
+    enable_logger,
+    mutable
+)]
+fn contract_accept_admin<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let sender = ctx.sender();
+
+    let (state, state_builder) = host.state_and_builder();
+    let proposal = match state.admin_proposal {
+        Some(proposal) if proposal.address == sender => proposal,
+        _ => return Err(ContractError::Unauthorized),
+    };
+    ensure!(
+        state.has_role(&proposal.proposer, Roles::Admin),
+        ContractError::Unauthorized
+    );
+
+    state.admin_proposal = None;
+    state.grant_role(&sender, Roles::Admin, state_builder);
+    logger.log(&Event::GrantRole(GrantRoleEvent {
+        address: sender,
+        role: Roles::Admin,
+    }))?;
+    if proposal.proposer != sender {
+        state.revoke_role_checked(&proposal.proposer, Roles::Admin)?;
+        logger.log(&Event::RevokeRole(RevokeRoleEvent {
+            address: proposal.proposer,
+            role: Roles::Admin,
+        }))?;
+    }
+
+    logger.log(&Event::AcceptAdmin(AdminHandoverEvent {
+        proposer: proposal.proposer,
+        address: sender,
+    }))?;
+
+    Ok(())
+}
+
+/// Part of the return parameter of the `viewRoles` function.
+#[derive(Serialize, SchemaType, PartialEq)]
+struct ViewRolesState {
+    /// Vector of roles.
+    roles: Vec<Roles>,
+}
+
+/// Return parameter of the `viewRoles` function.
+#[derive(Serialize, SchemaType)]
+struct ViewAllRolesState {
+    /// Vector specifiying for each address a vector of its associated roles.
+    all_roles: Vec<(Address, ViewRolesState)>,
+}
+
+/// View function that returns the entire `roles` content of the state. Meant
+/// for monitoring.
+#[receive(
+    contract = "mysomeid",
+    name = "viewRoles",
+    return_value = "ViewAllRolesState"
+)]
+fn contract_view_roles<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewAllRolesState> {
+    let state = host.state();
+
+    let mut all_roles = Vec::new();
+    for (address, a_state) in state.roles.iter() {
+        let roles: Vec<Roles> = a_state.roles.iter().map(|x| *x).collect();
+
+        all_roles.push((*address, ViewRolesState { roles }));
+    }
+
+    Ok(ViewAllRolesState { all_roles })
+}
+
+// Tests
+
+#[concordium_cfg_test]
+mod tests {
+    use super::*;
+    use test_infrastructure::*;
+
+    const ACCOUNT_0: AccountAddress = AccountAddress([0u8; 32]);
+    const ADDRESS_0: Address = Address::Account(ACCOUNT_0);
+    const ACCOUNT_1: AccountAddress = AccountAddress([1u8; 32]);
+    const ADDRESS_1: Address = Address::Account(ACCOUNT_1);
+    const ADMIN_ACCOUNT: AccountAddress = AccountAddress([2u8; 32]);
+    const ADMIN_ADDRESS: Address = Address::Account(ADMIN_ACCOUNT);
+    const SETTER_ACCOUNT: AccountAddress = AccountAddress([3u8; 32]);
+    const SETTER_ADDRESS: Address = Address::Account(SETTER_ACCOUNT);
+    const MINTER_ACCOUNT: AccountAddress = AccountAddress([4u8; 32]);
+    const MINTER_ADDRESS: Address = Address::Account(MINTER_ACCOUNT);
+    const REVOKER_ACCOUNT: AccountAddress = AccountAddress([7u8; 32]);
+    const REVOKER_ADDRESS: Address = Address::Account(REVOKER_ACCOUNT);
+    const PAUSER_ACCOUNT: AccountAddress = AccountAddress([9u8; 32]);
+    const PAUSER_ADDRESS: Address = Address::Account(PAUSER_ACCOUNT);
+    const RECOVERER_ACCOUNT: AccountAddress = AccountAddress([8u8; 32]);
+    const RECOVERER_ADDRESS: Address = Address::Account(RECOVERER_ACCOUNT);
+    const TOKEN_0: ContractTokenId = TokenIdU64(0);
+    const SELF_ADDRESS: ContractAddress = ContractAddress {
+        index: 10,
+        subindex: 0,
+    };
+    const ISSUER_KEY: PublicKeyEd25519 = PublicKeyEd25519([5u8; 32]);
+    const SIGNATURE: SignatureEd25519 = SignatureEd25519([6u8; 64]);
+
+    const PLATFORM: Platform = Platform([0x01, 0x01]);
+    const ISSUED_AT: Timestamp = Timestamp::from_timestamp_millis(10);
+    const VALID_UNTIL: Timestamp = Timestamp::from_timestamp_millis(1000);
+    const BURNED_AT: Timestamp = Timestamp::from_timestamp_millis(20);
+
+    // The metadata url for this nft contract.
+    const TOKEN_METADATA_BASE_URL: &str = "https://api.mysomeid.dev/v1/proof/meta/";
+
+    fn initial_state<S: HasStateApi>(state_builder: &mut StateBuilder<S>) -> State<S> {
+        let metadata_url = MetadataUrl {
+            url: TOKEN_METADATA_BASE_URL.to_string(),
+            hash: None,
+        };
+        let mut state = State::empty(state_builder, metadata_url);
+        state.grant_role(&ADMIN_ADDRESS, Roles::Admin, state_builder);
+        state
+    }
+
+    // Test initialization succeeds.
+    #[concordium_test]
+    fn test_init() {
+        // Setup the context
+        let mut ctx = TestInitContext::empty();
+        let mut builder = TestStateBuilder::new();
+
+        let mut logger = TestLogger::init();
+
+        ctx.set_init_origin(ADMIN_ACCOUNT);
+
+        // Set up the parameter.
+        let parameter = SetMetadataUrlParams {
+            url: TOKEN_METADATA_BASE_URL.to_string(),
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+
+        // Call the contract function.
+        let result = contract_init(&ctx, &mut builder, &mut logger);
+
+        // Check the result
+        let state = result.expect_report("Contract initialization failed");
+
+        // Check the state
+        claim_eq!(
+            state.all_tokens.iter().count(),
+            0,
+            "No token should be initialized"
+        );
+
+        // Check the logs
+        claim_eq!(logger.logs.len(), 1, "Exactly one event should be logged");
+        claim!(
+            logger
+                .logs
+                .contains(&to_bytes(&Event::GrantRole(GrantRoleEvent {
+                    address: ADMIN_ADDRESS,
+                    role: Roles::Admin,
+                }))),
+            "Missing event which grants the admin role"
+        );
+    }
+
+    /// Test only Setter can setMetadataUrl
+    #[concordium_test]
+    fn test_set_metadata_url() {
+        let mut state_builder = TestStateBuilder::new();
+
+        let metadata_url = concordium_cis2::MetadataUrl {
+            url: TOKEN_METADATA_BASE_URL.to_string(),
+            hash: None,
+        };
+
+        let mut state = State::empty(&mut state_builder, metadata_url);
+        state.grant_role(&SETTER_ADDRESS, Roles::Setter, &mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        // Set up the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(SETTER_ADDRESS);
+
+        // Create a new_url
+        let new_url = "https://some.example/updated".to_string();
+
+        // Set up the parameter.
+        let parameter = SetMetadataUrlParams {
+            url: new_url.clone(),
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+
+        // Call the contract function.
+        let result = contract_state_set_metadata_url(&ctx, &mut host);
+
+        // Check the result.
+        claim!(result.is_ok(), "Results in rejection");
+
+        // Check the state.
+        let url = host.state().metadata_url.url.clone();
+        let hash = host.state().metadata_url.hash;
+        claim_eq!(url, new_url, "Expected url being updated");
+        claim_eq!(hash, None, "Expected hash being None");
+
+        // Check only the admin can update the metadata URL
+        ctx.set_sender(ADDRESS_0);
+
+        // Call the contract function.
+        let err = contract_state_set_metadata_url(&ctx, &mut host);
+
+        // Check that ADDRESS_0 was not successful in updating the metadata url.
+        claim_eq!(
+            err,
+            Err(ContractError::Unauthorized),
+            "Error is expected to be Unauthorized"
+        )
+    }
+
+    /// Test per-platform metadata URLs and recording the hash of the metadata
+    /// of a token.
+    #[concordium_test]
+    fn test_platform_metadata() {
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.grant_role(&SETTER_ADDRESS, Roles::Setter, &mut state_builder);
+        state
+            .mint(
+                ACCOUNT_0,
+                PLATFORM,
+                vec![12],
+                None,
+                ISSUED_AT,
+                None,
+                &mut state_builder,
+            )
+            .expect_report("Minting should succeed");
+        let mut host = TestHost::new(state, state_builder);
+
+        // Set up the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(SETTER_ADDRESS);
+
+        let platform_url = "https://li.example/meta/".to_string();
+        let parameter = SetPlatformMetadataUrlParams {
+            platform: PLATFORM,
+            url: Some(platform_url.clone()),
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+        let result = contract_set_platform_metadata_url(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&Event::PlatformMetadataUrl(
+                PlatformMetadataUrlEvent {
+                    platform: PLATFORM,
+                    url: Some(platform_url.clone()),
+                }
+            ))],
+            "Expected a PlatformMetadataUrl event"
+        );
+
+        let hash = [3u8; 32];
+        let parameter = UpdateTokenMetadataParams {
+            token_id: TOKEN_0,
+            metadata_hash: Some(hash),
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+        let result = contract_update_token_metadata(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+
+        // Check the metadata.
+        let parameter = TokenMetadataQueryParams {
+            queries: vec![TOKEN_0],
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+        let response = contract_token_metadata(&ctx, &host)
+            .expect_report("Querying the metadata should succeed");
+        claim_eq!(
+            response.0[0].url,
+            format!("{}0?p={}&r=0", platform_url, PLATFORM),
+            "The platform metadata URL should be used"
+        );
+        claim_eq!(
+            response.0[0].hash,
+            Some(hash),
+            "The metadata hash should be recorded"
+        );
+        claim_eq!(logger.logs.len(), 2, "Expected a TokenMetadata event");
+
+        // Removing the platform URL falls back to the global URL.
+        let parameter = SetPlatformMetadataUrlParams {
+            platform: PLATFORM,
+            url: None,
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+        let result = contract_set_platform_metadata_url(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+        claim_eq!(
+            host.state()
+                .build_token_metadata_url(&TOKEN_0, PLATFORM, false, None),
+            format!("{}0?p={}&r=0", TOKEN_METADATA_BASE_URL, PLATFORM),
+            "The global metadata URL should be used"
+        );
+
+        // Only the setter can update metadata.
+        ctx.set_sender(ADDRESS_0);
+        let err = contract_set_platform_metadata_url(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            err,
+            Err(ContractError::Unauthorized),
+            "Error is expected to be Unauthorized"
+        );
+
+        // Metadata cannot be updated while paused.
+        host.state_mut().paused = true;
+        ctx.set_sender(SETTER_ADDRESS);
+        let err = contract_set_platform_metadata_url(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            err,
+            Err(ContractError::Custom(CustomContractError::Paused)),
+            "Setting a platform metadata URL should be rejected while paused"
+        );
+        let parameter_bytes = to_bytes(&UpdateTokenMetadataParams {
+            token_id: TOKEN_0,
+            metadata_hash: None,
+        });
+        ctx.set_parameter(&parameter_bytes);
+        let err = contract_update_token_metadata(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            err,
+            Err(ContractError::Custom(CustomContractError::Paused)),
+            "Updating token metadata should be rejected while paused"
+        );
+    }
+
+    /// Test that minting is rejected while the contract is paused, and that
+    /// only a pauser can pause and unpause it.
+    #[concordium_test]
+    fn test_pause() {
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.grant_role(&PAUSER_ADDRESS, Roles::Pauser, &mut state_builder);
+        state.grant_role(&MINTER_ADDRESS, Roles::Minter, &mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_metadata_slot_time(ISSUED_AT);
+
+        // Only a pauser can pause.
+        ctx.set_sender(MINTER_ADDRESS);
+        let err = contract_pause(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            err,
+            Err(ContractError::Unauthorized),
+            "Only a pauser should be able to pause"
+        );
+
+        ctx.set_sender(PAUSER_ADDRESS);
+        contract_pause(&ctx, &mut host, &mut logger).expect_report("Pausing should succeed");
+        claim!(
+            logger.logs.contains(&to_bytes(&Event::Pause(PauseEvent {
+                address: PAUSER_ADDRESS,
+            }))),
+            "Expected an event for pausing"
+        );
+        claim!(
+            contract_is_paused(&ctx, &host).expect_report("Views should work while paused"),
+            "Contract should be paused"
+        );
+
+        // Minting is rejected while paused.
+        let parameter_bytes = to_bytes(&MintParams {
+            owner: ACCOUNT_0,
+            platform: PLATFORM,
+            data: vec![12],
+            valid_until: None,
+            commitment: None,
+        });
+        ctx.set_parameter(&parameter_bytes);
+        ctx.set_sender(MINTER_ADDRESS);
+        let err = contract_mint(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            err,
+            Err(ContractError::Custom(CustomContractError::Paused)),
+            "Minting should be rejected while paused"
+        );
+
+        ctx.set_sender(PAUSER_ADDRESS);
+        contract_unpause(&ctx, &mut host, &mut logger).expect_report("Unpausing should succeed");
+        ctx.set_sender(MINTER_ADDRESS);
+        contract_mint(&ctx, &mut host, &mut logger)
+            .expect_report("Minting should succeed after unpausing");
+    }
+
+    /// Test minting, ensuring the new token is owned by the given address and
+    /// the appropriate events are logged.
+    #[concordium_test]
+    fn test_mint() {
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(MINTER_ADDRESS);
+        ctx.set_metadata_slot_time(ISSUED_AT);
+
+        // and parameter.
+        let parameter = MintParams {
+            owner: ACCOUNT_0,
+            platform: PLATFORM,
+            data: vec![12],
+            valid_until: Some(VALID_UNTIL),
+            commitment: None,
+        };
+
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+
+        let metadata_url = concordium_cis2::MetadataUrl {
+            url: TOKEN_METADATA_BASE_URL.to_string(),
+            hash: None,
+        };
+
+        let mut state = State::empty(&mut state_builder, metadata_url);
+        let url = state.build_token_metadata_url(&TOKEN_0, PLATFORM, false, Some(VALID_UNTIL));
+        state.grant_role(&MINTER_ADDRESS, Roles::Minter, &mut state_builder);
+
+        let mut host = TestHost::new(state, state_builder);
+
+        // Call the contract function.
+        let result: ContractResult<()> = contract_mint(&ctx, &mut host, &mut logger);
+
+        // Check the result
+        claim!(result.is_ok(), "Results in rejection");
+
+        // Check the state
+        claim_eq!(
+            host.state().all_tokens.iter().count(),
+            1,
+            "Expected one token in the state."
+        );
+
+        let balance0 = host
+            .state()
+            .balance(&TOKEN_0, &ADDRESS_0)
+            .expect_report("Token is expected to exist");
+        claim_eq!(
+            balance0,
+            1.into(),
+            "Tokens should be owned by the given address 0"
+        );
+
+        let token = host
+            .state()
+            .all_tokens
+            .get(&TOKEN_0)
+            .expect_report("Token is expected to exist");
+        claim_eq!(token.issued_at, ISSUED_AT, "Token should be issued now");
+        claim_eq!(
+            token.valid_until,
+            Some(VALID_UNTIL),
+            "Token should expire at the requested time"
+        );
+        claim!(
+            url.ends_with("&e=1000"),
+            "Metadata URL should include the expiry time"
+        );
+
+        // Check the logs
+        claim!(
+            logger.logs.contains(&to_bytes(&Cis2Event::Mint(MintEvent {
+                owner: ADDRESS_0,
+                token_id: TOKEN_0,
+                amount: ContractTokenAmount::from(1),
+            }))),
+            "Expected an event for minting TOKEN_0"
+        );
+        claim!(
+            logger.logs.contains(&to_bytes(
+                &Cis2Event::TokenMetadata::<_, ContractTokenAmount>(TokenMetadataEvent {
+                    token_id: TOKEN_0,
+                    metadata_url: MetadataUrl { url, hash: None },
+                })
+            )),
+            "Expected an event for token metadata for TOKEN_0"
+        );
+        claim!(
+            logger
+                .logs
+                .contains(&to_bytes(&Event::TokenMinted(TokenMintedEvent {
+                    token_id: TOKEN_0,
+                    owner: ACCOUNT_0,
+                    platform: PLATFORM,
+                    issued_at: ISSUED_AT,
+                }))),
+            "Expected a TokenMinted event for TOKEN_0"
+        );
+    }
+
+    /// Test minting several tokens in one call.
+    #[concordium_test]
+    fn test_batch_mint() {
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.grant_role(&MINTER_ADDRESS, Roles::Minter, &mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(MINTER_ADDRESS);
+        ctx.set_metadata_slot_time(ISSUED_AT);
+
+        // and parameter.
+        let parameter = BatchMintParams {
+            mints: [ACCOUNT_0, ACCOUNT_1]
+                .iter()
+                .map(|owner| MintParams {
+                    owner: *owner,
+                    platform: PLATFORM,
+                    data: vec![12],
+                    valid_until: None,
+                    commitment: None,
+                })
+                .collect(),
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+
+        let token_ids = contract_batch_mint(&ctx, &mut host, &mut logger)
+            .expect_report("Batch minting should succeed");
+        claim_eq!(
+            token_ids,
+            vec![TOKEN_0, TokenIdU64(1)],
+            "Tokens should be minted in order"
+        );
+        claim_eq!(
+            host.state().balance(&TokenIdU64(1), &ADDRESS_1),
+            Ok(1.into()),
+            "The second token should be owned by the second owner"
+        );
+        claim_eq!(logger.logs.len(), 6, "Expected three events per token");
+
+        // Only a minter can mint.
+        ctx.set_sender(ADDRESS_0);
+        let err = contract_batch_mint(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            err,
+            Err(ContractError::Unauthorized),
+            "Error is expected to be Unauthorized"
+        );
+    }
+
+    /// Test minting with a permit, ensuring the token is minted to the sender,
+    /// the nonce is incremented, and the permit cannot be replayed.
+    #[concordium_test]
+    fn test_permit() {
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADDRESS_0);
+        ctx.set_self_address(SELF_ADDRESS);
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(100));
+
+        // and parameter.
+        let parameter = PermitParams {
+            signer: ISSUER_KEY,
+            signature: SIGNATURE,
+            message: PermitMessage {
+                contract_address: SELF_ADDRESS,
+                nonce: 0,
+                timestamp: Timestamp::from_timestamp_millis(200),
+                mint: MintParams {
+                    owner: ACCOUNT_0,
+                    platform: PLATFORM,
+                    data: vec![12],
+                    valid_until: None,
+                    commitment: None,
+                },
+            },
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+
+        let crypto_primitives = TestCryptoPrimitives::new();
+        crypto_primitives.setup_hash_sha2_256_mock(|_| HashSha2256([0u8; 32]));
+        crypto_primitives.setup_verify_ed25519_signature_mock(|key, signature, _| {
+            key == ISSUER_KEY && signature == SIGNATURE
+        });
+
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.issuer_keys.insert(ISSUER_KEY);
+        let mut host = TestHost::new(state, state_builder);
+
+        // Call the contract function.
+        let result = contract_permit(&ctx, &mut host, &mut logger, &crypto_primitives);
+
+        // Check the result
+        claim!(result.is_ok(), "Results in rejection");
+
+        // Check the state
+        let balance0 = host
+            .state()
+            .balance(&TOKEN_0, &ADDRESS_0)
+            .expect_report("Token is expected to exist");
+        claim_eq!(balance0, 1.into(), "Token should be owned by the sender");
+        claim_eq!(
+            host.state().nonces.get(&ACCOUNT_0).map(|n| *n),
+            Some(1),
+            "Nonce should be incremented"
+        );
+
+        // Check the logs
+        claim!(
+            logger.logs.contains(&to_bytes(&Event::Nonce(NonceEvent {
+                account: ACCOUNT_0,
+                nonce: 0,
+            }))),
+            "Expected a nonce event"
+        );
+        claim!(
+            logger.logs.contains(&to_bytes(&Cis2Event::Mint(MintEvent {
+                owner: ADDRESS_0,
+                token_id: TOKEN_0,
+                amount: ContractTokenAmount::from(1),
+            }))),
+            "Expected an event for minting TOKEN_0"
+        );
+
+        // The same permit cannot be used twice.
+        let err = contract_permit(&ctx, &mut host, &mut logger, &crypto_primitives);
+        claim_eq!(
+            err,
+            Err(ContractError::Custom(CustomContractError::NonceMismatch)),
+            "Replaying a permit should fail"
+        );
+
+        // Only the owner can submit the permit.
+        ctx.set_sender(ADDRESS_1);
+        let err = contract_permit(&ctx, &mut host, &mut logger, &crypto_primitives);
+        claim_eq!(
+            err,
+            Err(ContractError::Unauthorized),
+            "Permit should only be usable by the owner"
+        );
+    }
+
+    /// Test that the owner can burn a token, and that the data of the token is
+    /// erased.
+    #[concordium_test]
+    fn test_burn() {
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state
+            .mint(
+                ACCOUNT_0,
+                PLATFORM,
+                vec![12],
+                None,
+                ISSUED_AT,
+                None,
+                &mut state_builder,
+            )
+            .expect_report("Minting should succeed");
+        let mut host = TestHost::new(state, state_builder);
+
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_metadata_slot_time(BURNED_AT);
+        let parameter_bytes = to_bytes(&TOKEN_0);
+        ctx.set_parameter(&parameter_bytes);
+
+        // Only the owner can burn.
+        ctx.set_sender(ADDRESS_1);
+        let err = contract_burn(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            err,
+            Err(ContractError::InsufficientFunds),
+            "Only the owner should be able to burn"
+        );
+
+        ctx.set_sender(ADDRESS_0);
+        let result = contract_burn(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+
+        // Check the logs
+        claim!(
+            logger.logs.contains(&to_bytes(&Cis2Event::Burn(BurnEvent {
+                owner: ADDRESS_0,
+                token_id: TOKEN_0,
+                amount: ContractTokenAmount::from(1),
+            }))),
+            "Expected an event for burning TOKEN_0"
+        );
+
+        // Check that only the tombstone is left.
+        let view = contract_view_data(&ctx, &host)
+            .expect_report("Viewing the token should succeed")
+            .expect_report("Token should exist");
+        claim!(view.revoked, "Token should be revoked");
+        claim_eq!(view.owner, ACCOUNT_0, "Owner should be kept");
+        claim_eq!(
+            view.burned_at,
+            Some(BURNED_AT),
+            "Burn time should be recorded"
+        );
+        claim!(view.data.is_empty(), "Data should be erased");
+        claim!(
+            host.state()
+                .all_tokens
+                .get(&TOKEN_0)
+                .map_or(false, |token| token.data.is_none()),
+            "No data should be stored for the tombstone"
+        );
+    }
+
+    /// Test minting a token whose data is stored off-chain, and that data and a
+    /// commitment cannot both be given.
+    #[concordium_test]
+    fn test_mint_commitment() {
+        let commitment = DataCommitment {
+            hash: [8u8; 32],
+            locator: String::from("https://api.mysomeid.dev/v1/proof/data/08"),
+        };
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+
+        let err = state.mint(
+            ACCOUNT_0,
+            PLATFORM,
+            vec![12],
+            Some(commitment.clone()),
+            ISSUED_AT,
+            None,
+            &mut state_builder,
+        );
+        claim_eq!(
+            err,
+            Err(ContractError::Custom(
+                CustomContractError::DataAndCommitment
+            )),
+            "Minting with both data and a commitment should fail"
+        );
+
+        state
+            .mint(
+                ACCOUNT_0,
+                PLATFORM,
+                Vec::new(),
+                Some(commitment.clone()),
+                ISSUED_AT,
+                None,
+                &mut state_builder,
+            )
+            .expect_report("Minting should succeed");
+        let host = TestHost::new(state, state_builder);
+
+        let mut ctx = TestReceiveContext::empty();
+        let parameter_bytes = to_bytes(&TOKEN_0);
+        ctx.set_parameter(&parameter_bytes);
+        let view = contract_view_data(&ctx, &host)
+            .expect_report("Viewing the token should succeed")
+            .expect_report("Token should exist");
+        claim!(view.data.is_empty(), "No data should be stored on-chain");
+        claim_eq!(
+            view.commitment,
+            Some(commitment),
+            "The commitment should be stored"
+        );
+    }
+
+    /// Test that tokens minted to one alias of an account can be listed,
+    /// queried and burned using any other alias of the account.
+    #[concordium_test]
+    fn test_aliases() {
+        let mut logger = TestLogger::init();
+        let mut alias_0 = [1u8; 32];
+        alias_0[31] = 0;
+        let mut alias_1 = [1u8; 32];
+        alias_1[31] = 1;
+        let alias_0 = AccountAddress(alias_0);
+        let alias_1 = AccountAddress(alias_1);
+
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state
+            .mint(
+                alias_0,
+                PLATFORM,
+                vec![12],
+                None,
+                ISSUED_AT,
+                None,
+                &mut state_builder,
+            )
+            .expect_report("Minting should succeed");
+        let mut host = TestHost::new(state, state_builder);
+
+        let mut ctx = TestReceiveContext::empty();
+        let parameter_bytes = to_bytes(&alias_1);
+        ctx.set_parameter(&parameter_bytes);
+        let list =
+            contract_list_owned_tokens(&ctx, &host).expect_report("Listing tokens should succeed");
+        claim_eq!(
+            list.tokens,
+            vec![TOKEN_0],
+            "The token should be listed for the alias"
+        );
+        claim_eq!(
+            host.state()
+                .balance(&TOKEN_0, &Address::Account(alias_1))
+                .expect_report("Querying the balance should succeed"),
+            ContractTokenAmount::from(1),
+            "The alias should hold the token"
+        );
+
+        ctx.set_metadata_slot_time(BURNED_AT);
+        let parameter_bytes = to_bytes(&TOKEN_0);
+        ctx.set_parameter(&parameter_bytes);
+        ctx.set_sender(Address::Account(alias_1));
+        let result = contract_burn(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "The alias should be able to burn the token");
+        claim_eq!(
+            host.state()
+                .balance(&TOKEN_0, &Address::Account(alias_0))
+                .expect_report("Querying the balance should succeed"),
+            ContractTokenAmount::from(0),
+            "The token should be burned for all aliases"
+        );
+    }
+
+    /// Test that tokens stored under the address they were minted to, as
+    /// before the state was keyed by canonical addresses, are still found.
+    #[concordium_test]
+    fn test_aliases_legacy_keys() {
+        let mut logger = TestLogger::init();
+        let mut alias_1 = [1u8; 32];
+        alias_1[31] = 1;
+        let mut alias_2 = [1u8; 32];
+        alias_2[31] = 2;
+        let alias_1 = AccountAddress(alias_1);
+        let alias_2 = AccountAddress(alias_2);
+
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state
+            .mint(
+                alias_1,
+                PLATFORM,
+                vec![12],
+                None,
+                ISSUED_AT,
+                None,
+                &mut state_builder,
+            )
+            .expect_report("Minting should succeed");
+        // Move the token to the key used before canonicalisation.
+        state.state.remove(&canonical_address(&alias_1));
+        let mut owner_state = AddressState::empty(&mut state_builder);
+        owner_state.owned_tokens.insert(TOKEN_0);
+        state.state.insert(alias_1, owner_state);
+        let mut host = TestHost::new(state, state_builder);
+
+        let mut ctx = TestReceiveContext::empty();
+        let parameter_bytes = to_bytes(&alias_1);
+        ctx.set_parameter(&parameter_bytes);
+        let list =
+            contract_list_owned_tokens(&ctx, &host).expect_report("Listing tokens should succeed");
+        claim_eq!(list.tokens, vec![TOKEN_0], "The token should be listed");
+        claim_eq!(
+            host.state()
+                .balance(&TOKEN_0, &Address::Account(alias_2))
+                .expect_report("Querying the balance should succeed"),
+            ContractTokenAmount::from(1),
+            "Another alias should hold the token"
+        );
+
+        ctx.set_metadata_slot_time(BURNED_AT);
+        let parameter_bytes = to_bytes(&TOKEN_0);
+        ctx.set_parameter(&parameter_bytes);
+        ctx.set_sender(Address::Account(alias_2));
+        let result = contract_burn(&ctx, &mut host, &mut logger);
+        claim!(
+            result.is_ok(),
+            "Another alias should be able to burn the token"
+        );
+        claim_eq!(
+            host.state()
+                .balance(&TOKEN_0, &Address::Account(alias_1))
+                .expect_report("Querying the balance should succeed"),
+            ContractTokenAmount::from(0),
+            "The token should be burned"
+        );
+    }
+
+    /// Test migrating a state stored in the layout before the state was
+    /// versioned.
+    #[concordium_test]
+    fn test_migrate() {
+        let mut alias = [1u8; 32];
+        alias[31] = 1;
+        let alias = AccountAddress(alias);
+
+        let mut state_api = TestStateApi::new();
+        let mut state_builder = StateBuilder::open(state_api.clone());
+        let mut roles = state_builder.new_map();
+        let mut upgrader_roles = state_builder.new_set();
+        upgrader_roles.insert(Roles::Upgrader);
+        roles.insert(
+            ADMIN_ADDRESS,
+            AddressRoleState {
+                roles: upgrader_roles,
+            },
+        );
+        let mut state = state_builder.new_map();
+        let all_tokens: StateMap<ContractTokenId, TokenState<TestStateApi>, TestStateApi> =
+            state_builder.new_map();
+        // Token 2 was burned, which removed it from the tokens of the owner.
+        for (id, owner, revoked) in [
+            (0, ACCOUNT_0, false),
+            (1, alias, false),
+            (2, ACCOUNT_0, true),
+        ] {
+            // The map cannot write entries in the old layout, so they are
+            // written directly.
+            let token = TokenStateV0 {
+                revoked,
+                owner,
+                platform: PLATFORM,
+                data: state_builder.new_box(vec![12]),
+            };
+            let mut key = to_bytes(&all_tokens);
+            key.extend(to_bytes(&TokenIdU64(id)));
+            let mut entry = state_api
+                .create_entry(&key)
+                .expect_report("Creating the entry should succeed");
+            token
+                .serial(&mut entry)
+                .expect_report("Writing the entry should succeed");
+            if !revoked {
+                let mut owner_state = state
+                    .entry(owner)
+                    .or_insert_with(|| AddressState::empty(&mut state_builder));
+                owner_state.owned_tokens.insert(TokenIdU64(id));
+            }
+        }
+        let old = StateV0 {
+            next_token_id: 3,
+            roles,
+            state,
+            all_tokens,
+            implementors: state_builder.new_map(),
+            metadata_url: state_builder.new_box(MetadataUrl {
+                url: TOKEN_METADATA_BASE_URL.to_string(),
+                hash: None,
+            }),
+        };
+        state_api.write_root(&old);
+
+        // Convert the top-level state and the tokens one at a time.
+        let mut state = State::migrate_root(&state_api).expect_report("Migration should succeed");
+        claim_eq!(state.version, STATE_VERSION, "Version should be set");
+        claim!(
+            state.has_role(&ADMIN_ADDRESS, Roles::Upgrader),
+            "Roles should be kept"
+        );
+        claim!(
+            !state
+                .migrate_tokens(&state_api, 1, BURNED_AT)
+                .expect_report("Migration should succeed"),
+            "Two tokens should remain"
+        );
+        claim_eq!(
+            state.ensure_migrated(),
+            Err(ContractError::Custom(
+                CustomContractError::MigrationInProgress
+            )),
+            "Tokens should not be accessible during the migration"
+        );
+        claim!(
+            state
+                .migrate_tokens(&state_api, 2, BURNED_AT)
+                .expect_report("Migration should succeed"),
+            "All tokens should be migrated"
+        );
+        claim!(
+            state.ensure_migrated().is_ok(),
+            "Tokens should be accessible after the migration"
+        );
+        state_api.write_root(&state);
+
+        // The state can now be read in the current layout.
+        let state: State<TestStateApi> = state_api
+            .read_root()
+            .expect_report("The state should be in the current layout");
+        claim_eq!(state.next_token_id, 3, "Token ID counter should be kept");
+        claim!(
+            state.token_migration.is_none(),
+            "No tokens should remain to be migrated"
+        );
+        let token = state
+            .all_tokens
+            .get(&TokenIdU64(1))
+            .expect_report("Token should exist");
+        claim_eq!(token.owner, alias, "Owner should be kept");
+        claim_eq!(token.data(), vec![12], "Data should be kept");
+        claim!(token.valid_until.is_none(), "Token should not expire");
+        claim_eq!(
+            state
+                .balance(&TokenIdU64(1), &Address::Account(canonical_address(&alias)))
+                .expect_report("Querying the balance should succeed"),
+            ContractTokenAmount::from(1),
+            "The token should be owned by the canonical address"
+        );
+        drop(token);
+        let burned = state
+            .all_tokens
+            .get(&TokenIdU64(2))
+            .expect_report("Burned token should exist");
+        claim!(burned.revoked, "Burned token should stay revoked");
+        claim!(
+            burned.data.is_none(),
+            "Data of a burned token should be erased"
+        );
+        claim_eq!(
+            burned.burned_at,
+            Some(BURNED_AT),
+            "Burned token should be recorded as burned during the migration"
+        );
+        drop(burned);
+        claim_eq!(state.owner_count, 2, "Migrated tokens should be counted");
+
+        // A state of a later version, e.g., after a downgrade, is rejected.
+        let mut state = state;
+        state.version = STATE_VERSION + 1;
+        state_api.write_root(&state);
+        claim!(
+            State::migrate_root(&state_api).is_err(),
+            "A state of a later version should be rejected"
+        );
+    }
+
+    /// Test that a revoker can revoke a token of another account, and that the
+    /// reason is recorded and returned by `viewData`.
+    #[concordium_test]
+    fn test_revoke_by_issuer() {
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.grant_role(&REVOKER_ADDRESS, Roles::Revoker, &mut state_builder);
+        state
+            .mint(
+                ACCOUNT_0,
+                PLATFORM,
+                vec![12],
+                None,
+                ISSUED_AT,
+                None,
+                &mut state_builder,
+            )
+            .expect_report("Minting should succeed");
+        let mut host = TestHost::new(state, state_builder);
+
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADDRESS_1);
+
+        // and parameter.
+        let parameter = RevokeByIssuerParams {
+            token_id: TOKEN_0,
+            reason: RevocationReason::Fraud,
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+
+        // Only the revoker can revoke.
+        let err = contract_revoke_by_issuer(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            err,
+            Err(ContractError::Unauthorized),
+            "Error is expected to be Unauthorized"
+        );
+
+        ctx.set_sender(REVOKER_ADDRESS);
+        let result = contract_revoke_by_issuer(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+
+        // Check the state
+        claim_eq!(
+            host.state().balance(&TOKEN_0, &ADDRESS_0),
+            Ok(0.into()),
+            "The owner should no longer hold the token"
+        );
+
+        // Check the logs
+        claim!(
+            logger.logs.contains(&to_bytes(&Cis2Event::Burn(BurnEvent {
+                owner: ADDRESS_0,
+                token_id: TOKEN_0,
+                amount: ContractTokenAmount::from(1),
+            }))),
+            "Expected an event for burning TOKEN_0"
+        );
+        claim!(
+            logger
+                .logs
+                .contains(&to_bytes(&Event::RevokeByIssuer(RevokeByIssuerEvent {
+                    token_id: TOKEN_0,
+                    reason: RevocationReason::Fraud,
+                }))),
+            "Expected an event for revoking TOKEN_0"
+        );
+
+        // Check the view
+        let parameter_bytes = to_bytes(&TOKEN_0);
+        ctx.set_parameter(&parameter_bytes);
+        let view = contract_view_data(&ctx, &host)
+            .expect_report("Viewing the token should succeed")
+            .expect_report("Token should exist");
+        claim!(view.revoked, "Token should be revoked");
+        claim_eq!(
+            view.revocation_reason,
+            Some(RevocationReason::Fraud),
+            "Revocation reason should be recorded"
+        );
+
+        // A token cannot be revoked twice.
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+        let err = contract_revoke_by_issuer(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            err,
+            Err(ContractError::Custom(CustomContractError::TokenRevoked)),
+            "Revoking twice should fail"
+        );
+    }
+
+    /// Test reissuing a token, which revokes the old token and mints a new one
+    /// pointing back to it.
+    #[concordium_test]
+    fn test_reissue() {
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.grant_role(&MINTER_ADDRESS, Roles::Minter, &mut state_builder);
+        state
+            .mint(
+                ACCOUNT_0,
+                PLATFORM,
+                vec![12],
+                None,
+                ISSUED_AT,
+                None,
+                &mut state_builder,
+            )
+            .expect_report("Minting should succeed");
+        let mut host = TestHost::new(state, state_builder);
+
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADDRESS_1);
+        ctx.set_metadata_slot_time(BURNED_AT);
+
+        // and parameter.
+        let mut parameter = ReissueParams {
+            token_id: TOKEN_0,
+            mint: MintParams {
+                owner: ACCOUNT_1,
+                platform: PLATFORM,
+                data: vec![13],
+                valid_until: None,
+                commitment: None,
+            },
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+
+        // Only a minter can reissue.
+        let err = contract_reissue(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            err,
+            Err(ContractError::Unauthorized),
+            "Error is expected to be Unauthorized"
+        );
+
+        // The new token must have the same owner.
+        ctx.set_sender(MINTER_ADDRESS);
+        let err = contract_reissue(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            err,
+            Err(ContractError::Custom(CustomContractError::WrongOwner)),
+            "Reissuing to another owner should fail"
+        );
+
+        parameter.mint.owner = ACCOUNT_0;
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+        let token_id = contract_reissue(&ctx, &mut host, &mut logger)
+            .expect_report("Reissuing should succeed");
+        claim_eq!(token_id, TokenIdU64(1), "A new token should be minted");
+
+        // Check the state
+        claim_eq!(
+            host.state().balance(&TOKEN_0, &ADDRESS_0),
+            Ok(0.into()),
+            "The owner should no longer hold the old token"
+        );
+        claim_eq!(
+            host.state().balance(&token_id, &ADDRESS_0),
+            Ok(1.into()),
+            "The owner should hold the new token"
+        );
+
+        // Check the logs
+        claim!(
+            logger
+                .logs
+                .contains(&to_bytes(&Event::RevokeByIssuer(RevokeByIssuerEvent {
+                    token_id: TOKEN_0,
+                    reason: RevocationReason::Superseded,
+                }))),
+            "Expected an event for revoking TOKEN_0"
+        );
+        claim!(
+            logger
+                .logs
+                .contains(&to_bytes(&Event::Reissue(ReissueEvent {
+                    replaced: TOKEN_0,
+                    token_id,
+                }))),
+            "Expected an event for reissuing TOKEN_0"
+        );
+
+        // Check the views
+        let parameter_bytes = to_bytes(&TOKEN_0);
+        ctx.set_parameter(&parameter_bytes);
+        let view = contract_view_data(&ctx, &host)
+            .expect_report("Viewing the token should succeed")
+            .expect_report("Token should exist");
+        claim_eq!(
+            view.revocation_reason,
+            Some(RevocationReason::Superseded),
+            "The old token should be superseded"
+        );
+        claim_eq!(
+            view.replaced_by,
+            Some(token_id),
+            "The old token should point to the new one"
+        );
+        let parameter_bytes = to_bytes(&token_id);
+        ctx.set_parameter(&parameter_bytes);
+        let view = contract_view_data(&ctx, &host)
+            .expect_report("Viewing the token should succeed")
+            .expect_report("Token should exist");
+        claim_eq!(
+            view.replaces,
+            Some(TOKEN_0),
+            "The new token should point to the old one"
+        );
+
+        // A superseded token cannot be reissued again.
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+        let err = contract_reissue(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            err,
+            Err(ContractError::Custom(CustomContractError::TokenRevoked)),
+            "Reissuing twice should fail"
+        );
+    }
+
+    /// Test recovering a token to a new account.
+    #[concordium_test]
+    fn test_recover() {
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.grant_role(&RECOVERER_ADDRESS, Roles::Recoverer, &mut state_builder);
+        state
+            .mint(
+                ACCOUNT_0,
+                PLATFORM,
+                vec![12],
+                None,
+                ISSUED_AT,
+                None,
+                &mut state_builder,
+            )
+            .expect_report("Minting should succeed");
+        let mut host = TestHost::new(state, state_builder);
+
+        // Setup the context
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADDRESS_1);
+
+        // and parameter.
+        let mut parameter = RecoverParams {
+            token_id: TOKEN_0,
+            owner: ACCOUNT_0,
+            data: vec![13],
+            commitment: None,
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+
+        // Only the recoverer can recover.
+        let err = contract_recover(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            err,
+            Err(ContractError::Unauthorized),
+            "Error is expected to be Unauthorized"
+        );
+
+        // The token cannot be recovered to its owner.
+        ctx.set_sender(RECOVERER_ADDRESS);
+        let err = contract_recover(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            err,
+            Err(ContractError::Custom(CustomContractError::SameOwner)),
+            "Recovering to the same owner should fail"
+        );
 
-        // Check the result
-        let state = result.expect_report("Contract initialization failed");
+        parameter.owner = ACCOUNT_1;
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+        let result = contract_recover(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
 
         // Check the state
         claim_eq!(
-            state.all_tokens.iter().count(),
-            0,
-            "No token should be initialized"
+            host.state().balance(&TOKEN_0, &ADDRESS_0),
+            Ok(0.into()),
+            "The previous owner should no longer hold the token"
+        );
+        claim_eq!(
+            host.state().balance(&TOKEN_0, &ADDRESS_1),
+            Ok(1.into()),
+            "The new owner should hold the token"
         );
 
         // Check the logs
-        claim_eq!(logger.logs.len(), 1, "Exactly one event should be logged");
+        claim!(
+            logger.logs.contains(&to_bytes(&Cis2Event::Burn(BurnEvent {
+                owner: ADDRESS_0,
+                token_id: TOKEN_0,
+                amount: ContractTokenAmount::from(1),
+            }))),
+            "Expected an event for burning TOKEN_0"
+        );
+        claim!(
+            logger.logs.contains(&to_bytes(&Cis2Event::Mint(MintEvent {
+                owner: ADDRESS_1,
+                token_id: TOKEN_0,
+                amount: ContractTokenAmount::from(1),
+            }))),
+            "Expected an event for minting TOKEN_0"
+        );
         claim!(
             logger
                 .logs
-                .contains(&to_bytes(&Event::GrantRole(GrantRoleEvent {
-                    address: ADMIN_ADDRESS,
-                    role: Roles::Admin,
+                .contains(&to_bytes(&Event::TokenMinted(TokenMintedEvent {
+                    token_id: TOKEN_0,
+                    owner: ACCOUNT_1,
+                    platform: PLATFORM,
+                    issued_at: ISSUED_AT,
                 }))),
-            "Missing event which grants the admin role"
+            "Expected a TokenMinted event for the new owner"
         );
+
+        // Check the view
+        let parameter_bytes = to_bytes(&TOKEN_0);
+        ctx.set_parameter(&parameter_bytes);
+        let view = contract_view_data(&ctx, &host)
+            .expect_report("Viewing the token should succeed")
+            .expect_report("Token should exist");
+        claim_eq!(view.owner, ACCOUNT_1, "The owner should be updated");
+        claim_eq!(view.data, vec![13], "The data should be replaced");
+        claim!(!view.revoked, "The token should not be revoked");
     }
 
-    /// Test only Setter can setMetadataUrl
+    /// Test replacing the data of a token by its owner and by the minter.
     #[concordium_test]
-    fn test_set_metadata_url() {
+    fn test_update_data() {
+        let mut logger = TestLogger::init();
         let mut state_builder = TestStateBuilder::new();
-
-        let metadata_url = concordium_cis2::MetadataUrl {
-            url: TOKEN_METADATA_BASE_URL.to_string(),
-            hash: None,
-        };
-
-        let mut state = State::empty(&mut state_builder, metadata_url);
-        state.grant_role(&SETTER_ADDRESS, Roles::Setter, &mut state_builder);
+        let mut state = initial_state(&mut state_builder);
+        state.grant_role(&MINTER_ADDRESS, Roles::Minter, &mut state_builder);
+        state
+            .mint(
+                ACCOUNT_0,
+                PLATFORM,
+                vec![12],
+                None,
+                ISSUED_AT,
+                None,
+                &mut state_builder,
+            )
+            .expect_report("Minting should succeed");
         let mut host = TestHost::new(state, state_builder);
 
-        // Set up the context
+        // Setup the context
         let mut ctx = TestReceiveContext::empty();
-        ctx.set_sender(SETTER_ADDRESS);
-
-        // Create a new_url
-        let new_url = "https://some.example/updated".to_string();
+        ctx.set_sender(ADDRESS_1);
 
-        // Set up the parameter.
-        let parameter = SetMetadataUrlParams {
-            url: new_url.clone(),
+        // and parameter.
+        let parameter = UpdateDataParams {
+            token_id: TOKEN_0,
+            data: vec![13],
+            commitment: None,
         };
         let parameter_bytes = to_bytes(&parameter);
         ctx.set_parameter(&parameter_bytes);
 
-        // Call the contract function.
-        let result = contract_state_set_metadata_url(&ctx, &mut host);
+        // Only the owner and the minter can update the data.
+        let err = contract_update_data(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            err,
+            Err(ContractError::Unauthorized),
+            "Error is expected to be Unauthorized"
+        );
 
-        // Check the result.
+        ctx.set_sender(ADDRESS_0);
+        let result = contract_update_data(&ctx, &mut host, &mut logger);
         claim!(result.is_ok(), "Results in rejection");
+        claim!(
+            logger
+                .logs
+                .contains(&to_bytes(&Event::UpdateData(UpdateDataEvent {
+                    token_id: TOKEN_0,
+                    address: ADDRESS_0,
+                }))),
+            "Expected an event for updating the data of TOKEN_0"
+        );
 
-        // Check the state.
-        let url = host.state().metadata_url.url.clone();
-        let hash = host.state().metadata_url.hash;
-        claim_eq!(url, new_url, "Expected url being updated");
-        claim_eq!(hash, None, "Expected hash being None");
+        let parameter = UpdateDataParams {
+            token_id: TOKEN_0,
+            data: vec![14],
+            commitment: None,
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+        ctx.set_sender(MINTER_ADDRESS);
+        let result = contract_update_data(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
 
-        // Check only the admin can update the metadata URL
-        ctx.set_sender(ADDRESS_0);
+        // Check the view
+        let parameter_bytes = to_bytes(&TOKEN_0);
+        ctx.set_parameter(&parameter_bytes);
+        let view = contract_view_data(&ctx, &host)
+            .expect_report("Viewing the token should succeed")
+            .expect_report("Token should exist");
+        claim_eq!(view.owner, ACCOUNT_0, "The owner should not change");
+        claim_eq!(view.data, vec![14], "The data should be replaced");
+
+        // The data of a revoked token cannot be updated.
+        host.state_mut()
+            .revoke(&TOKEN_0, RevocationReason::Fraud)
+            .expect_report("Revoking should succeed");
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+        let err = contract_update_data(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            err,
+            Err(ContractError::Custom(CustomContractError::TokenRevoked)),
+            "Updating a revoked token should fail"
+        );
+    }
 
-        // Call the contract function.
-        let err = contract_state_set_metadata_url(&ctx, &mut host);
+    /// Test that `viewSummary` reflects minting, revoking, burning and
+    /// recovering tokens.
+    #[concordium_test]
+    fn test_view_summary() {
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        state.grant_role(&REVOKER_ADDRESS, Roles::Revoker, &mut state_builder);
+        for owner in [ACCOUNT_0, ACCOUNT_0, ACCOUNT_1] {
+            state
+                .mint(
+                    owner,
+                    PLATFORM,
+                    vec![12],
+                    None,
+                    ISSUED_AT,
+                    None,
+                    &mut state_builder,
+                )
+                .expect_report("Minting should succeed");
+        }
+        state
+            .revoke(&TOKEN_0, RevocationReason::Fraud)
+            .expect_report("Revoking should succeed");
+        state
+            .burn(&TokenIdU64(2), &ADDRESS_1, BURNED_AT, &mut state_builder)
+            .expect_report("Burning should succeed");
+        let host = TestHost::new(state, state_builder);
+        let ctx = TestReceiveContext::empty();
 
-        // Check that ADDRESS_0 was not successful in updating the metadata url.
+        let summary = contract_view_summary(&ctx, &host).expect_report("Viewing should succeed");
+        claim_eq!(summary.next_token_id, 3, "Three tokens should be minted");
+        claim_eq!(summary.platforms.len(), 1, "One platform should be counted");
+        claim!(summary.platforms[0].0 == PLATFORM, "Wrong platform");
         claim_eq!(
-            err,
-            Err(ContractError::Unauthorized),
-            "Error is expected to be Unauthorized"
-        )
+            summary.platforms[0].1.active,
+            1,
+            "One token should be active"
+        );
+        claim_eq!(
+            summary.platforms[0].1.revoked,
+            2,
+            "Two tokens should be revoked"
+        );
+        claim_eq!(summary.owners, 1, "One account should hold a token");
+        claim_eq!(
+            summary.metadata_url.url,
+            TOKEN_METADATA_BASE_URL.to_string(),
+            "The metadata URL should be returned"
+        );
+        claim_eq!(summary.role_holders, 2, "Two addresses should have roles");
+
+        // Recovering moves the owner without changing the token counts.
+        let mut host = host;
+        let (state, builder) = host.state_and_builder();
+        state
+            .recover(&TokenIdU64(1), ACCOUNT_1, vec![13], None, builder)
+            .expect_report("Recovering should succeed");
+        let summary = contract_view_summary(&ctx, &host).expect_report("Viewing should succeed");
+        claim_eq!(
+            summary.platforms[0].1.active,
+            1,
+            "One token should be active"
+        );
+        claim_eq!(summary.owners, 1, "One account should hold a token");
+        claim_eq!(
+            host.state().owner_token_counts.get(&ACCOUNT_1).map(|c| *c),
+            Some(1),
+            "The new owner should be counted"
+        );
     }
 
-    /// Test minting, ensuring the new token is owned by the given address and
-    /// the appropriate events are logged.
+    /// Test paging over all tokens with `viewTokens`, with and without filters.
     #[concordium_test]
-    fn test_mint() {
-        // Setup the context
+    fn test_view_tokens() {
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        for owner in [ACCOUNT_0, ACCOUNT_1, ACCOUNT_0] {
+            state
+                .mint(
+                    owner,
+                    PLATFORM,
+                    vec![12],
+                    None,
+                    ISSUED_AT,
+                    None,
+                    &mut state_builder,
+                )
+                .expect_report("Minting should succeed");
+        }
+        state
+            .revoke(&TokenIdU64(1), RevocationReason::Fraud)
+            .expect_report("Revoking should succeed");
+        let host = TestHost::new(state, state_builder);
+
         let mut ctx = TestReceiveContext::empty();
-        ctx.set_sender(MINTER_ADDRESS);
 
-        // and parameter.
-        let parameter = MintParams {
-            owner: ACCOUNT_0,
-            platform: PLATFORM,
-            data: vec![12],
+        // First page.
+        let parameter = ViewTokensParams {
+            from: TOKEN_0,
+            limit: 2,
+            platform: None,
+            revoked: None,
         };
-
         let parameter_bytes = to_bytes(&parameter);
         ctx.set_parameter(&parameter_bytes);
-
-        let mut logger = TestLogger::init();
-        let mut state_builder = TestStateBuilder::new();
-
-        let metadata_url = concordium_cis2::MetadataUrl {
-            url: TOKEN_METADATA_BASE_URL.to_string(),
-            hash: None,
+        let page = contract_view_tokens(&ctx, &host).expect_report("Viewing tokens should succeed");
+        claim_eq!(
+            page.tokens.len(),
+            2,
+            "Expected two tokens in the first page"
+        );
+        claim_eq!(page.next, Some(TokenIdU64(2)), "Expected another page");
+
+        // Last page.
+        let parameter = ViewTokensParams {
+            from: TokenIdU64(2),
+            limit: 2,
+            platform: None,
+            revoked: None,
         };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+        let page = contract_view_tokens(&ctx, &host).expect_report("Viewing tokens should succeed");
+        claim_eq!(page.tokens.len(), 1, "Expected one token in the last page");
+        claim_eq!(
+            page.tokens[0].owner,
+            ACCOUNT_0,
+            "Expected the token of ACCOUNT_0"
+        );
+        claim_eq!(page.next, None, "Expected no more pages");
+
+        // Only revoked tokens.
+        let parameter = ViewTokensParams {
+            from: TOKEN_0,
+            limit: 10,
+            platform: Some(PLATFORM),
+            revoked: Some(true),
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+        let page = contract_view_tokens(&ctx, &host).expect_report("Viewing tokens should succeed");
+        claim_eq!(page.tokens.len(), 1, "Expected exactly one revoked token");
+        claim_eq!(
+            page.tokens[0].token_id,
+            TokenIdU64(1),
+            "Expected token 1 to be revoked"
+        );
+        claim_eq!(
+            page.tokens[0].revocation_reason,
+            Some(RevocationReason::Fraud),
+            "Expected the revocation reason"
+        );
 
-        let mut state = State::empty(&mut state_builder, metadata_url);
-        let url = state.build_token_metadata_url(&TOKEN_0, PLATFORM, false);
-        state.grant_role(&MINTER_ADDRESS, Roles::Minter, &mut state_builder);
-
-        let mut host = TestHost::new(state, state_builder);
+        // An empty page would never get to the next page.
+        let parameter = ViewTokensParams {
+            from: TOKEN_0,
+            limit: 0,
+            platform: None,
+            revoked: None,
+        };
+        let parameter_bytes = to_bytes(&parameter);
+        ctx.set_parameter(&parameter_bytes);
+        let err = contract_view_tokens(&ctx, &host);
+        claim_eq!(
+            err.map(|page| page.next),
+            Err(ContractError::Custom(CustomContractError::ZeroLimit)),
+            "Viewing an empty page should be rejected"
+        );
+    }
 
-        // Call the contract function.
-        let result: ContractResult<()> = contract_mint(&ctx, &mut host, &mut logger);
+    /// Test `isVerified` only reports tokens which are neither revoked nor
+    /// expired.
+    #[concordium_test]
+    fn test_is_verified() {
+        let mut state_builder = TestStateBuilder::new();
+        let mut state = initial_state(&mut state_builder);
+        // Token 0 expires, token 1 is revoked, and token 2 is active.
+        for valid_until in [Some(VALID_UNTIL), None, None] {
+            state
+                .mint(
+                    ACCOUNT_0,
+                    PLATFORM,
+                    vec![12],
+                    None,
+                    ISSUED_AT,
+                    valid_until,
+                    &mut state_builder,
+                )
+                .expect_report("Minting should succeed");
+        }
+        state
+            .revoke(&TokenIdU64(1), RevocationReason::Fraud)
+            .expect_report("Revoking should succeed");
+        let host = TestHost::new(state, state_builder);
 
-        // Check the result
-        claim!(result.is_ok(), "Results in rejection");
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_metadata_slot_time(VALID_UNTIL);
 
-        // Check the state
+        let parameter_bytes = to_bytes(&IsVerifiedQueryParams {
+            owner: ACCOUNT_0,
+            platform: Some(PLATFORM),
+            token_id: None,
+        });
+        ctx.set_parameter(&parameter_bytes);
         claim_eq!(
-            host.state().all_tokens.iter().count(),
-            1,
-            "Expected one token in the state."
+            contract_is_verified(&ctx, &host)
+                .expect_report("Query should succeed")
+                .0,
+            Some(TokenIdU64(2)),
+            "Only token 2 should be active"
         );
 
-        let balance0 = host
-            .state()
-            .balance(&TOKEN_0, &ADDRESS_0)
-            .expect_report("Token is expected to exist");
+        let parameter_bytes = to_bytes(&IsVerifiedQueryParams {
+            owner: ACCOUNT_0,
+            platform: None,
+            token_id: Some(TOKEN_0),
+        });
+        ctx.set_parameter(&parameter_bytes);
         claim_eq!(
-            balance0,
-            1.into(),
-            "Tokens should be owned by the given address 0"
+            contract_is_verified(&ctx, &host)
+                .expect_report("Query should succeed")
+                .0,
+            None,
+            "Token 0 should be expired"
         );
 
-        // Check the logs
-        claim!(
-            logger.logs.contains(&to_bytes(&Cis2Event::Mint(MintEvent {
-                owner: ADDRESS_0,
-                token_id: TOKEN_0,
-                amount: ContractTokenAmount::from(1),
-            }))),
-            "Expected an event for minting TOKEN_0"
+        let parameter_bytes = to_bytes(&IsVerifiedQueryParams {
+            owner: ACCOUNT_0,
+            platform: Some(Platform([0x02, 0x02])),
+            token_id: None,
+        });
+        ctx.set_parameter(&parameter_bytes);
+        claim_eq!(
+            contract_is_verified(&ctx, &host)
+                .expect_report("Query should succeed")
+                .0,
+            None,
+            "There should be no token of another platform"
         );
-        claim!(
-            logger.logs.contains(&to_bytes(
-                &Cis2Event::TokenMetadata::<_, ContractTokenAmount>(TokenMetadataEvent {
-                    token_id: TOKEN_0,
-                    metadata_url: MetadataUrl { url, hash: None },
-                })
-            )),
-            "Expected an event for token metadata for TOKEN_0"
+
+        let parameter_bytes = to_bytes(&IsVerifiedQueryParams {
+            owner: ACCOUNT_1,
+            platform: None,
+            token_id: None,
+        });
+        ctx.set_parameter(&parameter_bytes);
+        claim_eq!(
+            contract_is_verified(&ctx, &host)
+                .expect_report("Query should succeed")
+                .0,
+            None,
+            "ACCOUNT_1 should not be verified"
         );
     }
 
@@ -1652,4 +5316,75 @@ mod tests {
             "ADMIN_ADDRESS should have the roles Admin"
         );
     }
+
+    /// Test that the last admin cannot revoke their Admin role, and that the
+    /// role can be handed over in two steps.
+    #[concordium_test]
+    fn test_admin_handover() {
+        let mut logger = TestLogger::init();
+        let mut state_builder = TestStateBuilder::new();
+        let state = initial_state(&mut state_builder);
+        let mut host = TestHost::new(state, state_builder);
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(ADMIN_ADDRESS);
+        let parameter_bytes = to_bytes(&RevokeRoleParams {
+            address: ADMIN_ADDRESS,
+            role: Roles::Admin,
+        });
+        ctx.set_parameter(&parameter_bytes);
+        let err = contract_revoke_role(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            err,
+            Err(ContractError::Custom(CustomContractError::LastAdmin)),
+            "The last admin should not be removed"
+        );
+
+        let parameter_bytes = to_bytes(&ADDRESS_1);
+        ctx.set_parameter(&parameter_bytes);
+        contract_propose_admin(&ctx, &mut host, &mut logger)
+            .expect_report("Proposing an admin should succeed");
+        claim!(
+            !host.state().has_role(&ADDRESS_1, Roles::Admin),
+            "The role should only be granted once accepted"
+        );
+
+        // Only the proposed address can accept.
+        ctx.set_sender(ADDRESS_0);
+        let err = contract_accept_admin(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            err,
+            Err(ContractError::Unauthorized),
+            "Only the proposed address should be able to accept"
+        );
+
+        ctx.set_sender(ADDRESS_1);
+        contract_accept_admin(&ctx, &mut host, &mut logger)
+            .expect_report("Accepting should succeed");
+        claim!(
+            host.state().has_role(&ADDRESS_1, Roles::Admin),
+            "ADDRESS_1 should be admin"
+        );
+        claim!(
+            !host.state().has_role(&ADMIN_ADDRESS, Roles::Admin),
+            "ADMIN_ADDRESS should no longer be admin"
+        );
+        claim!(
+            logger
+                .logs
+                .contains(&to_bytes(&Event::AcceptAdmin(AdminHandoverEvent {
+                    proposer: ADMIN_ADDRESS,
+                    address: ADDRESS_1,
+                }))),
+            "Expected an event for accepting the handover"
+        );
+
+        // The proposal cannot be accepted twice.
+        let err = contract_accept_admin(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            err,
+            Err(ContractError::Unauthorized),
+            "The proposal should be consumed"
+        );
+    }
 }
@@ -83,6 +83,7 @@ function App() {
               <NewProofView />
             } />
             <Route path="/view/:id/:decryptionKey" element={<ViewProofView/>} />
+            <Route path="/v/:link" element={<ViewProofView/>} />
             <Route path="/v/:id/:decryptionKey" element={<ViewProofView/>} />
             <Route path="/my-proof/:id/:decryptionKey" element={<ViewMyProofView/>} />          
             <Route path="*" element={<Navigate to="/home"/>} />
@@ -91,6 +92,7 @@ function App() {
         <Routes>
           <Route path="/home" element={<MobileHomeView />} />
           <Route path="/view/:id/:decryptionKey" element={<MobileViewProofView/>} />
+          <Route path="/v/:link" element={<MobileViewProofView/>} />
           <Route path="/v/:id/:decryptionKey" element={<MobileViewProofView/>} />          
           <Route path="*" element={<Navigate to="/home"/>} />
         </Routes>
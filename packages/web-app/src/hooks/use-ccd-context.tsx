@@ -368,7 +368,7 @@ export const CCDContextProvider: React.FC<{ children: ReactElement }> = ({ child
     const {
       statement
     } = await fetch(
-      serviceUrl(`/proof/statement`, ''),
+      serviceUrl(`/proof/statement`, { platform }),
       { method: 'GET', headers: { 'Content-Type': 'application/json' } }
     )
       .then(res => res.json());
@@ -378,6 +378,7 @@ export const CCDContextProvider: React.FC<{ children: ReactElement }> = ({ child
     } = await fetch(serviceUrl(`/proof/challenge`, {
       platform,
       userData,
+      account,
     }),
       {
         method: 'GET',
@@ -402,7 +403,7 @@ export const CCDContextProvider: React.FC<{ children: ReactElement }> = ({ child
       const response = await fetch(serviceUrl(`/proof/verify`), {
         method: 'POST',
         headers: { 'content-type': 'application/json' },
-        body: JSON.stringify({ challenge, proof, account }),
+        body: JSON.stringify({ challenge, proof, account, platform, userData }),
       });
 
       const code = response.status;
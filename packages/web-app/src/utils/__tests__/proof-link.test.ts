@@ -0,0 +1,33 @@
+import { decodeProofLink, resolveProofParams } from "../proof-link";
+
+// The key 0xfb repeated 32 times, as encoded by the backend.
+const key = '+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/s=';
+const link = '1-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_sFySWL68GP';
+
+describe('decodeProofLink', () => {
+    test('decodes links with the token id', () => {
+        expect(decodeProofLink(link)).toEqual({ id: '1000000', decryptionKey: key });
+        expect(decodeProofLink('1-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_vyXoff1')).toEqual({ id: '0', decryptionKey: key });
+    });
+
+    test('decodes links without the token id', () => {
+        const withoutId = link.slice(0, 49);
+        expect(decodeProofLink(withoutId, '1000000')).toEqual({ id: '1000000', decryptionKey: key });
+        expect(decodeProofLink(withoutId)).toBeNull();
+        expect(decodeProofLink(withoutId, '1000001')).toBeNull();
+    });
+
+    test('rejects links with a wrong checksum or version', () => {
+        expect(decodeProofLink(link.slice(0, 5) + 'A' + link.slice(6))).toBeNull();
+        expect(decodeProofLink('2' + link.slice(1))).toBeNull();
+        expect(decodeProofLink(key)).toBeNull();
+    });
+});
+
+describe('resolveProofParams', () => {
+    test('resolves all link formats', () => {
+        expect(resolveProofParams({ link })).toEqual({ id: '1000000', decryptionKey: key });
+        expect(resolveProofParams({ id: '1000000', decryptionKey: link.slice(0, 49) })).toEqual({ id: '1000000', decryptionKey: key });
+        expect(resolveProofParams({ id: '1000000', decryptionKey: encodeURIComponent(key) })).toEqual({ id: '1000000', decryptionKey: key });
+    });
+});
@@ -3,3 +3,4 @@ export * from './set-all';
 export * from './timeout';
 export * from './convert';
 export * from './sleep';
+export * from './proof-link';
@@ -0,0 +1,96 @@
+import CryptoJS from 'crypto-js';
+
+// Compact links to proofs, as produced by the backend:
+// `1 || base64url(key || checksum) || base58(token id)`, where the checksum is
+// the first 4 bytes of SHA-256(version || token id as big-endian u64 || key).
+// The token id may be left out, in which case it is given by the preceding
+// path segment as in `/v/:id/:link`.
+
+const LINK_VERSION_1 = '1';
+const ENCODED_KEY_LENGTH = 48;
+const KEY_LENGTH = 32;
+const CHECKSUM_LENGTH = 4;
+const BASE58_ALPHABET = '123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz';
+const MAX_TOKEN_ID = (BigInt(1) << BigInt(64)) - BigInt(1);
+
+function bytesToHex(bytes: number[]): string {
+    return bytes.map(b => b.toString(16).padStart(2, '0')).join('');
+}
+
+function decodeBase64Url(value: string): number[] | null {
+    try {
+        const base64 = value.replace(/-/g, '+').replace(/_/g, '/');
+        return Array.from(atob(base64), c => c.charCodeAt(0));
+    } catch {
+        return null;
+    }
+}
+
+// Decode a base58 encoded token id. Like the backend, it must be encoded in
+// 1 to 8 bytes.
+function decodeTokenId(value: string): bigint | null {
+    let id = BigInt(0);
+    let leadingZeros = 0;
+    for (const c of value) {
+        const digit = BASE58_ALPHABET.indexOf(c);
+        if (digit < 0) {
+            return null;
+        }
+        if (digit === 0 && id === BigInt(0)) {
+            leadingZeros++;
+        }
+        id = id * BigInt(58) + BigInt(digit);
+    }
+    const length = leadingZeros + (id === BigInt(0) ? 0 : Math.ceil(id.toString(16).length / 2));
+    if (length === 0 || length > 8 || id > MAX_TOKEN_ID) {
+        return null;
+    }
+    return id;
+}
+
+function checksum(id: bigint, key: number[]): string {
+    const message = bytesToHex([LINK_VERSION_1.charCodeAt(0)]) + id.toString(16).padStart(16, '0') + bytesToHex(key);
+    return CryptoJS.SHA256(CryptoJS.enc.Hex.parse(message)).toString(CryptoJS.enc.Hex).slice(0, 2 * CHECKSUM_LENGTH);
+}
+
+// Decode a compact link to a proof. If the link does not contain the token id
+// `id` is used instead. Returns the token id and the decryption key in the
+// base64 encoding used by the backend, or null if the link is malformed, of an
+// unknown version, or its checksum does not match.
+export function decodeProofLink(link: string, id?: string): { id: string, decryptionKey: string } | null {
+    if (!link?.startsWith(LINK_VERSION_1) || link.length < 1 + ENCODED_KEY_LENGTH) {
+        return null;
+    }
+    const keyAndChecksum = decodeBase64Url(link.slice(1, 1 + ENCODED_KEY_LENGTH));
+    if (!keyAndChecksum || keyAndChecksum.length !== KEY_LENGTH + CHECKSUM_LENGTH) {
+        return null;
+    }
+    const encodedId = link.slice(1 + ENCODED_KEY_LENGTH);
+    let tokenId: bigint | null;
+    if (encodedId) {
+        tokenId = decodeTokenId(encodedId);
+    } else {
+        tokenId = /^[0-9]+$/.test(id ?? '') && BigInt(id) <= MAX_TOKEN_ID ? BigInt(id) : null;
+    }
+    if (tokenId === null) {
+        return null;
+    }
+    const key = keyAndChecksum.slice(0, KEY_LENGTH);
+    if (checksum(tokenId, key) !== bytesToHex(keyAndChecksum.slice(KEY_LENGTH))) {
+        return null;
+    }
+    return {
+        id: tokenId.toString(),
+        decryptionKey: btoa(String.fromCharCode(...key)),
+    };
+}
+
+// Resolve the token id and decryption key from the parameters of the routes
+// `/v/:link`, `/v/:id/:link` and `/v/:id/:decryptionKey`.
+export function resolveProofParams({ id, link, decryptionKey }: { id?: string, link?: string, decryptionKey?: string }): { id?: string, decryptionKey?: string } {
+    if (link) {
+        return decodeProofLink(link) ?? {};
+    }
+    const decoded = decryptionKey ? decodeURIComponent(decryptionKey) : undefined;
+    return decodeProofLink(decoded, id) ?? { id, decryptionKey: decoded };
+}
@@ -7,14 +7,13 @@ import { paperSX } from "src/themes";
 import { ViewProof } from "./view-proof";
 import { useEffect } from "react";
 import { useAnalytics } from "src/hooks/use-analytics";
+import { resolveProofParams } from "src/utils";
 
 export const ViewProofView = ({ }) => {
     const {
         id,
-        decryptionKey: decryptionKeyEncoded
-    } = useParams();
-
-    const decryptionKey = decodeURIComponent(decryptionKeyEncoded);
+        decryptionKey,
+    } = resolveProofParams(useParams());
 
     const theme = useTheme();
     const ltsm = useMediaQuery(theme.breakpoints.down('sm'));
@@ -16,13 +16,13 @@ import { AspectBox } from "src/views/desktop/new-proof/aspect-box";
 import { MobileHeader } from './mobile-header';
 import { Button } from 'src/components';
 import { useAnalytics } from "src/hooks/use-analytics";
+import { resolveProofParams } from "src/utils";
 
 export const MobileViewProofView = ({ }) => {
     const {
         id,
-        decryptionKey: decryptionKeyEncoded
-    } = useParams();
-    const decryptionKey = decodeURIComponent(decryptionKeyEncoded);
+        decryptionKey,
+    } = resolveProofParams(useParams());
     const [profileFirstName, setFirstName] = useState('');
     const [profileSurname, setSurname] = useState('');
     const [platform, setPlatform] = useState('');